@@ -31,6 +31,10 @@ platformtree!(
         led2@20 { direction = "out"; }
       }
     }
+
+    watchdog {
+      mode = "disabled";
+    }
   }
 
   os {
@@ -0,0 +1,42 @@
+#![feature(phase)]
+#![crate_type="staticlib"]
+#![no_std]
+
+extern crate core;
+extern crate zinc;
+#[phase(plugin)] extern crate macro_platformtree;
+
+platformtree_file!("boards/mbed_lpc1768.pt",
+  extend mcu {
+    timer {
+      timer@1 {
+        counter = 25;
+        divisor = 4;
+      }
+    }
+  }
+
+  os {
+    single_task {
+      loop = "run";
+      args {
+        timer = &timer;
+        led1 = &led1;
+        led2 = &led2;
+      }
+    }
+  }
+);
+
+fn run(args: &pt::run_args) {
+  use zinc::hal::pin::Gpio;
+  use zinc::hal::timer::Timer;
+
+  args.led1.set_high();
+  args.led2.set_low();
+  args.timer.wait(1);
+
+  args.led1.set_low();
+  args.led2.set_high();
+  args.timer.wait(1);
+}
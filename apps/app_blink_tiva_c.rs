@@ -31,6 +31,10 @@ platformtree!(
         mode = "periodic";
       }
     }
+
+    watchdog {
+      mode = "disabled";
+    }
   }
 
   os {
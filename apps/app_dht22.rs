@@ -43,6 +43,10 @@ platformtree!(
         dht_pin@4;
       }
     }
+
+    watchdog {
+      mode = "disabled";
+    }
   }
 
   drivers {
@@ -17,6 +17,10 @@ platformtree!(
         divisor = 4;
       }
     }
+
+    watchdog {
+      mode = "disabled";
+    }
   }
 
   os {
@@ -41,6 +41,10 @@ platformtree!(
         mode = "periodic";
       }
     }
+
+    watchdog {
+      mode = "disabled";
+    }
   }
 
   os {
@@ -10,7 +10,7 @@ platformtree!(
   lpc17xx@mcu {
     clock {
       source = "main-oscillator";
-      source_frequency = 12_000_000;
+      source_frequency = 12MHz;
       pll {
         m = 50;
         n = 3;
@@ -27,7 +27,7 @@ platformtree!(
 
     uart {
       uart@0 {
-        baud_rate = 115200;
+        baud_rate = 115200baud;
         mode = "8N1";
         tx = &uart_tx;
         rx = &uart_rx;
@@ -43,6 +43,10 @@ platformtree!(
         led4@23 { direction = "out"; }
       }
     }
+
+    watchdog {
+      mode = "disabled";
+    }
   }
 
   os {
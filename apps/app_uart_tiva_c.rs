@@ -49,6 +49,9 @@ platformtree!(
       }
     }
 
+    watchdog {
+      mode = "disabled";
+    }
   }
 
   os {
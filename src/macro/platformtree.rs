@@ -31,14 +31,15 @@ use rustc::plugin::Registry;
 use syntax::ast;
 use syntax::codemap::DUMMY_SP;
 use syntax::codemap::Span;
-use syntax::ext::base::{ExtCtxt, MacResult, Modifier};
+use syntax::ext::base::{ExtCtxt, MacResult, DummyResult, Modifier};
 use syntax::ext::build::AstBuilder;
 use syntax::owned_slice::OwnedSlice;
+use syntax::parse::token;
 use syntax::print::pprust;
 use syntax::util::small_vector::SmallVector;
 use syntax::ptr::P;
 
-use platformtree::parser::Parser;
+use platformtree::parser::{Parser, read_file_tts};
 use platformtree::builder::Builder;
 use platformtree::builder::meta_args::ToTyHash;
 
@@ -46,17 +47,62 @@ use platformtree::builder::meta_args::ToTyHash;
 pub fn plugin_registrar(reg: &mut Registry) {
   reg.register_macro("platformtree", macro_platformtree);
   reg.register_macro("platformtree_verbose", macro_platformtree_verbose);
+  reg.register_macro("platformtree_file", macro_platformtree_file);
   reg.register_syntax_extension(syntax::parse::token::intern("zinc_task"),
       Modifier(box macro_zinc_task));
 }
 
-pub fn macro_platformtree(cx: &mut ExtCtxt, _: Span, tts: &[ast::TokenTree])
+pub fn macro_platformtree(cx: &mut ExtCtxt, sp: Span, tts: &[ast::TokenTree])
     -> Box<MacResult+'static> {
-  let pt = Parser::new(cx, tts).parse_platformtree();
-  let items = Builder::build(cx, pt.unwrap())
-    .expect(format!("Unexpected failure on {}", line!()).as_slice())
-    .emit_items(cx);
-  MacItems::new(items)
+  let maybe_pt = Parser::new(cx, tts).parse_platformtree();
+  let builder = match maybe_pt.and_then(|pt| Builder::build(cx, pt)) {
+    Some(builder) => builder,
+    // all the errors are already reported by parser and builder
+    None => return DummyResult::any(sp),
+  };
+  MacItems::new(builder.emit_items(cx))
+}
+
+/// Expands a platform tree read from a file.
+///
+/// The file name is resolved relative to the source file that invokes the
+/// macro. Optionally, it can be followed by a comma and an inline platform tree
+/// which is parsed as if it was appended to the file, e.g.
+/// `platformtree_file!("boards/mbed.pt", extend mcu { ... } os { ... })`.
+pub fn macro_platformtree_file(cx: &mut ExtCtxt, sp: Span,
+    tts: &[ast::TokenTree]) -> Box<MacResult+'static> {
+  let file = match tts.get(0) {
+    Some(&ast::TtToken(_, token::Literal(token::Lit::Str_(name), _))) =>
+        name.as_str().to_string(),
+    _ => {
+      cx.span_err(sp, "platformtree_file! expects a string literal file name");
+      return DummyResult::any(sp);
+    }
+  };
+
+  let inline_tts = match tts.get(1) {
+    None => tts.slice_from(1),
+    Some(&ast::TtToken(_, token::Comma)) => tts.slice_from(2),
+    Some(_) => {
+      cx.span_err(sp, "expected `,` after the file name");
+      return DummyResult::any(sp);
+    }
+  };
+
+  let mut path = Path::new(cx.codemap().span_to_filename(sp));
+  path.pop();
+  path.push(file);
+
+  let mut all_tts = match read_file_tts(cx.parse_sess(), &path) {
+    Ok(file_tts) => file_tts,
+    Err(msg) => {
+      cx.span_err(sp, msg.as_slice());
+      return DummyResult::any(sp);
+    }
+  };
+  all_tts.push_all(inline_tts);
+
+  macro_platformtree(cx, sp, all_tts.as_slice())
 }
 
 pub fn macro_platformtree_verbose(cx: &mut ExtCtxt, sp: Span,
@@ -13,13 +13,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::ops::DerefMut;
 use syntax::abi;
 use syntax::ast::TokenTree;
 use syntax::ast;
 use syntax::ast_util::empty_generics;
-use syntax::codemap::{Span, DUMMY_SP};
+use syntax::codemap::{respan, Span, DUMMY_SP};
 use syntax::ext::base::ExtCtxt;
 use syntax::ext::build::AstBuilder;
 use syntax::ext::quote::rt::{ToTokens, ExtParseUtils};
@@ -35,6 +36,11 @@ pub mod meta_args;
 pub struct Builder {
   main_stmts: Vec<P<ast::Stmt>>,
   type_items: Vec<P<ast::Item>>,
+  isr_items: Vec<P<ast::Item>>,
+  refresh_stmts: Vec<P<ast::Stmt>>,
+  bound_isrs: HashMap<String, Span>,
+  materialized: HashSet<String>,
+  board_entry: Option<String>,
   pt: Rc<node::PlatformTree>,
 }
 
@@ -65,9 +71,24 @@ impl Builder {
       Builder::walk_mutate(&mut builder, cx, sub);
     }
 
+    let mut valid = true;
+    for sub in pt.nodes().iter() {
+      if !Builder::walk_validate(cx, sub) {
+        valid = false;
+      }
+    }
+    if !valid {
+      return None;
+    }
+
     let base_node = pt.get_by_path("mcu").and_then(|mcu|{mcu.get_by_path("clock")});
     match base_node {
-      Some(node) => Builder::walk_materialize(&mut builder, cx, node),
+      Some(node) => {
+        Builder::walk_materialize(&mut builder, cx, node);
+        if !builder.verify_materialized(cx) {
+          return None;
+        }
+      },
       None => {
         cx.parse_sess().span_diagnostic.span_err(DUMMY_SP,
             "root node `mcu::clock` must be present");
@@ -87,8 +108,24 @@ impl Builder {
     }
   }
 
-  // FIXME(farcaller): verify that all nodes have been materialized
+  /// Validates the node and all of its subnodes against their schemas. Doesn't
+  /// stop on first failure, so that all the errors are reported at once.
+  fn walk_validate(cx: &mut ExtCtxt, node: &Rc<node::Node>) -> bool {
+    let mut ok = true;
+    let maybe_schema = node.schema.get();
+    if maybe_schema.is_some() {
+      ok = node.validate(cx, &maybe_schema.unwrap()());
+    }
+    for sub in node.subnodes().iter() {
+      if !Builder::walk_validate(cx, sub) {
+        ok = false;
+      }
+    }
+    ok
+  }
+
   fn walk_materialize(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+    builder.materialized.insert(node.full_path());
     let maybe_mat = node.materializer.get();
     if maybe_mat.is_some() {
       maybe_mat.unwrap()(builder, cx, node.clone());
@@ -120,10 +157,111 @@ impl Builder {
     }
   }
 
+  /// Reports a parser error for every node that has a materializer or pending
+  /// dependencies but was not materialized. Returns false if any such node was
+  /// found.
+  ///
+  /// Nodes that form a dependency cycle are reported once per cycle, listing
+  /// paths of all the nodes involved.
+  fn verify_materialized(&self, cx: &mut ExtCtxt) -> bool {
+    let mut pending = vec!();
+    for sub in self.pt.nodes().iter() {
+      self.collect_pending(sub, &mut pending);
+    }
+
+    let mut reported = HashSet::new();
+    for node in pending.iter() {
+      if reported.contains(&node.full_path()) {
+        continue;
+      }
+
+      match Builder::find_cycle(node) {
+        Some(cycle) => {
+          let mut paths: Vec<String> = cycle.iter().map(|n| {
+            format!("`{}`", n.full_path())
+          }).collect();
+          for n in cycle.iter() {
+            reported.insert(n.full_path());
+          }
+          paths.push(format!("`{}`", node.full_path()));
+          cx.parse_sess().span_diagnostic.span_err(node.name_span,
+              format!("dependency cycle detected: {}", paths.connect(" -> "))
+              .as_slice());
+        },
+        None => {
+          let deps: Vec<String> = node.depends_on.borrow().iter().map(|dep| {
+            format!("`{}`", dep.upgrade().unwrap().full_path())
+          }).collect();
+          let msg = if deps.len() == 0 {
+            format!("node `{}` was not materialized as it doesn't depend \
+                     on any other node", node.full_path())
+          } else {
+            format!("node `{}` was not materialized, unresolved \
+                     dependencies: {}", node.full_path(), deps.connect(", "))
+          };
+          cx.parse_sess().span_diagnostic.span_err(node.name_span,
+              msg.as_slice());
+        },
+      }
+    }
+
+    pending.len() == 0
+  }
+
+  /// Collects the node and its subnodes that are expected to be materialized,
+  /// but weren't.
+  fn collect_pending(&self, node: &Rc<node::Node>,
+      pending: &mut Vec<Rc<node::Node>>) {
+    let expected = node.materializer.get().is_some() ||
+        node.depends_on.borrow().len() > 0;
+    if expected && !self.materialized.contains(&node.full_path()) {
+      pending.push(node.clone());
+    }
+    for sub in node.subnodes().iter() {
+      self.collect_pending(sub, pending);
+    }
+  }
+
+  /// Returns a chain of dependencies leading from the node back to itself, if
+  /// there's one.
+  fn find_cycle(node: &Rc<node::Node>) -> Option<Vec<Rc<node::Node>>> {
+    let mut chain = vec!(node.clone());
+    let mut visited = HashSet::new();
+    if Builder::walk_cycle(node, node, &mut chain, &mut visited) {
+      Some(chain)
+    } else {
+      None
+    }
+  }
+
+  fn walk_cycle(start: &Rc<node::Node>, current: &Rc<node::Node>,
+      chain: &mut Vec<Rc<node::Node>>, visited: &mut HashSet<String>) -> bool {
+    for weak_dep in current.depends_on.borrow().iter() {
+      let dep = weak_dep.upgrade().unwrap();
+      if dep == *start {
+        return true;
+      }
+      if !visited.insert(dep.full_path()) {
+        continue;
+      }
+      chain.push(dep.clone());
+      if Builder::walk_cycle(start, &dep, chain, visited) {
+        return true;
+      }
+      chain.pop();
+    }
+    false
+  }
+
   pub fn new(pt: Rc<node::PlatformTree>) -> Builder {
     Builder {
       main_stmts: Vec::new(),
       type_items: Vec::new(),
+      isr_items: Vec::new(),
+      refresh_stmts: Vec::new(),
+      bound_isrs: HashMap::new(),
+      materialized: HashSet::new(),
+      board_entry: None,
       pt: pt,
     }
   }
@@ -144,6 +282,76 @@ impl Builder {
     self.type_items.push(item);
   }
 
+  /// Adds a statement to `pt::refresh()`, that is called on every iteration of
+  /// the OS task loop, e.g. to feed the watchdog.
+  pub fn add_refresh_statement(&mut self, stmt: P<ast::Stmt>) {
+    self.refresh_stmts.push(stmt);
+  }
+
+  /// Returns true if `pt::refresh()` has anything to do.
+  pub fn has_refresh_statements(&self) -> bool {
+    self.refresh_stmts.len() > 0
+  }
+
+  /// Makes the builder emit a `pt::Board` struct and an `init()` function. The
+  /// generated main() calls `entry` with the initialized board.
+  pub fn set_board_entry(&mut self, entry: String) {
+    self.board_entry = Some(entry);
+  }
+
+  /// Binds a handler function to the interrupt.
+  ///
+  /// Generates the ISR named `isr` (as referenced from the MCU vector table)
+  /// that calls the handler, and main() statements that set the interrupt
+  /// priority, if any, and enable the interrupt in NVIC. `nvic` is the path to
+  /// the NVIC module of the MCU core, e.g. `zinc::hal::cortex_m3::nvic`.
+  ///
+  /// Reports a parser error and returns false if the interrupt is already
+  /// bound.
+  pub fn bind_interrupt(&mut self, cx: &ExtCtxt, nvic: &str, isr: &str,
+      irqn: uint, priority: Option<u8>, handler: &str, span: Span) -> bool {
+    match self.bound_isrs.get(&isr.to_string()) {
+      Some(old_span) => {
+        cx.parse_sess().span_diagnostic.span_err(span,
+            format!("interrupt `{}` is already bound", isr).as_slice());
+        cx.parse_sess().span_diagnostic.span_err(*old_span,
+            "previously bound here");
+        return false;
+      },
+      None => (),
+    }
+    self.bound_isrs.insert(isr.to_string(), span);
+
+    let isr_name = TokenString(isr.to_string());
+    let handler_name = TokenString(handler.to_string());
+    let nvic_path = TokenString(nvic.to_string());
+
+    // the explicit fn() binding makes rustc report handlers with a wrong
+    // signature
+    let isr_item = quote_item!(&*cx,
+        #[no_mangle]
+        #[allow(dead_code)]
+        pub unsafe extern fn $isr_name() {
+          let handler: fn() = $handler_name;
+          handler();
+        }
+    ).unwrap();
+    self.isr_items.push(isr_item);
+
+    match priority {
+      Some(prio) => {
+        self.add_main_statement(quote_stmt!(&*cx,
+            $nvic_path::set_priority($irqn, $prio);
+        ));
+      },
+      None => (),
+    }
+    self.add_main_statement(quote_stmt!(&*cx,
+        $nvic_path::enable_irq($irqn);
+    ));
+    true
+  }
+
   fn emit_main(&self, cx: &ExtCtxt) -> P<ast::Item> {
     // init stack
     let init_stack_stmt = cx.stmt_expr(quote_expr!(&*cx,
@@ -156,18 +364,116 @@ impl Builder {
     ));
 
     let mut stmts = vec!(init_stack_stmt, init_data_stmt);
-    stmts.push_all(self.main_stmts.as_slice());
+    match self.board_entry {
+      Some(ref entry) => {
+        let entry_ident = cx.ident_of(entry.as_slice());
+        stmts.push(quote_stmt!(&*cx, $entry_ident(init());));
+      },
+      None => stmts.push_all(self.main_stmts.as_slice()),
+    }
 
     let body = cx.block(DUMMY_SP, stmts, None);
 
+    self.item_fn(cx, DUMMY_SP, "main", &[self.allow_unused_variables(cx)], body)
+  }
+
+  /// Returns the typed fields of the board struct, one for every named node
+  /// that generates an object. Nodes that borrow other nodes can't be moved
+  /// into the struct alongside them and are reported as errors.
+  fn board_fields(&self, cx: &ExtCtxt) -> Vec<(ast::Ident, P<ast::Ty>)> {
+    let mut fields = vec!();
+    for node in self.pt.named_nodes().iter() {
+      let type_name = match node.type_name() {
+        Some(name) => name,
+        None => continue,
+      };
+      let name = node.name.clone().unwrap();
+      if node.type_params().len() > 0 {
+        cx.parse_sess().span_diagnostic.span_err(node.name_span, format!(
+            "node `{}` borrows other nodes and cannot be stored in the board",
+            name).as_slice());
+        continue;
+      }
+      let ty = cx.ty_path(os::type_name_as_path(
+          cx, type_name.as_slice(), vec!()));
+      fields.push((cx.ident_of(name.as_slice()), ty));
+    }
+    fields
+  }
+
+  fn emit_board_struct(&self, cx: &ExtCtxt,
+      fields: &Vec<(ast::Ident, P<ast::Ty>)>) -> P<ast::Item> {
+    let struct_fields = fields.iter().map(|&(ident, ref ty)| {
+      respan(DUMMY_SP, ast::StructField_ {
+        kind: ast::NamedField(ident, ast::Public),
+        id: ast::DUMMY_NODE_ID,
+        ty: ty.clone(),
+        attrs: vec!(),
+      })
+    }).collect();
+
+    P(ast::Item {
+      ident: cx.ident_of("Board"),
+      attrs: vec!(),
+      id: ast::DUMMY_NODE_ID,
+      node: ast::ItemStruct(P(ast::StructDef {
+        fields: struct_fields,
+        ctor_id: None,
+      }), empty_generics()),
+      vis: ast::Public,
+      span: DUMMY_SP,
+    })
+  }
+
+  fn emit_board_init(&self, cx: &ExtCtxt,
+      fields: &Vec<(ast::Ident, P<ast::Ty>)>) -> P<ast::Item> {
+    let board_path = cx.path(DUMMY_SP,
+        vec!(cx.ident_of("pt"), cx.ident_of("Board")));
+    let expr_fields = fields.iter().map(|&(ident, _)| {
+      cx.field_imm(DUMMY_SP, ident, cx.expr_ident(DUMMY_SP, ident))
+    }).collect();
+    let board_expr = cx.expr_struct(DUMMY_SP, board_path.clone(), expr_fields);
+    let body = cx.block(DUMMY_SP, self.main_stmts.clone(), Some(board_expr));
+
+    P(ast::Item {
+      ident: cx.ident_of("init"),
+      attrs: vec!(self.allow_unused_variables(cx)),
+      id: ast::DUMMY_NODE_ID,
+      node: ast::ItemFn(
+          cx.fn_decl(Vec::new(), cx.ty_path(board_path)),
+          ast::Unsafety::Unsafe,
+          abi::Rust,
+          empty_generics(),
+          body),
+      vis: ast::Public,
+      span: DUMMY_SP,
+    })
+  }
+
+  fn emit_refresh(&self, cx: &ExtCtxt) -> P<ast::Item> {
+    let body = cx.block(DUMMY_SP, self.refresh_stmts.clone(), None);
+    P(ast::Item {
+      ident: cx.ident_of("refresh"),
+      attrs: vec!(),
+      id: ast::DUMMY_NODE_ID,
+      node: ast::ItemFn(
+          cx.fn_decl(Vec::new(), cx.ty(DUMMY_SP, ast::Ty_::TyTup(Vec::new()))),
+          ast::Unsafety::Normal,
+          abi::Rust,
+          empty_generics(),
+          body),
+      vis: ast::Public,
+      span: DUMMY_SP,
+    })
+  }
+
+  fn allow_unused_variables(&self, cx: &ExtCtxt) -> ast::Attribute {
     let unused_variables = cx.meta_word(DUMMY_SP,
         InternedString::new("unused_variables"));
     let allow = cx.meta_list(
         DUMMY_SP,
         InternedString::new("allow"), vec!(unused_variables));
-    let allow_noncamel = cx.attribute(DUMMY_SP, allow);
-
-    self.item_fn(cx, DUMMY_SP, "main", &[allow_noncamel], body)
+    cx.attribute(DUMMY_SP, allow)
   }
 
   fn emit_morestack(&self, cx: &ExtCtxt) -> P<ast::Item> {
@@ -190,14 +496,29 @@ impl Builder {
     let allow_noncamel = cx.attribute(DUMMY_SP, allow);
     let use_zinc = cx.view_use_simple(DUMMY_SP, ast::Inherited, cx.path_ident(
         DUMMY_SP, cx.ident_of("zinc")));
+    let mut type_items = self.type_items.clone();
+    let mut board_items = vec!();
+    if self.has_refresh_statements() {
+      type_items.push(self.emit_refresh(cx));
+    }
+    if self.board_entry.is_some() {
+      let fields = self.board_fields(cx);
+      type_items.push(self.emit_board_struct(cx, &fields));
+      board_items.push(self.emit_board_init(cx, &fields));
+    }
     let pt_mod_item = cx.item_mod(DUMMY_SP, DUMMY_SP, cx.ident_of("pt"),
-        vec!(allow_noncamel), vec!(use_zinc), self.type_items.clone());
+        vec!(allow_noncamel), vec!(use_zinc), type_items.clone());
 
-    if self.type_items.len() > 0 {
-      vec!(pt_mod_item, self.emit_main(cx), self.emit_morestack(cx))
+    let mut items = if type_items.len() > 0 {
+      vec!(pt_mod_item)
     } else {
-      vec!(self.emit_main(cx), self.emit_morestack(cx))
-    }
+      vec!()
+    };
+    items.push_all(board_items.as_slice());
+    items.push(self.emit_main(cx));
+    items.push(self.emit_morestack(cx));
+    items.push_all(self.isr_items.as_slice());
+    items
   }
 
   fn item_fn(&self, cx: &ExtCtxt, span: Span, name: &str,
@@ -243,7 +564,25 @@ pub fn add_node_dependency(node: &Rc<node::Node>, dep: &Rc<node::Node>) {
 
 #[cfg(test)]
 mod test {
-  use test_helpers::fails_to_build;
+  use std::ops::Deref;
+  use std::rc::Rc;
+  use syntax::codemap::DUMMY_SP;
+  use syntax::ext::base::ExtCtxt;
+  use syntax::ext::build::AstBuilder;
+
+  use builder::Builder;
+  use node;
+  use test_helpers::{assert_equal_items, assert_equal_source, fails_to_build,
+      with_parsed};
+  use super::add_node_dependency;
+
+  fn noop(_: &mut Builder, _: &mut ExtCtxt, _: Rc<node::Node>) {}
+
+  fn set_noop_materializer(pt: &Rc<node::PlatformTree>, name: &str) -> Rc<node::Node> {
+    let node = pt.get_by_name(name).unwrap();
+    node.materializer.set(Some(noop as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+    node
+  }
 
   #[test]
   fn fails_to_parse_pt_with_unknown_root_node() {
@@ -254,4 +593,214 @@ mod test {
   fn fails_to_parse_pt_with_unknown_mcu() {
     fails_to_build("mcu@bad {}");
   }
+
+  #[test]
+  fn fails_to_build_pt_with_unmaterialized_nodes() {
+    with_parsed("
+      mcu@lpc17xx {
+        clock {
+          source = \"main-oscillator\";
+          source_frequency = 12_000_000;
+          pll {
+            m = 50;
+            n = 3;
+            divisor = 4;
+          }
+        }
+        watchdog {
+          mode = \"disabled\";
+        }
+        timer {}
+      }", |cx, failed, pt| {
+      assert!(Builder::build(cx, pt).is_none());
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn binds_interrupt_handler() {
+    with_parsed("node;", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      assert!(builder.bind_interrupt(cx, "zinc::hal::cortex_m3::nvic",
+          "isr_uart_0", 5, Some(16), "handle_rx", DUMMY_SP) == true);
+      assert!(unsafe{*failed} == false);
+      assert!(builder.isr_items.len() == 1);
+      assert!(builder.main_stmts.len() == 2);
+
+      assert_equal_items(builder.isr_items[0].deref(),
+          "#[no_mangle]
+          #[allow(dead_code)]
+          pub unsafe extern \"C\" fn isr_uart_0() {
+            let handler: fn() = handle_rx;
+            handler();
+          }");
+      assert_equal_source(builder.main_stmts[0].deref(),
+          "zinc::hal::cortex_m3::nvic::set_priority(5u, 16u8);");
+      assert_equal_source(builder.main_stmts[1].deref(),
+          "zinc::hal::cortex_m3::nvic::enable_irq(5u);");
+    });
+  }
+
+  #[test]
+  fn fails_to_bind_interrupt_twice() {
+    with_parsed("node;", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      assert!(builder.bind_interrupt(cx, "zinc::hal::cortex_m3::nvic",
+          "isr_uart_0", 5, None, "handle_rx", DUMMY_SP) == true);
+      assert!(builder.bind_interrupt(cx, "zinc::hal::cortex_m3::nvic",
+          "isr_uart_0", 5, None, "handle_other", DUMMY_SP) == false);
+      assert!(unsafe{*failed} == true);
+      assert!(builder.isr_items.len() == 1);
+    });
+  }
+
+  #[test]
+  fn emits_board_struct_and_init() {
+    with_parsed("led@led; timer@timer; unnamed;", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      pt.get_by_name("led").unwrap().set_type_name(
+          "zinc::hal::lpc17xx::pin::Pin".to_string());
+      pt.get_by_name("timer").unwrap().set_type_name(
+          "zinc::hal::lpc17xx::timer::Timer".to_string());
+      builder.add_main_statement(cx.stmt_let(DUMMY_SP, false,
+          cx.ident_of("led"), cx.expr_uint(DUMMY_SP, 1)));
+      builder.add_main_statement(cx.stmt_let(DUMMY_SP, false,
+          cx.ident_of("timer"), cx.expr_uint(DUMMY_SP, 2)));
+      builder.set_board_entry("run".to_string());
+
+      let items = builder.emit_items(cx);
+      assert!(unsafe{*failed} == false);
+      assert!(items.len() == 4);
+
+      assert_equal_items(items[0].deref(),
+          "#[allow(non_camel_case_types)]
+          mod pt {
+            use zinc;
+            pub struct Board {
+              pub led: zinc::hal::lpc17xx::pin::Pin,
+              pub timer: zinc::hal::lpc17xx::timer::Timer,
+            }
+          }");
+      assert_equal_items(items[1].deref(),
+          "#[allow(unused_variables)]
+          pub unsafe fn init() -> pt::Board {
+            let led = 1u;
+            let timer = 2u;
+            pt::Board {
+              led: led,
+              timer: timer,
+            }
+          }");
+      assert_equal_items(items[2].deref(),
+          "#[no_mangle]
+          #[allow(unused_variables)]
+          pub unsafe fn main() {
+            zinc::hal::mem_init::init_stack();
+            zinc::hal::mem_init::init_data();
+            run(init());
+          }");
+    });
+  }
+
+  #[test]
+  fn fails_to_emit_board_with_borrowing_node() {
+    with_parsed("timer@timer; dht@dht;", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      pt.get_by_name("timer").unwrap().set_type_name(
+          "zinc::hal::lpc17xx::timer::Timer".to_string());
+      let dht = pt.get_by_name("dht").unwrap();
+      dht.set_type_name("zinc::drivers::dht22::DHT22".to_string());
+      dht.set_type_params(vec!("'a".to_string()));
+      builder.set_board_entry("run".to_string());
+
+      builder.emit_items(cx);
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn materializes_all_dependent_nodes() {
+    with_parsed("root@root; a@a; b@b;", |cx, failed, pt| {
+      let root = set_noop_materializer(&pt, "root");
+      let a = set_noop_materializer(&pt, "a");
+      let b = set_noop_materializer(&pt, "b");
+      add_node_dependency(&a, &root);
+      add_node_dependency(&b, &a);
+
+      let mut builder = Builder::new(pt.clone());
+      Builder::walk_materialize(&mut builder, cx, root);
+      assert!(builder.verify_materialized(cx) == true);
+      assert!(unsafe{*failed} == false);
+    });
+  }
+
+  #[test]
+  fn fails_to_materialize_dependency_cycle() {
+    with_parsed("root@root; a@a; b@b; c@c;", |cx, failed, pt| {
+      let root = set_noop_materializer(&pt, "root");
+      let a = set_noop_materializer(&pt, "a");
+      let b = set_noop_materializer(&pt, "b");
+      let c = set_noop_materializer(&pt, "c");
+      add_node_dependency(&a, &root);
+      add_node_dependency(&a, &c);
+      add_node_dependency(&b, &a);
+      add_node_dependency(&c, &b);
+
+      let mut builder = Builder::new(pt.clone());
+      Builder::walk_materialize(&mut builder, cx, root);
+      assert!(builder.verify_materialized(cx) == false);
+      assert!(unsafe{*failed} == true);
+
+      let cycle = Builder::find_cycle(&a).unwrap();
+      assert!(cycle.len() == 3);
+    });
+  }
+
+  #[test]
+  fn fails_to_materialize_self_dependency() {
+    with_parsed("root@root; a@a;", |cx, failed, pt| {
+      let root = set_noop_materializer(&pt, "root");
+      let a = set_noop_materializer(&pt, "a");
+      add_node_dependency(&a, &root);
+      add_node_dependency(&a, &a);
+
+      let mut builder = Builder::new(pt.clone());
+      Builder::walk_materialize(&mut builder, cx, root);
+      assert!(builder.verify_materialized(cx) == false);
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn fails_to_materialize_node_without_dependencies() {
+    with_parsed("root@root; a@a;", |cx, failed, pt| {
+      let root = set_noop_materializer(&pt, "root");
+      set_noop_materializer(&pt, "a");
+
+      let mut builder = Builder::new(pt.clone());
+      Builder::walk_materialize(&mut builder, cx, root);
+      assert!(builder.verify_materialized(cx) == false);
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn fails_to_materialize_node_depending_on_cycle() {
+    with_parsed("root@root; a@a; b@b; c@c;", |cx, failed, pt| {
+      let root = set_noop_materializer(&pt, "root");
+      let a = set_noop_materializer(&pt, "a");
+      let b = set_noop_materializer(&pt, "b");
+      let c = set_noop_materializer(&pt, "c");
+      add_node_dependency(&a, &root);
+      add_node_dependency(&a, &b);
+      add_node_dependency(&b, &a);
+      add_node_dependency(&c, &b);
+
+      let mut builder = Builder::new(pt.clone());
+      Builder::walk_materialize(&mut builder, cx, root);
+      assert!(builder.verify_materialized(cx) == false);
+      assert!(unsafe{*failed} == true);
+      assert!(Builder::find_cycle(&c).is_none());
+    });
+  }
 }
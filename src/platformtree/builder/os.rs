@@ -39,6 +39,11 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
     add_node_dependency(&node, &task_node);
     add_node_dependency(&task_node, &mcu_node);
 
+    match mcu_node.get_by_path("watchdog") {
+      Some(watchdog_node) => add_node_dependency(&task_node, &watchdog_node),
+      None => (),
+    }
+
     let maybe_args_node = task_node.get_by_path("args");
     if maybe_args_node.is_some() {
       let args_node = maybe_args_node.unwrap();
@@ -53,15 +58,43 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
       }
     }
   }
+
+  let maybe_board_node = node.get_by_path("board");
+  if maybe_board_node.is_some() {
+    let board_node = maybe_board_node.unwrap();
+    board_node.materializer.set(Some(build_board as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+    add_node_dependency(&node, &board_node);
+    add_node_dependency(&board_node, &mcu_node);
+    match mcu_node.get_by_path("watchdog") {
+      Some(watchdog_node) => add_node_dependency(&board_node, &watchdog_node),
+      None => (),
+    }
+  }
 }
 
 pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
-  node.expect_subnodes(cx, &["single_task"]);
-  if node.get_by_path("single_task").is_none() {
+  node.expect_subnodes(cx, &["single_task", "board"]);
+  let has_task = node.get_by_path("single_task").is_some();
+  let has_board = node.get_by_path("board").is_some();
+  if has_task == has_board {
     cx.parse_sess().span_diagnostic.span_err(node.name_span,
-        "subnode `single_task` must be present");
+        "exactly one of subnodes `single_task` or `board` must be present");
+  }
+}
+
+/// Switches the builder to emit a `pt::Board` struct and an `init()` function
+/// returning it. The generated main() passes the board to the `entry` function,
+/// which owns the main loop from there on and must call `pt::refresh()`, if the
+/// tree generates one.
+fn build_board(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  if !node.expect_attributes(cx, &[("entry", node::StrAttribute)]) {
+    return;
   }
+  node.expect_no_subnodes(cx);
+
+  let entry = node.get_string_attr("entry").unwrap();
+  builder.set_board_entry(entry);
 }
 
 fn build_single_task(builder: &mut Builder, cx: &mut ExtCtxt,
@@ -81,7 +114,11 @@ fn build_single_task(builder: &mut Builder, cx: &mut ExtCtxt,
           node.get_attr("loop").value_span,
           cx.ident_of(loop_fn.as_slice()),
           args);
-      let loop_stmt = quote_stmt!(&*cx, loop { $call_expr; } );
+      let loop_stmt = if builder.has_refresh_statements() {
+        quote_stmt!(&*cx, loop { $call_expr; pt::refresh(); } )
+      } else {
+        quote_stmt!(&*cx, loop { $call_expr; } )
+      };
       builder.add_main_statement(loop_stmt);
     },
     None => (),
@@ -137,6 +174,22 @@ fn build_args(builder: &mut Builder, cx: &mut ExtCtxt,
           Some(a_lifetime),
           ast::MutImmutable), quote_expr!(&*cx, &$val_slice))
       },
+      node::IdentValue(_) => {
+        cx.parse_sess().span_diagnostic.span_err(v.value_span,
+            "identifiers cannot be passed as task arguments");
+        continue;
+      },
+      node::ListValue(ref list) => {
+        match build_list_arg(cx, list) {
+          Some(ty_val) => ty_val,
+          None => {
+            cx.parse_sess().span_diagnostic.span_err(v.value_span,
+                "only lists of integers, booleans or strings can be passed \
+                 as task arguments");
+            continue;
+          }
+        }
+      },
     };
     let name_ident = cx.ident_of(k.as_slice());
     let sf = ast::StructField_ {
@@ -193,7 +246,43 @@ fn build_args(builder: &mut Builder, cx: &mut ExtCtxt,
           expr_fields))
 }
 
-fn type_name_as_path(cx: &ExtCtxt, ty: &str, params: Vec<String>) -> ast::Path {
+/// Returns the type and value of a slice for a list of plain values, or None if
+/// the list contains references or other lists.
+fn build_list_arg(cx: &ExtCtxt, list: &Vec<node::AttributeValue>)
+    -> Option<(P<ast::Ty>, P<ast::Expr>)> {
+  let mut elt_ty = None;
+  let mut vals = vec!();
+  for v in list.iter() {
+    let (ty_name, val) = match *v {
+      node::IntValue(i) => ("u32", quote_expr!(&*cx, $i)),
+      node::BoolValue(b) => ("bool", quote_expr!(&*cx, $b)),
+      node::StrValue(ref string) => {
+        let val_slice = string.as_slice();
+        ("&'static str", quote_expr!(&*cx, $val_slice))
+      },
+      _ => return None,
+    };
+    elt_ty = Some(ty_name);
+    vals.push(val);
+  }
+
+  // an empty list doesn't carry any type, default to integers
+  let ty = match elt_ty.unwrap_or("u32") {
+    "&'static str" => {
+      let static_lifetime = cx.lifetime(DUMMY_SP, intern("'static"));
+      cx.ty_rptr(DUMMY_SP, cx.ty_ident(DUMMY_SP, cx.ident_of("str")),
+          Some(static_lifetime), ast::MutImmutable)
+    },
+    other => cx.ty_ident(DUMMY_SP, cx.ident_of(other)),
+  };
+  let a_lifetime = cx.lifetime(DUMMY_SP, intern("'a"));
+  Some((
+      cx.ty_rptr(DUMMY_SP, cx.ty(DUMMY_SP, ast::TyVec(ty)), Some(a_lifetime),
+          ast::MutImmutable),
+      cx.expr_vec_slice(DUMMY_SP, vals)))
+}
+
+pub fn type_name_as_path(cx: &ExtCtxt, ty: &str, params: Vec<String>) -> ast::Path {
   let mut lifetimes = vec!();
   let mut types = vec!();
   for p in params.iter() {
@@ -220,7 +309,7 @@ mod test {
   use syntax::ext::build::AstBuilder;
 
   use builder::Builder;
-  use super::build_single_task;
+  use super::{build_board, build_single_task};
   use test_helpers::{assert_equal_source, with_parsed};
 
   #[test]
@@ -241,6 +330,28 @@ mod test {
     });
   }
 
+  #[test]
+  fn builds_single_task_os_loop_with_refresh() {
+    with_parsed("
+      single_task {
+        loop = \"run\";
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      builder.add_refresh_statement(quote_stmt!(&*cx,
+          zinc::hal::lpc17xx::wdt::feed();
+      ));
+      build_single_task(&mut builder, cx, pt.get_by_path("single_task").unwrap().clone());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts.len() == 1);
+
+      assert_equal_source(builder.main_stmts[0].deref(),
+          "loop {
+            run();
+            pt::refresh();
+          }");
+    });
+  }
+
   #[test]
   fn builds_single_task_with_args() {
     with_parsed("
@@ -280,4 +391,72 @@ mod test {
           }");
     });
   }
+
+  #[test]
+  fn builds_single_task_with_list_args() {
+    with_parsed("
+      single_task {
+        loop = \"run\";
+        args {
+          pins = [1, 2, 3];
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      build_single_task(&mut builder, cx, pt.get_by_path("single_task").unwrap().clone());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.type_items.len() == 1);
+
+      assert_equal_source(cx.stmt_item(DUMMY_SP, builder.type_items[0].clone()).deref(),
+          "pub struct run_args<'a> {
+            pub pins: &'a [u32],
+          }");
+
+      assert_equal_source(builder.main_stmts[0].deref(),
+          "loop {
+            run(&pt::run_args {
+              pins: &[1u, 2u, 3u],
+            });
+          }");
+    });
+  }
+
+  #[test]
+  fn fails_to_build_single_task_with_ref_list_args() {
+    with_parsed("
+      single_task {
+        loop = \"run\";
+        args {
+          refs = [&a, &b];
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      build_single_task(&mut builder, cx, pt.get_by_path("single_task").unwrap().clone());
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn builds_board() {
+    with_parsed("
+      board {
+        entry = \"run\";
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      build_board(&mut builder, cx, pt.get_by_path("board").unwrap().clone());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.board_entry == Some("run".to_string()));
+      assert!(builder.main_stmts.len() == 0);
+    });
+  }
+
+  #[test]
+  fn fails_to_build_board_without_entry() {
+    with_parsed("
+      board {}", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      build_board(&mut builder, cx, pt.get_by_path("board").unwrap().clone());
+      assert!(unsafe{*failed} == true);
+      assert!(builder.board_entry == None);
+    });
+  }
 }
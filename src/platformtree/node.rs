@@ -25,16 +25,79 @@ use builder::Builder;
 
 pub use self::AttributeValue::*;
 pub use self::AttributeType::*;
+pub use self::AttributePresence::*;
+pub use self::AttributeRestriction::*;
+pub use self::Unit::*;
+
+/// Physical unit of an integer attribute value.
+///
+/// Units are given as integer literal suffixes, the value is normalized to the
+/// smallest unit of the kind: `12MHz` is stored as 12000000 and `5ms` as 5000.
+#[derive(Copy, PartialEq)]
+pub enum Unit {
+  /// Frequency in Hz, parsed from `Hz`, `kHz` and `MHz` suffixes.
+  Frequency,
+  /// Time in microseconds, parsed from `us`, `ms` and `s` suffixes.
+  Time,
+  /// Baud rate, parsed from the `baud` suffix.
+  BaudRate,
+}
+
+impl Unit {
+  /// Returns the unit and the multiplier for a literal suffix, or None if the
+  /// suffix is not a known unit.
+  pub fn from_suffix(suffix: &str) -> Option<(Unit, uint)> {
+    match suffix {
+      "Hz"   => Some((Frequency, 1)),
+      "kHz"  => Some((Frequency, 1_000)),
+      "MHz"  => Some((Frequency, 1_000_000)),
+      "us"   => Some((Time, 1)),
+      "ms"   => Some((Time, 1_000)),
+      "s"    => Some((Time, 1_000_000)),
+      "baud" => Some((BaudRate, 1)),
+      _      => None,
+    }
+  }
+
+  /// Returns a human-readable unit kind used in diagnostics.
+  pub fn describe(&self) -> &'static str {
+    match *self {
+      Frequency => "frequency",
+      Time      => "time",
+      BaudRate  => "baud rate",
+    }
+  }
+}
 
 /// Holds a value for an attribute.
 ///
-/// The value can be an unsigned integer, string or reference.
+/// The value can be an unsigned integer, string, reference, identifier or a list
+/// of other values.
 #[derive(Clone)]
 pub enum AttributeValue {
   IntValue(uint),
   BoolValue(bool),
   StrValue(String),
   RefValue(String),
+  IdentValue(String),
+  ListValue(Vec<AttributeValue>),
+}
+
+impl AttributeValue {
+  /// Returns true if both values are of the same kind, e.g. both are integers.
+  ///
+  /// Lists are compared by kind only, their elements are not inspected.
+  pub fn is_same_kind(&self, other: &AttributeValue) -> bool {
+    match (self, other) {
+      (&IntValue(_),  &IntValue(_))  => true,
+      (&BoolValue(_), &BoolValue(_)) => true,
+      (&StrValue(_),  &StrValue(_))  => true,
+      (&RefValue(_),  &RefValue(_))  => true,
+      (&IdentValue(_), &IdentValue(_)) => true,
+      (&ListValue(_), &ListValue(_)) => true,
+      _ => false,
+    }
+  }
 }
 
 /// Expected attribute type.
@@ -47,8 +110,126 @@ pub enum AttributeType {
   BoolAttribute,
   StrAttribute,
   RefAttribute,
+  IdentAttribute,
+  IntListAttribute,
+  StrListAttribute,
+  RefListAttribute,
+}
+
+impl AttributeType {
+  /// Returns true if the value is of this type.
+  pub fn matches(&self, value: &AttributeValue) -> bool {
+    match *self {
+      IntAttribute  => match *value { IntValue(_)  => true, _ => false },
+      BoolAttribute => match *value { BoolValue(_) => true, _ => false },
+      StrAttribute  => match *value { StrValue(_)  => true, _ => false },
+      RefAttribute  => match *value { RefValue(_)  => true, _ => false },
+      IdentAttribute => match *value { IdentValue(_) => true, _ => false },
+      IntListAttribute => IntAttribute.matches_list(value),
+      StrListAttribute => StrAttribute.matches_list(value),
+      RefListAttribute => RefAttribute.matches_list(value),
+    }
+  }
+
+  /// Returns true if the value is a list and all of its elements are of this
+  /// type.
+  fn matches_list(&self, value: &AttributeValue) -> bool {
+    match *value {
+      ListValue(ref l) => l.iter().all(|v| self.matches(v)),
+      _ => false,
+    }
+  }
+
+  /// Returns a human-readable type name used in diagnostics.
+  pub fn describe(&self) -> &'static str {
+    match *self {
+      IntAttribute     => "integer",
+      BoolAttribute    => "boolean",
+      StrAttribute     => "string",
+      RefAttribute     => "ref",
+      IdentAttribute   => "identifier",
+      IntListAttribute => "integer list",
+      StrListAttribute => "string list",
+      RefListAttribute => "ref list",
+    }
+  }
+}
+
+/// Specifies if an attribute described by AttributeSchema must be present.
+pub enum AttributePresence {
+  /// The attribute must be present.
+  Required,
+  /// The attribute may be omitted.
+  Optional,
+  /// The attribute may be omitted, in which case it's set to the given value.
+  WithDefault(AttributeValue),
+}
+
+/// Additional restriction on the value of an attribute described by
+/// AttributeSchema.
+///
+/// For list attributes the restriction is applied to every element.
+pub enum AttributeRestriction {
+  /// Any value of the expected type is allowed.
+  Unrestricted,
+  /// String value must be one of the listed values.
+  OneOf(Vec<&'static str>),
+  /// Integer value must be within the inclusive range.
+  InRange(uint, uint),
+  /// Integer value must be one of the listed values.
+  OneOfInt(Vec<uint>),
+}
+
+/// Declares a single attribute of a node.
+pub struct AttributeSchema {
+  pub name: &'static str,
+  pub ty: AttributeType,
+  pub presence: AttributePresence,
+  pub restriction: AttributeRestriction,
+
+  /// Unit of the integer value. If set, unit-suffixed values must be of this
+  /// unit, otherwise suffixed values are not allowed.
+  pub unit: Option<Unit>,
 }
 
+impl AttributeSchema {
+  pub fn new(name: &'static str, ty: AttributeType,
+      presence: AttributePresence, restriction: AttributeRestriction)
+      -> AttributeSchema {
+    AttributeSchema {
+      name: name,
+      ty: ty,
+      presence: presence,
+      restriction: restriction,
+      unit: None,
+    }
+  }
+
+  /// Sets the unit the attribute value may be suffixed with.
+  pub fn with_unit(self, unit: Unit) -> AttributeSchema {
+    AttributeSchema {
+      unit: Some(unit),
+      ..self
+    }
+  }
+}
+
+/// Declares all the attributes and subnodes a node may have.
+///
+/// Attributes not listed in the schema are reported as errors.
+pub struct NodeSchema {
+  pub attributes: Vec<AttributeSchema>,
+
+  /// Paths of allowed subnodes, or None if subnodes are not checked.
+  pub subnodes: Option<Vec<&'static str>>,
+}
+
+/// Schema function returns a schema for the node it's attached to.
+///
+/// The builder validates all nodes with a schema after mutators are executed
+/// and before any of the materializers run.
+pub type NodeSchemaFn = fn() -> NodeSchema;
+
 /// Attribute value and metadata.
 ///
 /// Stored inside of a HashMap, the key to HashMap is the attribute name.
@@ -58,16 +239,26 @@ pub struct Attribute {
   pub value: AttributeValue,
   pub key_span: Span,
   pub value_span: Span,
+
+  /// Unit of the value, if it was given with a unit suffix.
+  pub unit: Option<Unit>,
 }
 
 impl Attribute {
   /// Creates a new attribute with given span.
   pub fn new(value: AttributeValue, key_span: Span, value_span: Span)
       -> Attribute {
+    Attribute::new_with_unit(value, None, key_span, value_span)
+  }
+
+  /// Creates a new attribute with given span and value unit.
+  pub fn new_with_unit(value: AttributeValue, unit: Option<Unit>,
+      key_span: Span, value_span: Span) -> Attribute {
     Attribute {
       value: value,
       key_span: key_span,
       value_span: value_span,
+      unit: unit,
     }
   }
 
@@ -77,6 +268,7 @@ impl Attribute {
       value: value,
       key_span: DUMMY_SP,
       value_span: DUMMY_SP,
+      unit: None,
     }
   }
 }
@@ -111,6 +303,12 @@ impl Subnodes {
     self.by_index.push(node);
   }
 
+  /// Removes a node with the given path from subnodes, if present.
+  pub fn remove(&mut self, path: &str) {
+    self.by_path.remove(&path.to_string());
+    self.by_index.retain(|node| node.path.as_slice() != path);
+  }
+
   /// Returns a vector representation of subnodes.
   pub fn as_vec<'a>(&'a self) -> &'a Vec<Rc<Node>> {
     &self.by_index
@@ -179,6 +377,9 @@ pub struct Node {
   /// List of nodes that may be materialized before this node.
   pub rev_depends_on: RefCell<Vec<Weak<Node>>>,
 
+  /// A function that returns the schema this node is validated against.
+  pub schema: Cell<Option<NodeSchemaFn>>,
+
   subnodes: RefCell<Subnodes>,
   type_name: RefCell<Option<String>>,
   type_params: RefCell<Vec<String>>,
@@ -201,6 +402,7 @@ impl Node {
       mutator: Cell::new(None),
       depends_on: RefCell::new(Vec::new()),
       rev_depends_on: RefCell::new(Vec::new()),
+      schema: Cell::new(None),
     }
   }
 
@@ -252,6 +454,18 @@ impl Node {
     self.subnodes.borrow_mut().clone_from(new);
   }
 
+  /// Adds a node to node's subnodes.
+  ///
+  /// The node must not be present in the subnodes.
+  pub fn add_subnode(&self, node: Rc<Node>) {
+    self.subnodes.borrow_mut().push(node);
+  }
+
+  /// Removes a node with the given path from node's subnodes.
+  pub fn remove_subnode(&self, path: &str) {
+    self.subnodes.borrow_mut().remove(path);
+  }
+
   /// Returns a clones string of current node's path.
   pub fn path(&self) -> String {
     self.path.clone()
@@ -308,6 +522,69 @@ impl Node {
     })
   }
 
+  /// Returns an identifier attribute by name or None, if it's not present or
+  /// not of an IdentAttribute type.
+  pub fn get_ident_attr(&self, key: &str) -> Option<String> {
+    self.attributes.borrow().get(&key.to_string()).and_then(|av| match av.value {
+      IdentValue(ref s) => Some(s.clone()),
+      _ => None,
+    })
+  }
+
+  /// Returns a list attribute by name or None, if it's not present or not of
+  /// a list type.
+  pub fn get_list_attr(&self, key: &str) -> Option<Vec<AttributeValue>> {
+    self.attributes.borrow().get(&key.to_string()).and_then(|av| match av.value {
+      ListValue(ref l) => Some(l.clone()),
+      _ => None,
+    })
+  }
+
+  /// Returns an integer list attribute by name or None, if it's not present or
+  /// any of its elements is not an integer.
+  pub fn get_int_list_attr(&self, key: &str) -> Option<Vec<uint>> {
+    self.get_list_attr(key).and_then(|list| {
+      let mut vals = vec!();
+      for v in list.iter() {
+        match *v {
+          IntValue(u) => vals.push(u),
+          _ => return None,
+        }
+      }
+      Some(vals)
+    })
+  }
+
+  /// Returns a string list attribute by name or None, if it's not present or
+  /// any of its elements is not a string.
+  pub fn get_string_list_attr(&self, key: &str) -> Option<Vec<String>> {
+    self.get_list_attr(key).and_then(|list| {
+      let mut vals = vec!();
+      for v in list.iter() {
+        match *v {
+          StrValue(ref s) => vals.push(s.clone()),
+          _ => return None,
+        }
+      }
+      Some(vals)
+    })
+  }
+
+  /// Returns a reference list attribute by name or None, if it's not present
+  /// or any of its elements is not a reference.
+  pub fn get_ref_list_attr(&self, key: &str) -> Option<Vec<String>> {
+    self.get_list_attr(key).and_then(|list| {
+      let mut vals = vec!();
+      for v in list.iter() {
+        match *v {
+          RefValue(ref s) => vals.push(s.clone()),
+          _ => return None,
+        }
+      }
+      Some(vals)
+    })
+  }
+
   /// Returns a string attribute by name or None, if it's not present or not of
   /// a StrAttribute type. Reports a parser error if an attribute is
   /// missing.
@@ -372,6 +649,70 @@ impl Node {
     }
   }
 
+  /// Returns an identifier attribute by name or None, if it's not present or
+  /// not of an IdentAttribute type. Reports a parser error if an attribute is
+  /// missing.
+  pub fn get_required_ident_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<String> {
+    match self.get_ident_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required identifier attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
+  /// Returns an integer list attribute by name or None, if it's not present or
+  /// not of an IntListAttribute type. Reports a parser error if an attribute
+  /// is missing.
+  pub fn get_required_int_list_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<Vec<uint>> {
+    match self.get_int_list_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required integer list attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
+  /// Returns a string list attribute by name or None, if it's not present or
+  /// not of a StrListAttribute type. Reports a parser error if an attribute
+  /// is missing.
+  pub fn get_required_string_list_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<Vec<String>> {
+    match self.get_string_list_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required string list attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
+  /// Returns a reference list attribute by name or None, if it's not present
+  /// or not of a RefListAttribute type. Reports a parser error if an
+  /// attribute is missing.
+  pub fn get_required_ref_list_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<Vec<String>> {
+    match self.get_ref_list_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required ref list attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
   /// Returns true if node has no attributes. Returs false and reports a parser
   /// error for each found attribute otherwise.
   pub fn expect_no_attributes(&self, cx: &ExtCtxt) -> bool {
@@ -415,6 +756,18 @@ impl Node {
         &RefAttribute => {
           if self.get_required_ref_attr(cx, n).is_none() {ok = false}
         },
+        &IdentAttribute => {
+          if self.get_required_ident_attr(cx, n).is_none() {ok = false}
+        },
+        &IntListAttribute => {
+          if self.get_required_int_list_attr(cx, n).is_none() {ok = false}
+        },
+        &StrListAttribute => {
+          if self.get_required_string_list_attr(cx, n).is_none() {ok = false}
+        },
+        &RefListAttribute => {
+          if self.get_required_ref_list_attr(cx, n).is_none() {ok = false}
+        },
       }
     }
     ok
@@ -435,6 +788,86 @@ impl Node {
     ok
   }
 
+  /// Validates node's attributes and subnodes against the schema.
+  ///
+  /// All of the violations are reported as parser errors. Missing attributes
+  /// that have a default value are inserted into the node. Returns false if
+  /// any violation was found.
+  pub fn validate(&self, cx: &ExtCtxt, schema: &NodeSchema) -> bool {
+    let mut ok = true;
+
+    for (k, v) in self.attributes.borrow().iter() {
+      if !schema.attributes.iter().any(|a| a.name == k.as_slice()) {
+        ok = false;
+        cx.parse_sess().span_diagnostic.span_err(v.key_span,
+            format!("unknown attribute `{}` in node `{}`", k, self.path)
+            .as_slice());
+      }
+    }
+
+    for a in schema.attributes.iter() {
+      let maybe_attr = self.attributes.borrow().get(&a.name.to_string())
+          .map(|attr| attr.clone());
+      match maybe_attr {
+        None => match a.presence {
+          Required => {
+            ok = false;
+            cx.parse_sess().span_diagnostic.span_err(self.name_span,
+                format!("required {} attribute `{}` is missing",
+                    a.ty.describe(), a.name).as_slice());
+          },
+          Optional => (),
+          WithDefault(ref value) => {
+            self.attributes.borrow_mut().insert(a.name.to_string(),
+                Rc::new(Attribute::new_nosp(value.clone())));
+          },
+        },
+        Some(attr) => {
+          if !a.ty.matches(&attr.value) {
+            ok = false;
+            cx.parse_sess().span_diagnostic.span_err(attr.value_span,
+                format!("attribute `{}` must be of {} type",
+                    a.name, a.ty.describe()).as_slice());
+          } else if !check_unit(cx, a, &*attr) {
+            ok = false;
+          } else if !check_restriction(cx, a, &attr.value, attr.value_span) {
+            ok = false;
+          }
+        },
+      }
+    }
+
+    match schema.subnodes {
+      Some(ref allowed) => {
+        if !self.expect_subnodes(cx, allowed.as_slice()) {
+          ok = false;
+        }
+      },
+      None => (),
+    }
+
+    ok
+  }
+
+  /// Returns true if the attribute, if present, was given without a unit or
+  /// with the expected one. Reports a parser error and returns false otherwise.
+  ///
+  /// Used by nodes that don't have a schema to check unit-suffixed values.
+  pub fn expect_unit(&self, cx: &ExtCtxt, key: &str, unit: Unit) -> bool {
+    match self.attributes.borrow().get(&key.to_string()) {
+      Some(attr) => match attr.unit {
+        Some(u) if u != unit => {
+          cx.parse_sess().span_diagnostic.span_err(attr.value_span,
+              format!("attribute `{}` expects a {} value, but found a {} value",
+                  key, unit.describe(), u.describe()).as_slice());
+          false
+        },
+        _ => true,
+      },
+      None => true,
+    }
+  }
+
   /// Returns a subnode by path or None, if not found.
   pub fn get_by_path(&self, path: &str) -> Option<Rc<Node>> {
     self.subnodes.borrow().as_map().get(&path.to_string()).and_then(|node| {
@@ -443,6 +876,78 @@ impl Node {
   }
 }
 
+/// Checks that the unit of the value, if any, is the one expected by the
+/// attribute schema. Reports a parser error and returns false otherwise.
+fn check_unit(cx: &ExtCtxt, schema: &AttributeSchema, attr: &Attribute)
+    -> bool {
+  match (attr.unit, schema.unit) {
+    (None, _) => true,
+    (Some(unit), Some(expected)) if unit == expected => true,
+    (Some(unit), Some(expected)) => {
+      cx.parse_sess().span_diagnostic.span_err(attr.value_span,
+          format!("attribute `{}` expects a {} value, but found a {} value",
+              schema.name, expected.describe(), unit.describe()).as_slice());
+      false
+    },
+    (Some(unit), None) => {
+      cx.parse_sess().span_diagnostic.span_err(attr.value_span,
+          format!("attribute `{}` doesn't take a unit, but found a {} value",
+              schema.name, unit.describe()).as_slice());
+      false
+    },
+  }
+}
+
+/// Checks the value against the restriction of the attribute schema. Reports a
+/// parser error and returns false if the value is not allowed.
+fn check_restriction(cx: &ExtCtxt, schema: &AttributeSchema,
+    value: &AttributeValue, span: Span) -> bool {
+  match (value, &schema.restriction) {
+    (&ListValue(ref l), _) => {
+      let mut ok = true;
+      for v in l.iter() {
+        if !check_restriction(cx, schema, v, span) { ok = false }
+      }
+      ok
+    },
+    (&StrValue(ref s), &OneOf(ref allowed)) => {
+      if allowed.contains(&s.as_slice()) {
+        true
+      } else {
+        cx.parse_sess().span_diagnostic.span_err(span,
+            format!("unknown value `{}` for attribute `{}`, allowed values: {}",
+                s, schema.name, allowed.connect(", ")).as_slice());
+        false
+      }
+    },
+    (&IntValue(i), &InRange(lo, hi)) => {
+      if i >= lo && i <= hi {
+        true
+      } else {
+        cx.parse_sess().span_diagnostic.span_err(span,
+            format!("value `{}` for attribute `{}` is out of range, \
+                     allowed values: {}...{}", i, schema.name, lo, hi)
+            .as_slice());
+        false
+      }
+    },
+    (&IntValue(i), &OneOfInt(ref allowed)) => {
+      if allowed.contains(&i) {
+        true
+      } else {
+        let allowed: Vec<String> = allowed.iter().map(|a| a.to_string())
+            .collect();
+        cx.parse_sess().span_diagnostic.span_err(span,
+            format!("value `{}` for attribute `{}` is not allowed, \
+                     allowed values: {}", i, schema.name, allowed.connect(", "))
+            .as_slice());
+        false
+      }
+    },
+    _ => true,
+  }
+}
+
 impl PartialEq for Node {
   fn eq(&self, other: &Node) -> bool {
     self.full_path() == other.full_path()
@@ -489,6 +994,15 @@ impl PlatformTree {
     v
   }
 
+  /// Returns all named nodes, sorted by name.
+  pub fn named_nodes(&self) -> Vec<Rc<Node>> {
+    let mut names: Vec<&String> = self.named.keys().collect();
+    names.sort();
+    names.iter().map(|name| {
+      self.named[**name].upgrade().unwrap()
+    }).collect()
+  }
+
   /// Returns a node by name or None, if not found.
   pub fn get_by_name(&self, name: &str) -> Option<Rc<Node>> {
     self.named.get(&name.to_string()).and_then(|node| { Some(node.upgrade().unwrap().clone()) })
@@ -513,3 +1027,121 @@ impl PlatformTree {
     ok
   }
 }
+
+#[cfg(test)]
+mod test {
+  use test_helpers::with_parsed;
+  use super::{NodeSchema, AttributeSchema, IntAttribute, StrAttribute,
+      RefAttribute, Required, Optional, WithDefault, Unrestricted, OneOf,
+      InRange, OneOfInt, StrValue, Frequency};
+
+  fn unit_schema() -> NodeSchema {
+    NodeSchema {
+      attributes: vec!(
+        AttributeSchema::new("freq", IntAttribute, Optional, Unrestricted)
+            .with_unit(Frequency),
+        AttributeSchema::new("count", IntAttribute, Optional, Unrestricted),
+      ),
+      subnodes: None,
+    }
+  }
+
+  fn test_schema() -> NodeSchema {
+    NodeSchema {
+      attributes: vec!(
+        AttributeSchema::new("speed", IntAttribute, Required,
+            InRange(1, 10)),
+        AttributeSchema::new("mode", StrAttribute, WithDefault(
+            StrValue("fast".to_string())), OneOf(vec!("fast", "slow"))),
+        AttributeSchema::new("pin", RefAttribute, Optional, Unrestricted),
+        AttributeSchema::new("div", IntAttribute, Optional,
+            OneOfInt(vec!(1, 2, 4))),
+      ),
+      subnodes: Some(vec!("sub")),
+    }
+  }
+
+  #[test]
+  fn validates_node_against_schema() {
+    with_parsed("node { speed = 5; pin = &p; sub; }", |cx, failed, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == true);
+      assert!(unsafe{*failed} == false);
+      assert!(node.get_string_attr("mode") == Some("fast".to_string()));
+    });
+  }
+
+  #[test]
+  fn keeps_explicit_value_over_default() {
+    with_parsed("node { speed = 5; mode = \"slow\"; }", |cx, _, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == true);
+      assert!(node.get_string_attr("mode") == Some("slow".to_string()));
+    });
+  }
+
+  #[test]
+  fn fails_to_validate_missing_required_attribute() {
+    with_parsed("node { pin = &p; }", |cx, failed, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == false);
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn validates_attribute_units() {
+    with_parsed("node { freq = 12MHz; count = 3; }", |cx, failed, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &unit_schema()) == true);
+      assert!(unsafe{*failed} == false);
+    });
+    with_parsed("node { freq = 12ms; }", |cx, failed, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &unit_schema()) == false);
+      assert!(unsafe{*failed} == true);
+    });
+    with_parsed("node { count = 3baud; }", |cx, failed, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &unit_schema()) == false);
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn fails_to_validate_restricted_values() {
+    with_parsed("node { speed = 11; }", |cx, _, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == false);
+    });
+    with_parsed("node { speed = 1; mode = \"medium\"; }", |cx, _, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == false);
+    });
+    with_parsed("node { speed = 1; div = 3; }", |cx, _, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == false);
+    });
+  }
+
+  #[test]
+  fn fails_to_validate_unknown_attributes_and_subnodes() {
+    with_parsed("node { speed = 1; other = 1; }", |cx, _, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == false);
+    });
+    with_parsed("node { speed = 1; other; }", |cx, _, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == false);
+    });
+  }
+
+  #[test]
+  fn fails_to_validate_mistyped_attribute() {
+    with_parsed("node { speed = \"1\"; }", |cx, _, pt| {
+      let node = pt.get_by_path("node").unwrap();
+      assert!(node.validate(cx, &test_schema()) == false);
+      assert!(node.get_int_attr("speed").is_none());
+    });
+  }
+}
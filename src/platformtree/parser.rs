@@ -13,16 +13,57 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::File;
 use std::rc::{Rc, Weak};
 use syntax::ast::{TokenTree, LitInt, UnsuffixedIntLit};
 use syntax::codemap::{Span, mk_sp};
 use syntax::ext::base::ExtCtxt;
-use syntax::parse::{token, ParseSess, lexer, integer_lit};
+use syntax::parse::{token, ParseSess, lexer, integer_lit, filemap_to_tts};
 use syntax::print::pprust;
 
 use node;
 
+/// Reads a standalone Platform Tree file and returns its tokens.
+///
+/// The file is added to the codemap, so that spans of the returned tokens, and
+/// all the diagnostics based on them, point into the file.
+pub fn read_file_tts(sess: &ParseSess, path: &Path)
+    -> Result<Vec<TokenTree>, String> {
+  let src = match File::open(path).read_to_string() {
+    Ok(src) => src,
+    Err(e) => return Err(format!("couldn't read `{}`: {}", path.display(), e)),
+  };
+  let filemap = sess.codemap().new_filemap(
+      format!("{}", path.display()), src);
+  Ok(filemap_to_tts(sess, filemap))
+}
+
+/// Returns a node with the given name from the tree, if there's one.
+fn find_named(nodes: &HashMap<String, Rc<node::Node>>, name: &str)
+    -> Option<Rc<node::Node>> {
+  fn walk(node: &Rc<node::Node>, name: &str) -> Option<Rc<node::Node>> {
+    if node.name.as_ref().map(|n| n.as_slice()) == Some(name) {
+      return Some(node.clone());
+    }
+    for sub in node.subnodes().iter() {
+      let found = walk(sub, name);
+      if found.is_some() {
+        return found;
+      }
+    }
+    None
+  }
+
+  for (_, n) in nodes.iter() {
+    let found = walk(n, name);
+    if found.is_some() {
+      return found;
+    }
+  }
+  None
+}
+
 pub struct Parser<'a> {
   pub sess: &'a ParseSess,
   reader: Box<lexer::Reader+'a>,
@@ -31,6 +72,8 @@ pub struct Parser<'a> {
 
   last_token: Option<Box<token::Token>>,
   last_span: Span,
+
+  constants: HashMap<String, (node::AttributeValue, Option<node::Unit>)>,
 }
 
 impl<'a> Parser<'a> {
@@ -53,6 +96,8 @@ impl<'a> Parser<'a> {
 
       last_token: None,
       last_span: span,
+
+      constants: HashMap::new(),
     }
   }
 
@@ -65,6 +110,22 @@ impl<'a> Parser<'a> {
         break
       }
 
+      if self.is_constant() {
+        if !self.parse_constant() {
+          failed = true;
+          self.bump();
+        }
+        continue
+      }
+
+      if self.is_extension() {
+        if !self.parse_root_extension(&nodes) {
+          failed = true;
+          self.bump();
+        }
+        continue
+      }
+
       let node = match self.parse_node(None) {
         Some(node) => node,
         None => {
@@ -136,7 +197,18 @@ impl<'a> Parser<'a> {
 
   fn parse_node(&mut self, parent: Option<Weak<node::Node>>)
       -> Option<Rc<node::Node>> {
-    use syntax::parse::token::Token;
+    let header = match self.parse_node_header() {
+      Some(header) => header,
+      None => return None,
+    };
+    self.parse_node_with_header(header, parent)
+  }
+
+  /// Parses the node header, i.e. `NAME @ PATH` or `PATH`.
+  ///
+  /// Returns node name, node span, node path and path span.
+  fn parse_node_header(&mut self)
+      -> Option<(Option<String>, Span, String, Span)> {
     let name_span: Option<Span>;
     let node_name: Option<String>;
 
@@ -161,8 +233,6 @@ impl<'a> Parser<'a> {
 
     let node_span: Span;
     let node_path_span: Span;
-    let attributes: HashMap<String, Rc<node::Attribute>>;
-    let subnodes: node::Subnodes;
 
     // NAME is resolved, if it was there anyway.
     //    we're here
@@ -176,9 +246,20 @@ impl<'a> Parser<'a> {
       node_span = mk_sp(name_span.unwrap().lo, self.span.hi);
     }
 
-    let node_path = match self.token {
+    let node_path = match self.parse_node_path() {
+      Some(path) => path,
+      None => return None,
+    };
+
+    Some((node_name, node_span, node_path, node_path_span))
+  }
+
+  /// Parses node path, which is either an identifier or an unsuffixed integer.
+  fn parse_node_path(&mut self) -> Option<String> {
+    use syntax::parse::token::Token;
+    match self.token {
       Token::Ident(_, _) => {
-        pprust::token_to_string(&self.bump())
+        Some(pprust::token_to_string(&self.bump()))
       },
       Token::Literal(token::Lit::Integer(intname), _) => {
         self.bump();
@@ -186,21 +267,29 @@ impl<'a> Parser<'a> {
         let lit = integer_lit(intname.as_str(), None, &self.sess.span_diagnostic, self.span);
         match lit {
           LitInt(i, _) => {
-            format!("{}", i)
+            Some(format!("{}", i))
           },
           _ => {
             self.error(format!("expected unsuffixed integer but found `{}`",
                 pprust::token_to_string(&self.token)));
-            return None
+            None
           }
         }
       }
       ref other => {
         self.error(format!("expected node path but found `{}`",
             pprust::token_to_string(other)));
-        return None;
+        None
       }
-    };
+    }
+  }
+
+  fn parse_node_with_header(&mut self,
+      header: (Option<String>, Span, String, Span),
+      parent: Option<Weak<node::Node>>) -> Option<Rc<node::Node>> {
+    let (node_name, node_span, node_path, node_path_span) = header;
+    let attributes: HashMap<String, Rc<node::Attribute>>;
+    let subnodes: node::Subnodes;
 
     let node = Rc::new(node::Node::new(
         node_name, node_span, node_path, node_path_span, parent));
@@ -262,43 +351,16 @@ impl<'a> Parser<'a> {
       // PATH ;
       //      ^-- peeking here
       if self.reader.peek().tok == token::Eq {
-        // we're here
-        // |
-        // v
-        // ATTR = VAL ;
-        let name_span = self.span;
-        let some_name = match self.expect_ident() {
-          Some(name) => name,
+        let (name, attr) = match self.parse_attribute() {
+          Some(name_attr) => name_attr,
           None => return None,
         };
-
-        if attrs.contains_key(&some_name) {
-          self.error(format!("key `{}` is already defined", some_name));
-          return None;
-        }
-
-        self.bump(); // bump token::Eq
-
-        // we're here
-        //        |
-        //        v
-        // ATTR = VAL ;
-        let attr_value_span = self.span;
-        let attr_value = match self.parse_attribute_value() {
-          Some(value) => value,
-          None => return None,
-        };
-
-        //   we're here
-        //            |
-        //            v
-        // ATTR = VAL ;
-        if !self.expect(&token::Semi) {
+        if attrs.contains_key(&name) {
+          self.span = attr.key_span;
+          self.error(format!("key `{}` is already defined", name));
           return None;
         }
-
-        attrs.insert(some_name, Rc::new(node::Attribute::new(
-            attr_value, name_span, attr_value_span)));
+        attrs.insert(name, attr);
       } else {
         // this should be a subnode
         let oldsp = self.span;
@@ -331,33 +393,273 @@ impl<'a> Parser<'a> {
     Some((attrs, subnodes))
   }
 
-  /// Get an Option<AttributeValue> depending on the Token type and value of
-  /// self.token
-  fn parse_attribute_value(&mut self) -> Option<node::AttributeValue> {
+  /// Parses a single `ATTR = VAL ;` attribute definition.
+  fn parse_attribute(&mut self) -> Option<(String, Rc<node::Attribute>)> {
+    // we're here
+    // |
+    // v
+    // ATTR = VAL ;
+    let name_span = self.span;
+    let some_name = match self.expect_ident() {
+      Some(name) => name,
+      None => return None,
+    };
+
+    self.bump(); // bump token::Eq
+
+    // we're here
+    //        |
+    //        v
+    // ATTR = VAL ;
+    let attr_value_span = self.span;
+    let (attr_value, unit) = match self.parse_attribute_value() {
+      Some(value) => value,
+      None => return None,
+    };
+
+    //   we're here
+    //            |
+    //            v
+    // ATTR = VAL ;
+    if !self.expect(&token::Semi) {
+      return None;
+    }
+
+    Some((some_name, Rc::new(node::Attribute::new_with_unit(
+        attr_value, unit, name_span, attr_value_span))))
+  }
+
+  /// Returns true if the parser is at `const NAME`.
+  fn is_constant(&mut self) -> bool {
+    match self.token {
+      token::Ident(ident, _) if token::get_ident(ident).get() == "const" => true,
+      _ => false,
+    }
+  }
+
+  /// Parses `const NAME = VAL;`. Attributes defined after the constant can use
+  /// NAME in place of the value. VAL can't be an identifier other than a
+  /// previously defined constant.
+  fn parse_constant(&mut self) -> bool {
+    self.bump(); // bump `const`
+
+    let name_span = self.span;
+    let name = match self.expect_ident() {
+      Some(name) => name,
+      None => return false,
+    };
+
+    if !self.expect(&token::Eq) {
+      return false;
+    }
+
+    let value_span = self.span;
+    let value = match self.parse_attribute_value() {
+      Some(value) => value,
+      None => return false,
+    };
+
+    match value {
+      (node::IdentValue(ref ident), _) => {
+        self.sess.span_diagnostic.span_err(value_span,
+            format!("unknown constant `{}`", ident).as_slice());
+        return false;
+      },
+      _ => (),
+    }
+
+    if !self.expect(&token::Semi) {
+      return false;
+    }
+
+    if self.constants.contains_key(&name) {
+      self.sess.span_diagnostic.span_err(name_span,
+          format!("duplicate constant definition `{}`", name).as_slice());
+      return false;
+    }
+    self.constants.insert(name, value);
+    true
+  }
+
+  /// Returns true if the parser is at `extend PATH`.
+  fn is_extension(&mut self) -> bool {
+    match self.token {
+      token::Ident(ident, _) if token::get_ident(ident).get() == "extend" => {
+        match self.reader.peek().tok {
+          token::Ident(_, _) | token::Literal(token::Lit::Integer(_), _) => true,
+          _ => false,
+        }
+      },
+      _ => false,
+    }
+  }
+
+  /// Parses `extend PATH { ... }`, merging its content into a previously
+  /// defined root node.
+  fn parse_root_extension(&mut self, roots: &HashMap<String, Rc<node::Node>>)
+      -> bool {
+    self.bump(); // bump `extend`
+
+    let path_span = self.span;
+    let path = match self.parse_node_path() {
+      Some(path) => path,
+      None => return false,
+    };
+
+    let node = match roots.get(&path) {
+      Some(node) => node.clone(),
+      None => {
+        self.sess.span_diagnostic.span_err(path_span,
+            format!("cannot extend undefined node `{}`", path).as_slice());
+        return false;
+      }
+    };
+
+    if !self.expect(&token::OpenDelim(token::Brace)) {
+      return false;
+    }
+    if !self.parse_extension_body(roots, node) {
+      return false;
+    }
+    self.expect(&token::CloseDelim(token::Brace))
+  }
+
+  /// Parses the body of an extension into an existing node.
+  ///
+  /// Attributes override the ones already defined. Subnodes with known paths
+  /// are extended recursively, new subnodes are added to the node. If a new
+  /// subnode has the same name as some other node in the tree, that other node
+  /// is removed, so the named node is effectively moved.
+  fn parse_extension_body(&mut self, roots: &HashMap<String, Rc<node::Node>>,
+      node: Rc<node::Node>) -> bool {
+    let mut seen_attrs = HashSet::new();
+
+    loop {
+      if self.token == token::CloseDelim(token::Brace) {
+        break;
+      }
+
+      if self.reader.peek().tok == token::Eq {
+        let (name, attr) = match self.parse_attribute() {
+          Some(name_attr) => name_attr,
+          None => return false,
+        };
+        if !seen_attrs.insert(name.clone()) {
+          self.span = attr.key_span;
+          self.error(format!("key `{}` is already defined", name));
+          return false;
+        }
+        node.attributes.borrow_mut().insert(name, attr);
+        continue;
+      }
+
+      let (name, node_span, path, path_span) = match self.parse_node_header() {
+        Some(header) => header,
+        None => return false,
+      };
+
+      match node.get_by_path(path.as_slice()) {
+        Some(sub) => {
+          if name.is_some() && name != sub.name {
+            self.sess.span_diagnostic.span_err(node_span, format!(
+                "cannot rename node `{}` in extension", sub.full_path())
+                .as_slice());
+            return false;
+          }
+          match self.bump() {
+            token::OpenDelim(token::Brace) => {
+              if !self.parse_extension_body(roots, sub) {
+                return false;
+              }
+              if !self.expect(&token::CloseDelim(token::Brace)) {
+                return false;
+              }
+            },
+            token::Semi => (),
+            ref other => {
+              self.error(format!("expected `{{` or `;` but found `{}`",
+                  pprust::token_to_string(other)));
+              return false;
+            }
+          }
+        },
+        None => {
+          match name {
+            Some(ref name) => match find_named(roots, name.as_slice()) {
+              Some(old) => match old.parent {
+                Some(ref parent) => {
+                  parent.upgrade().unwrap().remove_subnode(old.path.as_slice());
+                },
+                None => (),
+              },
+              None => (),
+            },
+            None => (),
+          }
+
+          let header = (name, node_span, path, path_span);
+          let sub = match self.parse_node_with_header(header,
+              Some(node.downgrade())) {
+            Some(sub) => sub,
+            None => return false,
+          };
+          node.add_subnode(sub);
+        },
+      }
+    }
+
+    true
+  }
+
+  /// Parses an attribute value and returns it along with the unit it was
+  /// given in, if any. Identifiers naming a constant are replaced with the
+  /// constant value.
+  fn parse_attribute_value(&mut self)
+      -> Option<(node::AttributeValue, Option<node::Unit>)> {
     use syntax::parse::token::{Token, Lit};
     match self.token {
       Token::Literal(Lit::Str_(string_val), _) => {
         self.bump();
-        Some(node::StrValue(string_val.as_str().to_string()))
+        Some((node::StrValue(string_val.as_str().to_string()), None))
       },
       Token::Literal(Lit::Integer(intname), suffix) => {
-        if suffix.is_none() {
-          let lit = integer_lit(intname.as_str(), None, &self.sess.span_diagnostic, self.span);
-          match lit {
-            LitInt(i, UnsuffixedIntLit(_)) => {
-              self.bump();
-              Some(node::IntValue(i as uint))
-            },
-            _ => {
-              self.error(format!("expected unsuffixed positive integer but found `{}`",
-                  pprust::token_to_string(&self.token)));
-              None
+        let unit = match suffix {
+          None => None,
+          Some(suffix) => match node::Unit::from_suffix(suffix.as_str()) {
+            Some(unit) => Some(unit),
+            None => {
+              self.error(format!("expected unsuffixed integer or a known unit \
+                  but found `{}`", pprust::token_to_string(&self.token)));
+              return None;
             }
+          },
+        };
+        let lit = integer_lit(intname.as_str(), None, &self.sess.span_diagnostic, self.span);
+        match lit {
+          LitInt(i, UnsuffixedIntLit(_)) => {
+            match unit {
+              None => {
+                self.bump();
+                Some((node::IntValue(i as uint), None))
+              },
+              Some((unit, multiplier)) => match (i as uint).checked_mul(multiplier) {
+                Some(value) => {
+                  self.bump();
+                  Some((node::IntValue(value), Some(unit)))
+                },
+                None => {
+                  self.error(format!("value `{}` is too large",
+                      pprust::token_to_string(&self.token)));
+                  None
+                },
+              },
+            }
+          },
+          _ => {
+            self.error(format!("expected unsuffixed positive integer but found `{}`",
+                pprust::token_to_string(&self.token)));
+            None
           }
-        } else {
-          self.error(format!("expected unsuffixed integer but found `{}`",
-              pprust::token_to_string(&self.token)));
-          None
         }
       },
       Token::BinOp(token::And) => {
@@ -366,19 +668,21 @@ impl<'a> Parser<'a> {
           Some(name) => name,
           None => return None,
         };
-        Some(node::RefValue(name))
+        Some((node::RefValue(name), None))
+      },
+      Token::OpenDelim(token::Bracket) => {
+        self.bump();
+        self.parse_list_value()
       },
       token::Ident(ident, _) => {
         self.bump();
         match token::get_ident(ident).get() {
-          "true"  => Some(node::BoolValue(true)),
-          "false" => Some(node::BoolValue(false)),
-          other   => {
-            self.error(format!(
-              "expected attribute value but found identifier `{}`",
-              other));
-            None
-          }
+          "true"  => Some((node::BoolValue(true), None)),
+          "false" => Some((node::BoolValue(false), None)),
+          other   => match self.constants.get(&other.to_string()) {
+            Some(&(ref value, unit)) => Some((value.clone(), unit)),
+            None => Some((node::IdentValue(other.to_string()), None)),
+          },
         }
       },
       ref other => {
@@ -389,6 +693,60 @@ impl<'a> Parser<'a> {
     }
   }
 
+  /// Parses a comma-separated list of attribute values up to and including the
+  /// closing bracket. All elements of the list must be of the same kind and
+  /// unit.
+  fn parse_list_value(&mut self)
+      -> Option<(node::AttributeValue, Option<node::Unit>)> {
+    let mut values: Vec<node::AttributeValue> = vec!();
+    let mut list_unit = None;
+    loop {
+      //  we're here
+      //  |
+      //  v
+      // [VAL, VAL]
+      if self.token == token::CloseDelim(token::Bracket) {
+        self.bump();
+        break;
+      }
+
+      let value_span = self.span;
+      let (value, unit) = match self.parse_attribute_value() {
+        Some(value) => value,
+        None => return None,
+      };
+
+      if values.len() > 0 && !values[0].is_same_kind(&value) {
+        self.sess.span_diagnostic.span_err(value_span,
+            "list elements must be of the same type");
+        return None;
+      }
+      if values.len() > 0 && list_unit != unit {
+        self.sess.span_diagnostic.span_err(value_span,
+            "list elements must be of the same unit");
+        return None;
+      }
+      list_unit = unit;
+      values.push(value);
+
+      //     we're here
+      //     |
+      //     v
+      // [VAL, VAL]
+      match self.token {
+        token::Comma => { self.bump(); },
+        token::CloseDelim(token::Bracket) => (),
+        ref other => {
+          self.error(format!("expected `,` or `]` but found `{}`",
+              pprust::token_to_string(other)));
+          return None;
+        }
+      }
+    }
+
+    Some((node::ListValue(values), list_unit))
+  }
+
   fn error(&self, m: String) {
     self.sess.span_diagnostic.span_err(self.span, m.as_slice());
   }
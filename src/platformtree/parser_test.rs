@@ -13,7 +13,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use test_helpers::{fails_to_parse, with_parsed, with_parsed_node};
+use std::io::{File, TempDir};
+
+use node;
+use parser::{Parser, read_file_tts};
+use test_helpers::{fails_to_parse, with_ext_ctxt, with_parsed, with_parsed_node};
 
 #[test]
 fn parse_anonymous_node() {
@@ -103,6 +107,106 @@ fn parse_ref_attribute() {
   });
 }
 
+#[test]
+fn parse_ident_attribute() {
+  with_parsed_node("root", "test@root { key = handler; flag = true; }", |node| {
+    assert!(node.get_ident_attr("key") == Some("handler".to_string()));
+    assert!(node.get_bool_attr("flag") == Some(true));
+  });
+}
+
+#[test]
+fn parse_list_attributes() {
+  with_parsed_node("root", "test@root {
+      a = [1, 2, 3];
+      b = [\"x\", \"y\",];
+      c = [&p1, &p2];
+      d = [];
+    }", |node| {
+    assert!(node.get_int_list_attr("a")    == Some(vec!(1, 2, 3)));
+    assert!(node.get_string_list_attr("b") ==
+        Some(vec!("x".to_string(), "y".to_string())));
+    assert!(node.get_ref_list_attr("c")    ==
+        Some(vec!("p1".to_string(), "p2".to_string())));
+    assert!(node.get_int_list_attr("d")    == Some(vec!()));
+    assert!(node.get_ref_list_attr("a")    == None);
+  });
+}
+
+#[test]
+fn parse_nested_list_attribute() {
+  with_parsed_node("root", "test@root { matrix = [[1, 2], [3, 4]]; }", |node| {
+    let rows = node.get_list_attr("matrix").unwrap();
+    assert!(rows.len() == 2);
+  });
+}
+
+#[test]
+fn fails_to_parse_malformed_list_attribute() {
+  fails_to_parse("test@root { k = [1, 2; }");
+  fails_to_parse("test@root { k = [1 2]; }");
+  fails_to_parse("test@root { k = [1, \"a\"]; }");
+  fails_to_parse("test@root { k = [&a, 1]; }");
+}
+
+#[test]
+fn parse_unit_suffixed_attributes() {
+  with_parsed_node("root", "test@root {
+      freq = 12MHz;
+      slow = 32kHz;
+      delay = 5ms;
+      timeout = 2s;
+      speed = 9600baud;
+      delays = [1us, 2ms];
+    }", |node| {
+    assert!(node.get_int_attr("freq")    == Some(12_000_000));
+    assert!(node.get_int_attr("slow")    == Some(32_000));
+    assert!(node.get_int_attr("delay")   == Some(5_000));
+    assert!(node.get_int_attr("timeout") == Some(2_000_000));
+    assert!(node.get_int_attr("speed")   == Some(9600));
+    assert!(node.get_int_list_attr("delays") == Some(vec!(1, 2_000)));
+    assert!(node.get_attr("freq").unit   == Some(node::Frequency));
+    assert!(node.get_attr("delays").unit == Some(node::Time));
+    assert!(node.get_attr("speed").unit  == Some(node::BaudRate));
+  });
+}
+
+#[test]
+fn fails_to_parse_bad_units() {
+  fails_to_parse("test@root { k = 10furlongs; }");
+  fails_to_parse("test@root { k = [1ms, 2Hz]; }");
+  fails_to_parse("test@root { k = [1ms, 2]; }");
+}
+
+#[test]
+fn parse_constants() {
+  with_parsed_node("root", "
+    const XTAL = 12MHz;
+    const PINS = [1, 2];
+    const FREQ = XTAL;
+    test@root {
+      freq = FREQ;
+      pins = PINS;
+      handler = on_event;
+    }", |node| {
+    assert!(node.get_int_attr("freq") == Some(12_000_000));
+    assert!(node.get_attr("freq").unit == Some(node::Frequency));
+    assert!(node.get_int_list_attr("pins") == Some(vec!(1, 2)));
+    assert!(node.get_ident_attr("handler") == Some("on_event".to_string()));
+  });
+}
+
+#[test]
+fn fails_to_parse_duplicate_constants() {
+  fails_to_parse("const A = 1; const A = 2; test@root;");
+  fails_to_parse("const A 1; test@root;");
+}
+
+#[test]
+fn fails_to_parse_unknown_constant_reference() {
+  fails_to_parse("const A = B; test@root;");
+}
+
 #[test]
 fn fails_to_parse_duplicate_attributes() {
   fails_to_parse("test@root { a = 1; a = \"2\"; }");
@@ -112,7 +216,6 @@ fn fails_to_parse_duplicate_attributes() {
 fn fails_to_parse_malformed_attibute() {
   fails_to_parse("test@root { k = \"value\" }");
   fails_to_parse("test@root { 1 = \"value\"; }");
-  fails_to_parse("test@root { k = v; }");
   fails_to_parse("test@root { k = 10u8; }");
   fails_to_parse("test@root { k = 10i8; }");
   fails_to_parse("test@root { k = -42; }");
@@ -153,3 +256,78 @@ fn tracks_nodes_by_name() {
 fn fails_to_parse_duplicate_node_names() {
   fails_to_parse("duplicate@root { duplicate@child; }");
 }
+
+#[test]
+fn parse_extension_overrides_attributes() {
+  with_parsed("
+    root { a = 1; b = \"x\"; }
+    extend root { a = 2; c = 3; }", |_, _, pt| {
+    let node = pt.get_by_path("root").unwrap();
+    assert!(node.get_int_attr("a")    == Some(2));
+    assert!(node.get_string_attr("b") == Some("x".to_string()));
+    assert!(node.get_int_attr("c")    == Some(3));
+  });
+}
+
+#[test]
+fn parse_extension_merges_subnodes() {
+  with_parsed("
+    root { sub { a = 1; } }
+    extend root { sub { b = 2; } other@new; }", |_, _, pt| {
+    let node = pt.get_by_path("root").unwrap();
+    assert!(node.subnodes().len() == 2);
+    let sub = node.get_by_path("sub").unwrap();
+    assert!(sub.get_int_attr("a") == Some(1));
+    assert!(sub.get_int_attr("b") == Some(2));
+    assert!(pt.get_by_name("other").unwrap().full_path() ==
+        "root::new".to_string());
+  });
+}
+
+#[test]
+fn parse_extension_moves_named_node() {
+  with_parsed("
+    mcu { gpio { 1 { led1@18 { direction = \"out\"; } } } }
+    extend mcu { gpio { 1 { led1@21 { direction = \"out\"; } } } }", |_, _, pt| {
+    let led = pt.get_by_name("led1").unwrap();
+    assert!(led.path == "21".to_string());
+    let port = pt.get_by_path("mcu").unwrap()
+        .get_by_path("gpio").unwrap()
+        .get_by_path("1").unwrap();
+    assert!(port.subnodes().len() == 1);
+    assert!(port.get_by_path("18").is_none());
+  });
+}
+
+#[test]
+fn fails_to_parse_bad_extension() {
+  fails_to_parse("extend root { a = 1; }");
+  fails_to_parse("root { sub@child; } extend root { other@child; }");
+  fails_to_parse("root; extend root { a = 1; a = 2; }");
+}
+
+#[test]
+fn parse_platformtree_from_file() {
+  let dir = TempDir::new("platformtree").unwrap();
+  let path = dir.path().join("board.pt");
+  File::create(&path).write_str("board@root { a = 1; }").unwrap();
+
+  with_ext_ctxt(|cx, failed| {
+    let tts = read_file_tts(cx.parse_sess(), &path).unwrap();
+    let pt = Parser::new(cx, tts.as_slice()).parse_platformtree().unwrap();
+    assert!(unsafe{*failed} == false);
+
+    let node = pt.get_by_name("board").unwrap();
+    assert!(node.get_int_attr("a") == Some(1));
+    assert!(cx.codemap().span_to_filename(node.name_span) ==
+        format!("{}", path.display()));
+  });
+}
+
+#[test]
+fn fails_to_read_missing_file() {
+  with_ext_ctxt(|cx, _| {
+    let path = Path::new("/nonexistent/board.pt");
+    assert!(read_file_tts(cx.parse_sess(), &path).is_err());
+  });
+}
@@ -63,6 +63,16 @@ pub fn with_parsed_node(name: &str, src: &str, block: |Rc<node::Node>|) {
 }
 
 pub fn with_parsed_tts(src: &str, block: |&mut ExtCtxt, *mut bool, Option<Rc<node::PlatformTree>>|) {
+  with_ext_ctxt(|cx, failptr| {
+    let tts = cx.parse_tts(src.to_string());
+    let pt = Parser::new(cx, tts.as_slice()).parse_platformtree();
+    block(cx, failptr, pt);
+  });
+}
+
+/// Yields an ExtCtxt set up for a `platformtree!` expansion and parser error
+/// state.
+pub fn with_ext_ctxt(block: |&mut ExtCtxt, *mut bool|) {
   let mut failed = false;
   let failptr = &mut failed as *mut bool;
   let ce = box CustomEmmiter::new(failptr);
@@ -84,11 +94,8 @@ pub fn with_parsed_tts(src: &str, block: |&mut ExtCtxt, *mut bool, Option<Rc<nod
       span: None,
     },
   });
-  let tts = cx.parse_tts(src.to_string());
-
-  let pt = Parser::new(&mut cx, tts.as_slice()).parse_platformtree();
 
-  block(&mut cx, failptr, pt);
+  block(&mut cx, failptr);
 }
 
 struct CustomEmmiter {
@@ -0,0 +1,97 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standalone Platform Tree validator.
+//!
+//! Parses a PT file, runs the builder verification and materialization the
+//! same way `platformtree!` does, and prints the generated code. Diagnostics go
+//! to stderr. Doesn't require a cross-compiler.
+//!
+//! Usage: `ptcheck [--quiet] <file.pt>`
+
+#![crate_name="ptcheck"]
+#![crate_type="bin"]
+
+extern crate platformtree;
+extern crate syntax;
+
+use std::io;
+use std::ops::Deref;
+use std::os;
+use syntax::codemap::{DUMMY_SP, ExpnInfo, NameAndSpan, MacroBang};
+use syntax::ext::base::ExtCtxt;
+use syntax::ext::expand::ExpansionConfig;
+use syntax::parse::new_parse_sess;
+use syntax::print::pprust;
+
+use platformtree::builder::Builder;
+use platformtree::parser::{Parser, read_file_tts};
+
+fn main() {
+  let args = os::args();
+  let (quiet, file) = match args.len() {
+    2 => (false, args[1].clone()),
+    3 if args[1].as_slice() == "--quiet" => (true, args[2].clone()),
+    _ => {
+      let _ = writeln!(&mut io::stderr(), "usage: {} [--quiet] <file.pt>",
+          args[0]);
+      os::set_exit_status(2);
+      return;
+    }
+  };
+
+  let sess = new_parse_sess();
+  let ecfg = ExpansionConfig {
+    crate_name: ("ptcheck").parse().unwrap(),
+    deriving_hash_type_parameter: false,
+    enable_quotes: true,
+    recursion_limit: 64,
+  };
+  let mut cx = ExtCtxt::new(&sess, Vec::new(), ecfg);
+  cx.bt_push(ExpnInfo {
+    call_site: DUMMY_SP,
+    callee: NameAndSpan {
+      name: "platformtree".to_string(),
+      format: MacroBang,
+      span: None,
+    },
+  });
+
+  let tts = match read_file_tts(&sess, &Path::new(file)) {
+    Ok(tts) => tts,
+    Err(msg) => {
+      sess.span_diagnostic.handler().err(msg.as_slice());
+      os::set_exit_status(1);
+      return;
+    }
+  };
+
+  let maybe_pt = Parser::new(&cx, tts.as_slice()).parse_platformtree();
+  let maybe_builder = maybe_pt.and_then(|pt| Builder::build(&mut cx, pt));
+
+  match maybe_builder {
+    Some(ref builder) if !sess.span_diagnostic.handler().has_errors() => {
+      if !quiet {
+        for item in builder.emit_items(&cx).iter() {
+          println!("{}", pprust::item_to_string(item.deref()));
+        }
+      }
+    },
+    _ => {
+      let _ = writeln!(&mut io::stderr(), "platform tree validation failed");
+      os::set_exit_status(1);
+    },
+  }
+}
@@ -0,0 +1,359 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Interrupt-driven UART with transmit and receive ring buffers.
+
+`BufferedUART` wraps any UART implementing `hal::uart::InterruptUART`. Its
+`handle_interrupt()` method must be called from the UART ISR (`isr_uart_0`
+etc., e.g. bound with the `on_rx` platform tree attribute), which moves data
+between the hardware and the buffers. Tasks writing to a full buffer or
+reading from an empty one block on a condition variable instead of spinning.
+*/
+
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+
+use drivers::chario::{CharIO, CharInput, InputError};
+use hal::cortex_m3::irq::NoInterrupts;
+use hal::uart::InterruptUART;
+use os::cond_var::CondVar;
+use util::ring_buf::RingBuf;
+use util::shared::Shared;
+
+/// A UART serviced from its interrupt handler.
+pub struct BufferedUART<'a, U> {
+  uart: U,
+  tx: Shared<RingBuf<'a>>,
+  rx: Shared<RingBuf<'a>>,
+  rx_error: Shared<Option<InputError>>,
+  tx_space: CondVar,
+  rx_data: CondVar,
+}
+
+impl<'a, U: InterruptUART> BufferedUART<'a, U> {
+  /// Creates a buffered UART using the given storage for its buffers and
+  /// enables the receive interrupts.
+  ///
+  /// Each buffer holds one byte less than its storage.
+  pub fn new(uart: U, tx_storage: &'a mut [u8], rx_storage: &'a mut [u8])
+      -> BufferedUART<'a, U> {
+    uart.set_rx_interrupt(true);
+    BufferedUART {
+      uart: uart,
+      tx: Shared::new(RingBuf::new(tx_storage)),
+      rx: Shared::new(RingBuf::new(rx_storage)),
+      rx_error: Shared::new(None),
+      tx_space: CondVar::new(),
+      rx_data: CondVar::new(),
+    }
+  }
+
+  /// Moves received characters into the receive buffer and buffered ones to
+  /// the transmitter. Must be called from the UART ISR.
+  pub fn handle_interrupt(&self) {
+    let crit = NoInterrupts::new();
+
+    let mut received = false;
+    {
+      let mut rx = self.rx.borrow(&crit);
+      let mut rx_error = self.rx_error.borrow(&crit);
+      loop {
+        match self.uart.try_getc() {
+          Ok(None) => break,
+          Ok(Some(c)) => if !rx.push(c as u8) {
+            *rx_error = Some(InputError::Overrun);
+          },
+          Err(e) => *rx_error = Some(e),
+        }
+        received = true;
+      }
+    }
+    if received {
+      self.rx_data.signal();
+    }
+
+    let mut sent = false;
+    {
+      let mut tx = self.tx.borrow(&crit);
+      while self.uart.writable() {
+        match tx.pop() {
+          Some(value) => {
+            self.uart.write_char(value);
+            sent = true;
+          },
+          None => {
+            self.uart.set_tx_interrupt(false);
+            break;
+          },
+        }
+      }
+    }
+    if sent {
+      self.tx_space.signal();
+    }
+  }
+
+  fn take_input(&self, crit: &NoInterrupts) -> Result<Option<char>, InputError> {
+    match self.rx_error.borrow(crit).take() {
+      Some(e) => Err(e),
+      None => Ok(self.rx.borrow(crit).pop().map(|c| c as char)),
+    }
+  }
+}
+
+impl<'a, U: InterruptUART> CharIO for BufferedUART<'a, U> {
+  fn putc(&self, value: char) {
+    loop {
+      let crit = NoInterrupts::new();
+      {
+        let mut tx = self.tx.borrow(&crit);
+        // Nothing is queued, so the transmitter may be idle and won't raise
+        // an interrupt on its own.
+        if tx.is_empty() && self.uart.writable() {
+          self.uart.write_char(value as u8);
+          return;
+        }
+        if tx.push(value as u8) {
+          self.uart.set_tx_interrupt(true);
+          return;
+        }
+      }
+      self.tx_space.wait_in(crit);
+    }
+  }
+}
+
+impl<'a, U: InterruptUART> CharInput for BufferedUART<'a, U> {
+  fn readable(&self) -> bool {
+    let crit = NoInterrupts::new();
+    let readable = self.rx_error.borrow(&crit).is_some() ||
+        !self.rx.borrow(&crit).is_empty();
+    readable
+  }
+
+  fn try_getc(&self) -> Result<Option<char>, InputError> {
+    let crit = NoInterrupts::new();
+    self.take_input(&crit)
+  }
+
+  fn getc(&self) -> Result<char, InputError> {
+    loop {
+      let crit = NoInterrupts::new();
+      match self.take_input(&crit) {
+        Ok(Some(c)) => return Ok(c),
+        Ok(None) => (),
+        Err(e) => return Err(e),
+      }
+      self.rx_data.wait_in(crit);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use core::cell::{Cell, RefCell};
+  use core::option::Option;
+  use core::option::Option::{Some, None};
+  use core::result::Result;
+  use core::result::Result::{Ok, Err};
+  use core::slice::SliceExt;
+
+  use drivers::buffered_uart::BufferedUART;
+  use drivers::chario::{CharIO, CharInput, InputError};
+  use hal::uart::InterruptUART;
+  use util::support::set_wfi_hook;
+
+  /// A UART that receives `input` and has room for `tx_free` characters.
+  struct MockUART {
+    input: &'static [Result<Option<char>, InputError>],
+    position: Cell<uint>,
+    tx_free: Cell<uint>,
+    sent: RefCell<[u8; 8]>,
+    sent_len: Cell<uint>,
+    tx_interrupt: Cell<bool>,
+    rx_interrupt: Cell<bool>,
+  }
+
+  impl MockUART {
+    fn new(input: &'static [Result<Option<char>, InputError>], tx_free: uint)
+        -> MockUART {
+      MockUART {
+        input: input,
+        position: Cell::new(0),
+        tx_free: Cell::new(tx_free),
+        sent: RefCell::new([0; 8]),
+        sent_len: Cell::new(0),
+        tx_interrupt: Cell::new(false),
+        rx_interrupt: Cell::new(false),
+      }
+    }
+
+    fn sent(&self, expected: &[u8]) -> bool {
+      self.sent.borrow().slice_to(self.sent_len.get()) == expected
+    }
+  }
+
+  impl CharInput for MockUART {
+    fn readable(&self) -> bool {
+      self.position.get() < self.input.len()
+    }
+
+    fn try_getc(&self) -> Result<Option<char>, InputError> {
+      let position = self.position.get();
+      if position == self.input.len() {
+        return Ok(None);
+      }
+      self.position.set(position + 1);
+      self.input[position]
+    }
+  }
+
+  impl InterruptUART for MockUART {
+    fn set_tx_interrupt(&self, enabled: bool) {
+      self.tx_interrupt.set(enabled);
+    }
+
+    fn set_rx_interrupt(&self, enabled: bool) {
+      self.rx_interrupt.set(enabled);
+    }
+
+    fn writable(&self) -> bool {
+      self.tx_free.get() > 0
+    }
+
+    fn write_char(&self, value: u8) {
+      assert!(self.writable());
+      self.tx_free.set(self.tx_free.get() - 1);
+      let len = self.sent_len.get();
+      self.sent.borrow_mut()[len] = value;
+      self.sent_len.set(len + 1);
+    }
+  }
+
+  /// Stands for the UART interrupt raised while waiting: the transmitter
+  /// drains and the handler runs.
+  fn interrupt(arg: *const ()) {
+    let uart = unsafe { &*(arg as *const BufferedUART<MockUART>) };
+    uart.uart.tx_free.set(8);
+    uart.handle_interrupt();
+  }
+
+  static NO_INPUT: [Result<Option<char>, InputError>; 0] = [];
+
+  #[test]
+  fn enables_rx_interrupt() {
+    let (mut tx, mut rx) = ([0u8; 4], [0u8; 4]);
+    let uart = BufferedUART::new(MockUART::new(&NO_INPUT, 0), &mut tx, &mut rx);
+    assert!(uart.uart.rx_interrupt.get());
+    assert!(!uart.readable());
+  }
+
+  #[test]
+  fn receives_characters_in_interrupt() {
+    static INPUT: [Result<Option<char>, InputError>; 2] =
+        [Ok(Some('a')), Ok(Some('b'))];
+    let (mut tx, mut rx) = ([0u8; 4], [0u8; 4]);
+    let uart = BufferedUART::new(MockUART::new(&INPUT, 0), &mut tx, &mut rx);
+    assert!(uart.try_getc() == Ok(None));
+
+    uart.handle_interrupt();
+    assert!(uart.readable());
+    assert!(uart.try_getc() == Ok(Some('a')));
+    assert!(uart.getc() == Ok('b'));
+    assert!(uart.try_getc() == Ok(None));
+  }
+
+  #[test]
+  fn reports_receive_errors_and_overruns() {
+    static INPUT: [Result<Option<char>, InputError>; 4] =
+        [Ok(Some('a')), Ok(Some('b')), Ok(Some('c')), Err(InputError::Parity)];
+    let (mut tx, mut rx) = ([0u8; 4], [0u8; 3]);
+    let uart = BufferedUART::new(MockUART::new(&INPUT, 0), &mut tx, &mut rx);
+
+    uart.handle_interrupt();
+    // the latest error is reported first, the buffered characters are kept
+    assert!(uart.try_getc() == Err(InputError::Parity));
+    assert!(uart.try_getc() == Ok(Some('a')));
+    assert!(uart.try_getc() == Ok(Some('b')));
+    assert!(uart.try_getc() == Ok(None));
+
+    static OVERRUN: [Result<Option<char>, InputError>; 3] =
+        [Ok(Some('a')), Ok(Some('b')), Ok(Some('c'))];
+    let (mut tx, mut rx) = ([0u8; 4], [0u8; 3]);
+    let uart = BufferedUART::new(MockUART::new(&OVERRUN, 0), &mut tx, &mut rx);
+    uart.handle_interrupt();
+    assert!(uart.getc() == Err(InputError::Overrun));
+    assert!(uart.getc() == Ok('a'));
+    assert!(uart.getc() == Ok('b'));
+  }
+
+  #[test]
+  fn writes_directly_when_idle() {
+    let (mut tx, mut rx) = ([0u8; 4], [0u8; 4]);
+    let uart = BufferedUART::new(MockUART::new(&NO_INPUT, 1), &mut tx, &mut rx);
+    uart.putc('x');
+    assert!(uart.uart.sent(b"x"));
+    assert!(!uart.uart.tx_interrupt.get());
+  }
+
+  #[test]
+  fn sends_buffered_characters_in_interrupt() {
+    let (mut tx, mut rx) = ([0u8; 4], [0u8; 4]);
+    let uart = BufferedUART::new(MockUART::new(&NO_INPUT, 0), &mut tx, &mut rx);
+    uart.putc('a');
+    uart.putc('b');
+    assert!(uart.uart.sent(b""));
+    assert!(uart.uart.tx_interrupt.get());
+
+    uart.uart.tx_free.set(1);
+    uart.handle_interrupt();
+    assert!(uart.uart.sent(b"a"));
+    assert!(uart.uart.tx_interrupt.get());
+
+    uart.uart.tx_free.set(8);
+    uart.handle_interrupt();
+    assert!(uart.uart.sent(b"ab"));
+    assert!(!uart.uart.tx_interrupt.get());
+  }
+
+  #[test]
+  fn putc_waits_for_space_in_full_buffer() {
+    let (mut tx, mut rx) = ([0u8; 3], [0u8; 4]);
+    let uart = BufferedUART::new(MockUART::new(&NO_INPUT, 0), &mut tx, &mut rx);
+    uart.putc('a');
+    uart.putc('b');
+
+    set_wfi_hook(Some((interrupt as fn(*const ()),
+        &uart as *const BufferedUART<MockUART> as *const ())));
+    uart.putc('c');
+    set_wfi_hook(None);
+    assert!(uart.uart.sent(b"abc"));
+  }
+
+  #[test]
+  fn getc_waits_for_input() {
+    static INPUT: [Result<Option<char>, InputError>; 1] = [Ok(Some('z'))];
+    let (mut tx, mut rx) = ([0u8; 4], [0u8; 4]);
+    let uart = BufferedUART::new(MockUART::new(&INPUT, 0), &mut tx, &mut rx);
+
+    set_wfi_hook(Some((interrupt as fn(*const ()),
+        &uart as *const BufferedUART<MockUART> as *const ())));
+    assert!(uart.getc() == Ok('z'));
+    set_wfi_hook(None);
+  }
+}
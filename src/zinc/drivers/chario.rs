@@ -13,8 +13,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Generic char output trait.
+//! Generic char output and input traits.
 
+use core::option::Option;
+use core::option::Option::Some;
+use core::result::Result;
+use core::result::Result::{Ok, Err};
 use core::str::{Str, StrExt};
 use core::slice::SliceExt;
 use core::iter::range;
@@ -65,11 +69,45 @@ pub trait CharIO {
   }
 }
 
+/// Errors detected while receiving a character.
+#[derive(Copy, PartialEq)]
+pub enum InputError {
+  /// The stop bit wasn't found where expected.
+  Framing,
+  /// The parity bit didn't match.
+  Parity,
+  /// The receive buffer was full and incoming data was lost.
+  Overrun,
+}
+
+/// CharInput provides interface for reading characters.
+///
+/// When an error is reported, the character received along with it is
+/// discarded.
+pub trait CharInput {
+  /// Returns true if a character can be read without blocking.
+  fn readable(&self) -> bool;
+
+  /// Reads a character if one is available, returns `Ok(None)` otherwise.
+  fn try_getc(&self) -> Result<Option<char>, InputError>;
+
+  /// Waits for a character and reads it.
+  fn getc(&self) -> Result<char, InputError> {
+    loop {
+      match self.try_getc() {
+        Ok(Some(c)) => return Ok(c),
+        Ok(None) => (),
+        Err(e) => return Err(e),
+      }
+    }
+  }
+}
+
 #[cfg(test)]
 pub mod test {
-  use core::cell::RefCell;
+  use core::cell::{Cell, RefCell};
 
-  use drivers::chario::CharIO;
+  use drivers::chario::{CharIO, CharInput, InputError};
 
   #[derive(Copy)]
   pub struct TestCharIOData {
@@ -110,6 +148,46 @@ pub mod test {
     }
   }
 
+  pub struct TestCharInput {
+    input: &'static [Result<Option<char>, InputError>],
+    position: Cell<uint>,
+  }
+
+  impl CharInput for TestCharInput {
+    fn readable(&self) -> bool {
+      match self.input[self.position.get()] {
+        Ok(None) => false,
+        _ => true,
+      }
+    }
+
+    fn try_getc(&self) -> Result<Option<char>, InputError> {
+      let position = self.position.get();
+      self.position.set(position + 1);
+      self.input[position]
+    }
+  }
+
+  #[test]
+  fn getc_should_wait_for_a_char() {
+    static INPUT: [Result<Option<char>, InputError>; 3] =
+        [Ok(None), Ok(None), Ok(Some('a'))];
+    let io = TestCharInput { input: &INPUT, position: Cell::new(0) };
+    assert!(!io.readable());
+    assert!(io.getc() == Ok('a'));
+    assert!(io.position.get() == 3);
+  }
+
+  #[test]
+  fn getc_should_report_errors() {
+    static INPUT: [Result<Option<char>, InputError>; 3] =
+        [Ok(None), Err(InputError::Parity), Ok(Some('b'))];
+    let io = TestCharInput { input: &INPUT, position: Cell::new(0) };
+    assert!(io.getc() == Err(InputError::Parity));
+    assert!(io.readable());
+    assert!(io.getc() == Ok('b'));
+  }
+
   #[test]
   fn putc_should_store_a_char() {
     let io = TestCharIO::new();
@@ -16,6 +16,7 @@
 //! Driver for DHT22.
 
 use core::iter::range;
+use core::num::wrapping::WrappingOps;
 use core::option::Option::{self, Some, None};
 
 use hal::pin::Gpio;
@@ -25,6 +26,7 @@ use hal::pin::GpioDirection::In;
 use hal::pin::GpioDirection::Out;
 use hal::pin::GpioLevel;
 use hal::timer::Timer;
+use util::time::Duration;
 
 /// Basic DHT22 driver ported over from Arduino example.
 pub struct DHT22<'a, T:'a, P:'a> {
@@ -57,9 +59,9 @@ impl<'a, T: Timer, P: Gpio> DHT22<'a, T, P> {
 
     self.gpio.set_direction(Out);
     self.gpio.set_low();
-    self.timer.wait_ms(20);
+    self.timer.delay(Duration::from_ms(20));
     self.gpio.set_high();
-    self.timer.wait_us(40);
+    self.timer.delay(Duration::from_us(40));
     self.gpio.set_direction(In);
 
     if !self.wait_sync() {
@@ -67,17 +69,18 @@ impl<'a, T: Timer, P: Gpio> DHT22<'a, T, P> {
     }
 
     for _ in range(0u, 40) {
-      if !self.wait_while(Low, 80) {
+      if !self.wait_while(Low, Duration::from_us(80)) {
         return None
       }
 
-      let t = self.timer.get_counter();
+      let start = self.timer.get_counter();
 
-      if !self.wait_while(High, 80) {
+      if !self.wait_while(High, Duration::from_us(80)) {
         return None
       }
 
-      if self.timer.get_counter() - t > 40 {
+      // a high pulse is 26-28us for a 0 and 70us for a 1
+      if self.elapsed(start) > Duration::from_us(40) {
         buffer[idx] |= mask;
       }
 
@@ -106,19 +109,25 @@ impl<'a, T: Timer, P: Gpio> DHT22<'a, T, P> {
     }
   }
 
+  /// Returns the time elapsed since the timer counter read `start`, the
+  /// counter may have rolled over since.
+  fn elapsed(&self, start: u32) -> Duration {
+    Duration::from_us(self.timer.get_counter().wrapping_sub(start) as u64)
+  }
+
   fn wait_sync(&self) -> bool {
-    if !self.wait_while(Low, 80) {
+    if !self.wait_while(Low, Duration::from_us(80)) {
       false
-    } else if !self.wait_while(High, 100) {
+    } else if !self.wait_while(High, Duration::from_us(100)) {
       false
     } else {
       true
     }
   }
 
-  fn wait_while(&self, level: GpioLevel, timeout: uint) -> bool {
-    for _ in range(0, timeout / 10) {
-      self.timer.wait_us(10);
+  fn wait_while(&self, level: GpioLevel, timeout: Duration) -> bool {
+    for _ in range(0, timeout.as_us() / 10) {
+      self.timer.delay(Duration::from_us(10));
       if self.gpio.level() != level {
         return true;
       }
@@ -22,6 +22,7 @@ use node;
 pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(build_dht22 as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
   node.mutator.set(Some(mutate_pin as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+  node.schema.set(Some(dht22_schema as fn() -> node::NodeSchema));
 
   let pin_node_name = node.get_ref_attr("pin").unwrap();
   let pin_node = builder.pt().get_by_name(pin_node_name.as_slice()).unwrap();
@@ -32,6 +33,18 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   add_node_dependency(&node, &timer_node);
 }
 
+fn dht22_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("pin", node::RefAttribute,
+          node::Required, node::Unrestricted),
+      node::AttributeSchema::new("timer", node::RefAttribute,
+          node::Required, node::Unrestricted),
+    ),
+    subnodes: Some(vec!()),
+  }
+}
+
 fn mutate_pin(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   let pin_node_name = node.get_ref_attr("pin").unwrap();
   let pin_node = builder.pt().get_by_name(pin_node_name.as_slice()).unwrap();
@@ -32,6 +32,7 @@ use super::font_small_7;
 use super::LCD;
 use drivers::chario::CharIO;
 use hal::timer::Timer;
+use util::time::Duration;
 use hal::pin::Gpio;
 use hal::spi::Spi;
 
@@ -78,9 +79,9 @@ impl<'a, S: Spi, T: Timer, P: Gpio> C12332<'a, S, T, P> {
     self.dc.set_low();
     self.cs.set_high();
     self.reset.set_low();
-    self.timer.wait_us(50);
+    self.timer.delay(Duration::from_us(50));
     self.reset.set_high();
-    self.timer.wait_ms(5);
+    self.timer.delay(Duration::from_ms(5));
 
     self.wr_cmd(0xAE);   //  display off
     self.wr_cmd(0xA2);   //  bias voltage
@@ -20,6 +20,7 @@ use core::prelude::*;
 use drivers::chario::CharIO;
 use hal::pin::Gpio;
 use hal::timer::Timer;
+use util::time::Duration;
 
 /// HD44780U driver context
 pub struct Hd44780u<'a> {
@@ -71,27 +72,27 @@ impl<'a> Hd44780u<'a> {
 
     // We need to wait at least 40ms after the chip is powered before we can
     // talk to it. Assume that the power just went on and wait.
-    self.timer.wait_ms(50);
+    self.timer.delay(Duration::from_ms(50));
 
     // Set interface to 8bit
     self.write_nibble(0b0011);
 
     // Wait 4.1ms
-    self.timer.wait_us(4100);
+    self.timer.delay(Duration::from_us(4100));
 
     // Set interface to 8bit
     self.write_nibble(0b0011);
 
     // Wait 100us
-    self.timer.wait_us(100);
+    self.timer.delay(Duration::from_us(100));
 
     // Set interface to 8bit (for the last time...)
     self.write_nibble(0b0011);
-    self.timer.wait_us(100);
+    self.timer.delay(Duration::from_us(100));
 
     // We can now finally switch to 4bit
     self.write_nibble(0b0010);
-    self.timer.wait_us(100);
+    self.timer.delay(Duration::from_us(100));
 
     // Set function
     self.function_set(two_lines, font);
@@ -116,7 +117,7 @@ impl<'a> Hd44780u<'a> {
     self.instruction(0b0001);
     // I can't find in the datasheet how long this is supposed to take but it
     // seems to take at least a few ms.
-    self.timer.wait_ms(5);
+    self.timer.delay(Duration::from_ms(5));
   }
 
   /// Return the cursor to the left of the first line of the display. Also
@@ -125,7 +126,7 @@ impl<'a> Hd44780u<'a> {
   pub fn return_home(&self) {
     self.instruction(0b10);
     // This takes 1.52ms
-    self.timer.wait_ms(2);
+    self.timer.delay(Duration::from_ms(2));
   }
 
   /// Set the writing direction and optional display shifting.
@@ -270,7 +271,7 @@ impl<'a> Hd44780u<'a> {
     // Commands take *at least* 37us to execute, so default to that. If the
     // current instruction needs to delay for more than that the calling
     // function will wait some more.
-    self.timer.wait_us(40);
+    self.timer.delay(Duration::from_us(40));
   }
 
   /// Send 8bit of data
@@ -280,7 +281,7 @@ impl<'a> Hd44780u<'a> {
     self.write_nibble(data >> 4);
     self.write_nibble(data);
     // Data access take 37us
-    self.timer.wait_us(40);
+    self.timer.delay(Duration::from_us(40));
   }
 
   /// Write a 4bit value to the parallel interface.
@@ -309,7 +310,7 @@ impl<'a> Hd44780u<'a> {
     // Pulse the EN wire to notify the controller.
     self.en.set_high();
     // We need to assert EN for at least 450ns
-    self.timer.wait_us(1);
+    self.timer.delay(Duration::from_us(1));
     self.en.set_low();
   }
 }
@@ -20,6 +20,7 @@ use core::iter::range;
 use super::LCD;
 use drivers::chario::CharIO;
 use hal::timer::Timer;
+use util::time::Duration;
 use hal::pin::Gpio;
 use hal::spi::Spi;
 
@@ -60,7 +61,7 @@ impl<'a, S: Spi, T: Timer, P: Gpio> ILI9341<'a, S, T, P> {
     self.dc.set_high();
 
     self.reset.set_low();
-    self.timer.wait_ms(10);
+    self.timer.delay(Duration::from_ms(10));
     self.reset.set_high();
 
     self.verify_id(); // this fails :)
@@ -213,7 +214,7 @@ impl<'a, S: Spi, T: Timer, P: Gpio> ILI9341<'a, S, T, P> {
     self.write_data(0x0F);
 
     self.send_cmd(0x11);
-    self.timer.wait_ms(120);
+    self.timer.delay(Duration::from_ms(120));
 
     self.send_cmd(0x29);
     self.send_cmd(0x2c);
@@ -18,4 +18,5 @@
 pub mod lcd;
 pub mod bluenrg;
 pub mod chario;
+pub mod buffered_uart;
 pub mod dht22;
@@ -0,0 +1,49 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+ADC interface.
+
+ADCConf is a MCU-specific struct.
+
+ADC can perform a single blocking conversion, or convert a set of channels
+continuously. Results of continuous conversions are passed to a callback from
+`process()`, that should be called from the ADC interrupt handler, or
+periodically from the main loop.
+*/
+
+/// Continuous conversion callback, called with the channel number and the
+/// conversion result.
+pub type Callback = fn(u8, u16);
+
+/// ADC trait.
+pub trait ADC {
+  /// Returns the number of significant bits in conversion results.
+  fn resolution(&self) -> u8;
+
+  /// Performs a single conversion on the channel and returns the result.
+  ///
+  /// Must not be called while continuous conversion is running.
+  fn read(&self, channel: u8) -> u16;
+
+  /// Starts continuous conversion of the given channels.
+  fn start_continuous(&self, channels: &[u8], callback: Callback);
+
+  /// Stops continuous conversion.
+  fn stop_continuous(&self);
+
+  /// Passes the finished continuous conversion results to the callback.
+  fn process(&self);
+}
@@ -0,0 +1,255 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+CAN bus interface.
+
+Frames carry up to 8 data bytes under an 11-bit standard or a 29-bit extended
+identifier. Controllers only receive the frames that pass their acceptance
+filters, which are configured in a MCU-specific way.
+
+A node that sees too many errors becomes error passive and eventually goes bus
+off, disconnecting itself from the bus until it's recovered.
+*/
+
+use core::cmp::min;
+use core::iter::range;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::slice::SliceExt;
+
+/// Frame identifier.
+#[derive(Copy, PartialEq)]
+pub enum Id {
+  /// 11-bit standard identifier.
+  Standard(u16),
+  /// 29-bit extended identifier.
+  Extended(u32),
+}
+
+/// A CAN frame.
+#[derive(Copy)]
+pub struct Frame {
+  /// Identifier, lower ones win arbitration.
+  pub id: Id,
+  /// True for a remote frame, which requests data and carries none.
+  pub remote: bool,
+  /// Data length, 0 to 8.
+  pub len: u8,
+  /// Data, only the first `len` bytes are valid.
+  pub data: [u8; 8],
+}
+
+impl Frame {
+  /// Creates a data frame with the first 8 bytes of `data`.
+  pub fn new(id: Id, data: &[u8]) -> Frame {
+    let len = min(data.len(), 8);
+    let mut frame = Frame {
+      id: id,
+      remote: false,
+      len: len as u8,
+      data: [0; 8],
+    };
+    for i in range(0, len) {
+      frame.data[i] = data[i];
+    }
+    frame
+  }
+
+  /// Creates a remote frame requesting `len` bytes.
+  pub fn new_remote(id: Id, len: u8) -> Frame {
+    Frame {
+      id: id,
+      remote: true,
+      len: if len > 8 { 8 } else { len },
+      data: [0; 8],
+    }
+  }
+
+  /// Returns the data of the frame.
+  pub fn data(&self) -> &[u8] {
+    self.data.slice_to(self.len as uint)
+  }
+}
+
+/// Acceptance filter entry.
+#[derive(Copy)]
+pub enum Filter {
+  /// Accepts the frames with the given identifier.
+  Id(Id),
+  /// Accepts the standard frames with identifiers in the inclusive range.
+  StandardRange(u16, u16),
+  /// Accepts the extended frames with identifiers in the inclusive range.
+  ExtendedRange(u32, u32),
+}
+
+/// Transmission error.
+#[derive(Copy, PartialEq)]
+pub enum Error {
+  /// All transmit buffers are in use.
+  Busy,
+  /// The controller is bus off.
+  BusOff,
+}
+
+/// Error state of the controller, as defined by the CAN specification.
+#[derive(Copy, PartialEq)]
+pub enum BusState {
+  /// Both error counters are below 128, errors are signalled actively.
+  ErrorActive,
+  /// An error counter reached 128, errors are only signalled passively.
+  ErrorPassive,
+  /// The transmit error counter overflowed, the controller is off the bus.
+  BusOff,
+}
+
+/// Transmit and receive error counters.
+#[derive(Copy, PartialEq)]
+pub struct ErrorCounters {
+  /// Transmit error counter.
+  pub transmit: u8,
+  /// Receive error counter.
+  pub receive: u8,
+}
+
+/// CAN controller trait.
+pub trait CAN {
+  /// Queues `frame` for transmission.
+  fn send(&self, frame: &Frame) -> Result<(), Error>;
+
+  /// Returns the next received frame, if any.
+  fn receive(&self) -> Option<Frame>;
+
+  /// Returns the error state of the controller.
+  fn state(&self) -> BusState;
+
+  /// Returns the error counters.
+  fn error_counters(&self) -> ErrorCounters;
+
+  /// Rejoins the bus after going bus off. The controller becomes active once
+  /// it has seen 128 sequences of 11 recessive bits.
+  fn recover(&self);
+
+  /// Enables or disables the loopback mode, where sent frames are received
+  /// back without needing another node to acknowledge them.
+  fn set_loopback(&self, enabled: bool);
+}
+
+/// Bit timing, in time quanta of `prescaler` controller clock cycles.
+///
+/// A bit starts with a one quantum sync segment, and is sampled between
+/// `segment1` and `segment2`.
+#[derive(Copy, PartialEq)]
+pub struct BitTiming {
+  /// Clock cycles per time quantum.
+  pub prescaler: u32,
+  /// Time quanta before the sample point, sync segment excluded.
+  pub segment1: u8,
+  /// Time quanta after the sample point.
+  pub segment2: u8,
+  /// Synchronization jump width, in time quanta.
+  pub sjw: u8,
+}
+
+impl BitTiming {
+  /// Calculates the timing for `bitrate` from a `clock` in Hz, with the
+  /// sample point closest to 87.5% of the bit. Controllers limit segment 1 to
+  /// `max_segment1`, segment 2 to `max_segment2` and the prescaler to
+  /// `max_prescaler`.
+  ///
+  /// Returns None if the bitrate can't be derived exactly from the clock.
+  pub fn calculate(clock: u32, bitrate: u32, max_prescaler: u32,
+      max_segment1: u8, max_segment2: u8) -> Option<BitTiming> {
+    if bitrate == 0 {
+      return None;
+    }
+    // more quanta per bit allow finer placement of the sample point
+    let max_quanta = 1 + max_segment1 as u32 + max_segment2 as u32;
+    let mut quanta = if max_quanta > 25 { 25 } else { max_quanta };
+    while quanta >= 8 {
+      let cycles = bitrate * quanta;
+      if clock % cycles == 0 && clock / cycles <= max_prescaler {
+        let sample = (quanta * 7 + 4) / 8;
+        let segment1 = sample - 1;
+        let segment2 = quanta - sample;
+        if segment1 <= max_segment1 as u32 && segment2 <= max_segment2 as u32 {
+          return Some(BitTiming {
+            prescaler: clock / cycles,
+            segment1: segment1 as u8,
+            segment2: segment2 as u8,
+            sjw: if segment2 > 4 { 4 } else { segment2 as u8 },
+          });
+        }
+      }
+      quanta -= 1;
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use core::option::Option::{Some, None};
+
+  use core::slice::SliceExt;
+
+  use hal::can::{BitTiming, Frame, Id};
+
+  #[test]
+  fn calculates_bit_timing() {
+    // 500 kbit/s from 24 MHz: 16 quanta of 3 cycles, 24 quanta would need a
+    // longer segment 1
+    let timing = BitTiming::calculate(24000000, 500000, 1024, 16, 8).unwrap();
+    assert!(timing.prescaler == 3);
+    assert!(timing.segment1 == 13);
+    assert!(timing.segment2 == 2);
+    assert!(timing.sjw == 2);
+  }
+
+  #[test]
+  fn respects_segment_limits() {
+    // 1 Mbit/s from 18 MHz: 18 quanta, sampled at 16
+    let timing = BitTiming::calculate(18000000, 1000000, 1024, 16, 8).unwrap();
+    assert!(timing.prescaler == 1);
+    assert!(timing.segment1 == 15);
+    assert!(timing.segment2 == 2);
+  }
+
+  #[test]
+  fn rejects_inexact_bitrates() {
+    assert!(BitTiming::calculate(24000000, 333333, 1024, 16, 8) == None);
+    assert!(BitTiming::calculate(24000000, 0, 1024, 16, 8) == None);
+    // needs a prescaler of at least 192
+    assert!(BitTiming::calculate(24000000, 5000, 100, 16, 8) == None);
+  }
+
+  #[test]
+  fn builds_frames() {
+    let frame = Frame::new(Id::Standard(0x123), &[1, 2, 3]);
+    assert!(frame.len == 3);
+    assert!(frame.data().len() == 3);
+    assert!(frame.data()[0] == 1 && frame.data()[2] == 3);
+    assert!(!frame.remote);
+
+    let frame = Frame::new(Id::Extended(0x1234567), &[0; 12]);
+    assert!(frame.len == 8);
+
+    let frame = Frame::new_remote(Id::Standard(0x7ff), 4);
+    assert!(frame.remote);
+    assert!(frame.len == 4);
+    assert!(frame.data().len() == 4);
+  }
+}
@@ -57,8 +57,9 @@ unsafe fn disable_irqs() {
   irq_level += 1;
 }
 
+/// Host tests have no interrupts to disable (mock).
 #[cfg(test)]
-unsafe fn disable_irqs() { unimplemented!() }
+unsafe fn disable_irqs() { }
 
 /// Enables all interrupts except Reset, HardFault, and NMI.
 #[cfg(not(test))]
@@ -75,5 +76,6 @@ unsafe fn enable_irqs() {
   }
 }
 
+/// Host tests have no interrupts to enable (mock).
 #[cfg(test)]
-unsafe fn enable_irqs() { unimplemented!() }
+unsafe fn enable_irqs() { }
@@ -39,6 +39,11 @@ pub fn set_pendsv(val: bool) {
   }
 }
 
+/// Selects deep sleep instead of sleep as the low power mode entered by WFI.
+pub fn set_sleep_deep(val: bool) {
+  get_reg().scr.set_sleepdeep(val);
+}
+
 mod reg {
   use util::volatile_cell::VolatileCell;
   use core::ops::Drop;
@@ -0,0 +1,107 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+DMA interface.
+
+DMA controllers are MCU-specific, they hand out channels that implement the
+`Channel` trait and are released when dropped. A channel runs one transfer at a
+time, the optional callback is called from `process()` of the controller when
+the transfer is over, that should be called from the DMA interrupt handler.
+
+Memory taking part in a transfer must stay valid until the transfer is over,
+the channel can't check that for you.
+*/
+
+use core::option::Option;
+
+#[path="../util/wait_for.rs"] mod wait_for;
+
+/// Direction of a transfer.
+#[derive(Copy)]
+pub enum Direction {
+  /// Both addresses are incremented, the transfer starts immediately.
+  MemoryToMemory,
+  /// The destination address is fixed, the transfer is paced by the
+  /// peripheral request line.
+  MemoryToPeripheral,
+  /// The source address is fixed, the transfer is paced by the peripheral
+  /// request line.
+  PeripheralToMemory,
+}
+
+/// Width of a single transferred element.
+#[derive(Copy)]
+pub enum Width {
+  /// 8 bits.
+  Byte,
+  /// 16 bits.
+  HalfWord,
+  /// 32 bits.
+  Word,
+}
+
+impl Width {
+  /// Returns the element size in bytes.
+  pub fn bytes(self) -> u32 {
+    match self {
+      Width::Byte     => 1,
+      Width::HalfWord => 2,
+      Width::Word     => 4,
+    }
+  }
+}
+
+/// Transfer completion callback, called with the channel number and `true` if
+/// the transfer completed, or `false` if it was stopped by a bus error.
+pub type Callback = fn(u8, bool);
+
+/// Description of a single transfer.
+#[derive(Copy)]
+pub struct Transfer {
+  /// Transfer direction.
+  pub direction: Direction,
+  /// Source address.
+  pub source: u32,
+  /// Destination address.
+  pub destination: u32,
+  /// Number of elements to transfer.
+  pub count: u32,
+  /// Element width, used for both source and destination.
+  pub width: Width,
+  /// Peripheral request line, MCU-specific, ignored for memory to memory
+  /// transfers.
+  pub request: u8,
+}
+
+/// DMA channel trait.
+pub trait Channel {
+  /// Returns the hardware channel number.
+  fn number(&self) -> u8;
+
+  /// Starts the transfer, the channel must not be busy.
+  fn start(&self, transfer: &Transfer, callback: Option<Callback>);
+
+  /// Returns true while a transfer is running.
+  fn busy(&self) -> bool;
+
+  /// Stops the running transfer, the callback isn't called.
+  fn stop(&self);
+
+  /// Waits for the running transfer to finish.
+  fn wait(&self) {
+    wait_for!(!self.busy());
+  }
+}
@@ -0,0 +1,86 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+On-chip flash interface.
+
+Flash is divided in sectors, the smallest units that can be erased, and erased
+bits can only be programmed from 1 to 0. Programming is done in pages whose
+size depends on the MCU.
+
+The CPU can't fetch from flash while it's being written, so all operations
+block with interrupts disabled.
+*/
+
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::iter::range;
+use core::result::Result;
+
+/// Flash operation error.
+#[derive(Copy, PartialEq)]
+pub enum Error {
+  /// The address or length is out of the flash, or misaligned.
+  InvalidArgument,
+  /// The target sector is protected.
+  Protected,
+  /// The flash controller failed to complete the operation.
+  Failed,
+  /// Verify or blank check found a difference at the given address.
+  Mismatch(u32),
+}
+
+/// On-chip flash.
+pub trait Flash {
+  /// Returns the number of sectors.
+  fn sector_count(&self) -> uint;
+
+  /// Returns the start address of `sector`.
+  fn sector_address(&self, sector: uint) -> u32;
+
+  /// Returns the size of `sector` in bytes.
+  fn sector_size(&self, sector: uint) -> u32;
+
+  /// Returns the programming unit in bytes. `program` takes addresses aligned
+  /// to it and whole pages.
+  fn page_size(&self) -> u32;
+
+  /// Erases `sector`, setting all its bytes to 0xff.
+  fn erase_sector(&self, sector: uint) -> Result<(), Error>;
+
+  /// Programs `data` at `address`.
+  fn program(&self, address: u32, data: &[u8]) -> Result<(), Error>;
+
+  /// Checks that the flash at `address` holds `data`. The address and length
+  /// must be multiples of 4.
+  fn verify(&self, address: u32, data: &[u8]) -> Result<(), Error>;
+
+  /// Checks that `sector` is erased.
+  fn blank_check(&self, sector: uint) -> Result<(), Error>;
+
+  /// Reads `data.len()` bytes at `address`.
+  fn read(&self, address: u32, data: &mut [u8]);
+
+  /// Returns the sector containing `address`, if any.
+  fn sector_at(&self, address: u32) -> Option<uint> {
+    for sector in range(0, self.sector_count()) {
+      let start = self.sector_address(sector);
+      if address >= start && address - start < self.sector_size(sector) {
+        return Some(sector);
+      }
+    }
+    None
+  }
+}
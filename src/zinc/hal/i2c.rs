@@ -0,0 +1,54 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+I2C interface.
+
+I2CConf is a MCU-specific struct.
+
+Only the master mode is supported. All the transfers are blocking, addresses
+are 7-bit and must not be shifted to include the R/W bit.
+*/
+
+use core::result::Result;
+
+/// I2C transfer error.
+#[derive(Copy, PartialEq)]
+pub enum Error {
+  /// The slave didn't acknowledge its address.
+  AddressNack,
+  /// The slave didn't acknowledge a data byte.
+  DataNack,
+  /// Another master took over the bus.
+  ArbitrationLost,
+  /// A START or STOP condition was detected in an illegal position.
+  BusError,
+}
+
+/// I2C master trait.
+pub trait I2C {
+  /// Writes all of `data` to the slave at `address`.
+  fn write(&self, address: u8, data: &[u8]) -> Result<(), Error>;
+
+  /// Reads `data.len()` bytes from the slave at `address`.
+  fn read(&self, address: u8, data: &mut [u8]) -> Result<(), Error>;
+
+  /// Writes `wdata` to the slave and reads `rdata.len()` bytes back, using a
+  /// repeated START in between, so that no other master can take the bus.
+  ///
+  /// This is the common way to read a register of a sensor.
+  fn write_read(&self, address: u8, wdata: &[u8], rdata: &mut [u8])
+      -> Result<(), Error>;
+}
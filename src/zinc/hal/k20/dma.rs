@@ -0,0 +1,402 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Enhanced DMA controller (eDMA) and DMA request multiplexer.
+
+The eDMA has 16 channels, each with its own completion interrupt (`isr_dma_N`)
+and a shared error interrupt (`isr_dma_err`). Their handlers should call
+`process()` to dispatch completion callbacks. Channels are allocated with
+`allocate()` and released when dropped.
+
+Linked lists use the scatter/gather feature: each `Tcd` is loaded by the
+hardware when the previous one is done, and must be 32-byte aligned.
+*/
+
+use core::intrinsics::abort;
+use core::iter::range;
+use core::ops::Drop;
+use core::option::Option;
+use core::option::Option::{Some, None};
+
+use hal::cortex_m4::irq::NoInterrupts;
+use hal::dma;
+use super::sim;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// DMA request multiplexer sources.
+pub mod request {
+  #![allow(missing_docs)]
+  pub const UART0_RX: u8 = 2;
+  pub const UART0_TX: u8 = 3;
+  pub const UART1_RX: u8 = 4;
+  pub const UART1_TX: u8 = 5;
+  pub const UART2_RX: u8 = 6;
+  pub const UART2_TX: u8 = 7;
+  pub const SPI0_RX:  u8 = 16;
+  pub const SPI0_TX:  u8 = 17;
+  pub const SPI1_RX:  u8 = 18;
+  pub const SPI1_TX:  u8 = 19;
+  pub const I2C0:     u8 = 22;
+  pub const I2C1:     u8 = 23;
+  pub const ADC0:     u8 = 40;
+  pub const ADC1:     u8 = 41;
+  pub const DAC0:     u8 = 45;
+}
+
+/// Number of DMA channels.
+pub static CHANNELS: uint = 16;
+
+/// Maximum number of elements in a single peripheral transfer.
+pub static MAX_TRANSFER: u32 = 0x7fff;
+
+static mut ALLOCATED: u16 = 0;
+static mut CALLBACKS: [Option<dma::Callback>; 16] =
+    [None, None, None, None, None, None, None, None,
+     None, None, None, None, None, None, None, None];
+
+/// Enables the DMA controller and the request multiplexer.
+pub fn init() {
+  sim::enable_DMA();
+  reg::DMA.cint.set_cair(true);
+  reg::DMA.cerr.set_caei(true);
+}
+
+/// Allocates a free channel, lowest numbers first.
+pub fn allocate() -> Option<Channel> {
+  let _crit = NoInterrupts::new();
+  for number in range(0u8, CHANNELS as u8) {
+    let bit = 1u16 << number as uint;
+    unsafe {
+      if ALLOCATED & bit == 0 {
+        ALLOCATED |= bit;
+        return Some(Channel { number: number });
+      }
+    }
+  }
+  None
+}
+
+/// Clears the interrupt flags and calls the callbacks of finished transfers.
+pub fn process() {
+  for number in range(0u8, CHANNELS as u8) {
+    let n = number as uint;
+    let done = reg::DMA.intr.intr(n);
+    let error = reg::DMA.err.err(n);
+    if !done && !error {
+      continue;
+    }
+
+    reg::DMA.cint.set_cint(number);
+    reg::DMA.cerr.set_cerr(number);
+    match unsafe { CALLBACKS[n] } {
+      Some(callback) => callback(number, !error),
+      None => (),
+    }
+  }
+}
+
+/// Transfer control descriptor, as stored in memory for scatter/gather.
+#[repr(C)]
+pub struct Tcd {
+  saddr: u32,
+  soff: u16,
+  attr: u16,
+  nbytes: u32,
+  slast: u32,
+  daddr: u32,
+  doff: u16,
+  citer: u16,
+  dlastsga: u32,
+  csr: u16,
+  biter: u16,
+}
+
+impl Tcd {
+  /// Creates a descriptor, the last one (without `next`) raises the completion
+  /// interrupt.
+  pub fn new(transfer: &dma::Transfer, next: Option<&Tcd>) -> Tcd {
+    let width = transfer.width.bytes();
+    let size = match transfer.width {
+      dma::Width::Byte     => 0,
+      dma::Width::HalfWord => 1,
+      dma::Width::Word     => 2,
+    };
+    let (soff, doff) = match transfer.direction {
+      dma::Direction::MemoryToMemory     => (width, width),
+      dma::Direction::MemoryToPeripheral => (width, 0),
+      dma::Direction::PeripheralToMemory => (0, width),
+    };
+    // memory to memory transfers run as a single minor loop, peripheral ones
+    // move one element per request
+    let (nbytes, iterations, start) = match transfer.direction {
+      dma::Direction::MemoryToMemory => (transfer.count * width, 1, true),
+      _ => {
+        if transfer.count > MAX_TRANSFER {
+          unsafe { abort() }
+        }
+        (width, transfer.count, false)
+      },
+    };
+    let (dlastsga, csr) = match next {
+      Some(tcd) => {
+        let address = tcd as *const Tcd as u32;
+        if address & 31 != 0 {
+          unsafe { abort() }
+        }
+        (address, CSRESG)
+      },
+      None => (0, CSRIntMajor | CSRDReq),
+    };
+
+    Tcd {
+      saddr: transfer.source,
+      soff: soff as u16,
+      attr: (size << 8) | size,
+      nbytes: nbytes,
+      slast: 0,
+      daddr: transfer.destination,
+      doff: doff as u16,
+      citer: iterations as u16,
+      dlastsga: dlastsga,
+      csr: csr | if start { CSRStart } else { 0 },
+      biter: iterations as u16,
+    }
+  }
+}
+
+/// An allocated DMA channel.
+pub struct Channel {
+  number: u8,
+}
+
+impl Channel {
+  /// Starts the transfer followed by the linked list, the callback is called
+  /// once the last descriptor is done.
+  pub fn start_linked(&self, transfer: &dma::Transfer, next: &Tcd,
+      callback: Option<dma::Callback>) {
+    self.load(transfer, &Tcd::new(transfer, Some(next)), callback);
+  }
+
+  fn load(&self, transfer: &dma::Transfer, tcd: &Tcd,
+      callback: Option<dma::Callback>) {
+    let n = self.number as uint;
+    unsafe { CALLBACKS[n] = callback; }
+
+    reg::DMA.cerq.set_cerq(self.number);
+    reg::DMA.cdne.set_cdne(self.number);
+    reg::DMA.cint.set_cint(self.number);
+    reg::DMA.cerr.set_cerr(self.number);
+    reg::DMA.eei.set_eei(n, true);
+
+    let mux = &reg::DMAMUX.chcfg[n];
+    mux.set_enbl(false);
+    match transfer.direction {
+      dma::Direction::MemoryToMemory => (),
+      _ => { mux.set_source(transfer.request).set_enbl(true); },
+    }
+
+    let hw = &reg::DMATCD.tcd[n];
+    hw.saddr.set_saddr(tcd.saddr);
+    hw.soff.set_soff(tcd.soff);
+    hw.attr.set_attr(tcd.attr);
+    hw.nbytes.set_nbytes(tcd.nbytes);
+    hw.slast.set_slast(tcd.slast);
+    hw.daddr.set_daddr(tcd.daddr);
+    hw.doff.set_doff(tcd.doff);
+    hw.citer.set_citer(tcd.citer);
+    hw.dlastsga.set_dlastsga(tcd.dlastsga);
+    hw.biter.set_biter(tcd.biter);
+    // writing CSR last starts memory to memory transfers
+    hw.csr
+      .set_intmajor(tcd.csr & CSRIntMajor != 0)
+      .set_dreq(tcd.csr & CSRDReq != 0)
+      .set_esg(tcd.csr & CSRESG != 0)
+      .set_start(tcd.csr & CSRStart != 0);
+
+    match transfer.direction {
+      dma::Direction::MemoryToMemory => (),
+      _ => { reg::DMA.serq.set_serq(self.number); },
+    }
+  }
+
+  fn disable(&self) {
+    let n = self.number as uint;
+    unsafe { CALLBACKS[n] = None; }
+    reg::DMA.cerq.set_cerq(self.number);
+    reg::DMAMUX.chcfg[n].set_enbl(false);
+  }
+}
+
+impl dma::Channel for Channel {
+  fn number(&self) -> u8 {
+    self.number
+  }
+
+  fn start(&self, transfer: &dma::Transfer, callback: Option<dma::Callback>) {
+    self.load(transfer, &Tcd::new(transfer, None), callback);
+  }
+
+  fn busy(&self) -> bool {
+    let n = self.number as uint;
+    let csr = reg::DMATCD.tcd[n].csr.get();
+    !csr.done() && (csr.active() || csr.start() || reg::DMA.erq.erq(n))
+  }
+
+  fn stop(&self) {
+    self.disable();
+  }
+}
+
+impl Drop for Channel {
+  fn drop(&mut self) {
+    self.disable();
+
+    let _crit = NoInterrupts::new();
+    unsafe { ALLOCATED &= !(1u16 << self.number as uint); }
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static CSRStart: u16 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static CSRIntMajor: u16 = 1 << 1;
+#[allow(non_upper_case_globals)]
+static CSRDReq: u16 = 1 << 3;
+#[allow(non_upper_case_globals)]
+static CSRESG: u16 = 1 << 4;
+
+/// Register definitions
+#[allow(dead_code)]
+pub mod reg {
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(DMA = {
+    0x0    => reg32 cr {     //! Control register
+      1     => edbg,         //= enable debug
+      2     => erca,         //= enable round robin channel arbitration
+      4     => hoe,          //= halt on error
+      5     => halt,         //= halt DMA operations
+      6     => clm,          //= continuous link mode
+      7     => emlm,         //= enable minor loop mapping
+      16    => ecx,          //= error cancel transfer
+      17    => cx,           //= cancel transfer
+    },
+
+    0x4    => reg32 es {     //! Error status
+      0..31 => es: ro,
+    },
+
+    0xc    => reg32 erq {    //! Enable request
+      0..15 => erq[16],
+    },
+
+    0x14   => reg32 eei {    //! Enable error interrupt
+      0..15 => eei[16],
+    },
+
+    0x1a   => reg8 cerq {    //! Clear enable request
+      0..3  => cerq: wo,
+      6     => caer: wo,     //= clear all enable requests
+    },
+
+    0x1b   => reg8 serq {    //! Set enable request
+      0..3  => serq: wo,
+      6     => saer: wo,     //= set all enable requests
+    },
+
+    0x1c   => reg8 cdne {    //! Clear DONE status bit
+      0..3  => cdne: wo,
+      6     => cadn: wo,     //= clears all DONE bits
+    },
+
+    0x1e   => reg8 cerr {    //! Clear error
+      0..3  => cerr: wo,
+      6     => caei: wo,     //= clear all error indicators
+    },
+
+    0x1f   => reg8 cint {    //! Clear interrupt request
+      0..3  => cint: wo,
+      6     => cair: wo,     //= clear all interrupt requests
+    },
+
+    0x24   => reg32 intr {   //! Interrupt request
+      0..15 => intr[16]: ro,
+    },
+
+    0x2c   => reg32 err {    //! Error
+      0..15 => err[16]: ro,
+    },
+  });
+
+  ioregs!(DMATCD = {
+    0x0    => group tcd[16] { //! Transfer control descriptors
+      0x0  => reg32 saddr {
+        0..31 => saddr,      //= source address
+      },
+      0x4  => reg16 soff {
+        0..15 => soff,       //= source address signed offset
+      },
+      0x6  => reg16 attr {
+        0..15 => attr,       //= source and destination sizes and modulos
+      },
+      0x8  => reg32 nbytes {
+        0..31 => nbytes,     //= minor loop byte count
+      },
+      0xc  => reg32 slast {
+        0..31 => slast,      //= last source address adjustment
+      },
+      0x10 => reg32 daddr {
+        0..31 => daddr,      //= destination address
+      },
+      0x14 => reg16 doff {
+        0..15 => doff,       //= destination address signed offset
+      },
+      0x16 => reg16 citer {
+        0..15 => citer,      //= current major iteration count
+      },
+      0x18 => reg32 dlastsga {
+        0..31 => dlastsga,   //= last destination adjustment or next TCD
+      },
+      0x1c => reg16 csr {    //! Control and status
+        0     => start,      //= channel start
+        1     => intmajor,   //= interrupt on major loop completion
+        3     => dreq,       //= disable request on major loop completion
+        4     => esg,        //= enable scatter/gather
+        6     => active: ro, //= channel active
+        7     => done,       //= channel done
+      },
+      0x1e => reg16 biter {
+        0..15 => biter,      //= beginning major iteration count
+      },
+    },
+  });
+
+  ioregs!(DMAMUX = {
+    0x0    => reg8 chcfg[16] { //! Channel configuration
+      0..5  => source,       //= request source
+      6     => trig,         //= periodic trigger enable
+      7     => enbl,         //= channel enable
+    },
+  });
+
+  extern {
+    #[link_name="k20_iomem_DMACON"] pub static DMA: DMA;
+    #[link_name="k20_iomem_DMATCD"] pub static DMATCD: DMATCD;
+    #[link_name="k20_iomem_DMAMUX0"] pub static DMAMUX: DMAMUX;
+  }
+}
@@ -0,0 +1,265 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Dawid Ciężarkiewcz <dpc@ucore.info>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Flash programming through the FTFL (flash memory module) command interface.
+//!
+//! Sectors are 1KB and pages (longwords) are 4 bytes. The flash can't be read
+//! while a command runs, so the command launch loop is placed in RAM.
+
+use core::intrinsics::{volatile_load, volatile_store};
+use core::iter::{range, range_step};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+
+use hal::cortex_m4::irq::NoInterrupts;
+use hal::flash;
+use hal::flash::Error;
+use hal::k20::sim;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+#[path="../../util/wait_for.rs"] mod wait_for;
+
+/// Sector size in bytes.
+const SECTOR_SIZE: u32 = 0x400;
+
+/// The program flash.
+#[derive(Copy)]
+pub struct Flash;
+
+/// Launches the command loaded in FCCOB and waits for it to complete, returns
+/// FSTAT.
+///
+/// This runs from RAM, and must not call any function in flash.
+#[link_section=".data.ramfunc"]
+#[inline(never)]
+#[no_stack_check]
+fn launch(fstat: *mut u8) -> u8 {
+  unsafe {
+    volatile_store(fstat, FSTAT_CCIF);
+    while volatile_load(fstat as *const u8) & FSTAT_CCIF == 0 {}
+    volatile_load(fstat as *const u8)
+  }
+}
+
+/// Runs a command with the given FCCOB bytes, FCCOB0 first.
+fn run(fccob: &[u8; 12]) -> u8 {
+  let ftfl = &reg::FTFL;
+  wait_for!(ftfl.fstat.ccif());
+  // clear the errors of the previous command
+  ftfl.fstat.clear(FSTAT_ACCERR | FSTAT_FPVIOL);
+
+  for i in range(0u, 12) {
+    ftfl.fccob[FCCOB_OFFSETS[i]].set_fccob(fccob[i]);
+  }
+
+  let _crit = NoInterrupts::new();
+  launch(&ftfl.fstat as *const reg::FTFL_fstat as *mut u8)
+}
+
+/// Builds the FCCOB bytes for `command` on `address`, with `data` as a
+/// longword in FCCOB4-7 (big endian, as expected by the FTFL).
+fn fccob(command: u8, address: u32, data: u32) -> [u8; 12] {
+  [command, (address >> 16) as u8, (address >> 8) as u8, address as u8,
+   (data >> 24) as u8, (data >> 16) as u8, (data >> 8) as u8, data as u8,
+   0, 0, 0, 0]
+}
+
+fn to_result(fstat: u8) -> Result<(), Error> {
+  if fstat & FSTAT_ACCERR != 0 {
+    Err(Error::InvalidArgument)
+  } else if fstat & FSTAT_FPVIOL != 0 {
+    Err(Error::Protected)
+  } else if fstat & FSTAT_MGSTAT0 != 0 {
+    Err(Error::Failed)
+  } else {
+    Ok(())
+  }
+}
+
+fn longword(data: &[u8], offset: uint) -> u32 {
+  data[offset] as u32 |
+  (data[offset + 1] as u32 << 8) |
+  (data[offset + 2] as u32 << 16) |
+  (data[offset + 3] as u32 << 24)
+}
+
+impl flash::Flash for Flash {
+  fn sector_count(&self) -> uint {
+    let size: u32 = match sim::reg::SIM.fcfg1.pfsize() {
+      5 => 64 * 1024,
+      7 | 15 => 128 * 1024,
+      _ => 32 * 1024,
+    };
+    (size / SECTOR_SIZE) as uint
+  }
+
+  fn sector_address(&self, sector: uint) -> u32 {
+    sector as u32 * SECTOR_SIZE
+  }
+
+  fn sector_size(&self, _: uint) -> u32 {
+    SECTOR_SIZE
+  }
+
+  fn page_size(&self) -> u32 {
+    4
+  }
+
+  fn erase_sector(&self, sector: uint) -> Result<(), Error> {
+    if sector >= flash::Flash::sector_count(self) {
+      return Err(Error::InvalidArgument);
+    }
+    to_result(run(&fccob(FTFL_ERASE_SECTOR, sector as u32 * SECTOR_SIZE, 0)))
+  }
+
+  fn program(&self, address: u32, data: &[u8]) -> Result<(), Error> {
+    if address % 4 != 0 || data.len() % 4 != 0 {
+      return Err(Error::InvalidArgument);
+    }
+
+    for offset in range_step(0, data.len(), 4) {
+      let cmd = fccob(FTFL_PROGRAM_LONGWORD, address + offset as u32,
+          longword(data, offset));
+      let result = to_result(run(&cmd));
+      if result.is_err() {
+        return result;
+      }
+    }
+    Ok(())
+  }
+
+  fn verify(&self, address: u32, data: &[u8]) -> Result<(), Error> {
+    if address % 4 != 0 || data.len() % 4 != 0 {
+      return Err(Error::InvalidArgument);
+    }
+
+    for offset in range_step(0, data.len(), 4) {
+      let dst = address + offset as u32;
+      let mut cmd = fccob(FTFL_PROGRAM_CHECK, dst, 0);
+      // margin level in FCCOB4, expected data in FCCOB8-B
+      let expected = longword(data, offset);
+      cmd[4] = MARGIN_USER;
+      cmd[8] = (expected >> 24) as u8;
+      cmd[9] = (expected >> 16) as u8;
+      cmd[10] = (expected >> 8) as u8;
+      cmd[11] = expected as u8;
+
+      let fstat = run(&cmd);
+      if fstat & FSTAT_MGSTAT0 != 0 && fstat & FSTAT_ACCERR == 0 {
+        return Err(Error::Mismatch(dst));
+      }
+      let result = to_result(fstat);
+      if result.is_err() {
+        return result;
+      }
+    }
+    Ok(())
+  }
+
+  fn blank_check(&self, sector: uint) -> Result<(), Error> {
+    if sector >= flash::Flash::sector_count(self) {
+      return Err(Error::InvalidArgument);
+    }
+
+    let address = sector as u32 * SECTOR_SIZE;
+    let mut cmd = fccob(FTFL_READ_1S_SECTION, address, 0);
+    // number of longwords in FCCOB4-5, margin level in FCCOB6
+    let count = SECTOR_SIZE / 4;
+    cmd[4] = (count >> 8) as u8;
+    cmd[5] = count as u8;
+    cmd[6] = MARGIN_NORMAL;
+
+    let fstat = run(&cmd);
+    if fstat & FSTAT_MGSTAT0 != 0 && fstat & FSTAT_ACCERR == 0 {
+      // the command doesn't report where, find the first programmed byte
+      let mut byte = [0u8; 1];
+      for offset in range(0, SECTOR_SIZE) {
+        flash::Flash::read(self, address + offset, &mut byte);
+        if byte[0] != 0xff {
+          return Err(Error::Mismatch(address + offset));
+        }
+      }
+      return Err(Error::Mismatch(address));
+    }
+    to_result(fstat)
+  }
+
+  fn read(&self, address: u32, data: &mut [u8]) {
+    for i in range(0, data.len()) {
+      data[i] = unsafe { *((address + i as u32) as *const u8) };
+    }
+  }
+}
+
+/// FCCOB registers are laid out in big endian longwords: FCCOBn is at offset
+/// FCCOB_OFFSETS[n].
+static FCCOB_OFFSETS: [uint; 12] = [3, 2, 1, 0, 7, 6, 5, 4, 11, 10, 9, 8];
+
+const FTFL_READ_1S_SECTION:  u8 = 0x01;
+const FTFL_PROGRAM_CHECK:    u8 = 0x02;
+const FTFL_PROGRAM_LONGWORD: u8 = 0x06;
+const FTFL_ERASE_SECTOR:     u8 = 0x09;
+
+const MARGIN_NORMAL: u8 = 0x00;
+const MARGIN_USER:   u8 = 0x01;
+
+const FSTAT_MGSTAT0: u8 = 1 << 0;
+const FSTAT_FPVIOL:  u8 = 1 << 4;
+const FSTAT_ACCERR:  u8 = 1 << 5;
+const FSTAT_CCIF:    u8 = 1 << 7;
+
+#[allow(dead_code)]
+mod reg {
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(FTFL = {
+    /// Flash Status Register
+    0x0 => reg8 fstat {
+      0 => mgstat0: ro,    //= Memory controller command completion status
+      4 => fpviol,         //= Flash protection violation
+      5 => accerr,         //= Flash access error
+      6 => rdcolerr,       //= Flash read collision error
+      7 => ccif,           //= Command complete interrupt flag
+    },
+
+    /// Flash Configuration Register
+    0x1 => reg8 fcnfg {
+      7 => ccie,           //= Command complete interrupt enable
+    },
+
+    /// Flash Common Command Object Registers
+    0x4 => reg8 fccob[12] {
+      0..7 => fccob,
+    },
+
+    /// Program Flash Protection Registers
+    0x10 => reg8 fprot[4] {
+      0..7 => prot: ro,
+    },
+  });
+
+  impl FTFL_fstat {
+    /// Clears the flags set in `mask`. Flags are cleared by writing ones, so
+    /// a read-modify-write would clear all of them, or launch a command.
+    pub fn clear(&self, mask: u8) {
+      self.value.set(mask);
+    }
+  }
+
+  extern {
+    #[link_name="k20_iomem_FLASH"] pub static FTFL: FTFL;
+  }
+}
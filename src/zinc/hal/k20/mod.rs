@@ -15,7 +15,10 @@
 
 //! HAL for Freescale Kinetis K20.
 
+pub mod dma;
+pub mod flash;
 pub mod sim;
 pub mod pin;
+pub mod power;
 pub mod uart;
 pub mod watchdog;
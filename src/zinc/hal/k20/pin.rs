@@ -18,6 +18,9 @@ Pin configuration.
 
 Some pins that could be configured here may be missing from actual MCU depending
 on the package.
+
+Pin interrupts are raised through the interrupt of their port, `isr_port_a` to
+`isr_port_e`.
 */
 
 use core::option::Option;
@@ -156,14 +159,17 @@ impl Pin {
   }
 
   fn pcr(&self) -> &'static reg::Port_pcr {
-    let port: &reg::Port = match self.port {
+    return &self.portreg().pcr[self.pin as uint];
+  }
+
+  fn portreg(&self) -> &'static reg::Port {
+    match self.port {
       PortA => &reg::PORT_A,
       PortB => &reg::PORT_B,
       PortC => &reg::PORT_C,
       PortD => &reg::PORT_D,
       PortE => &reg::PORT_E,
-    };
-    return &port.pcr[self.pin as uint];
+    }
   }
 }
 
@@ -199,6 +205,34 @@ impl ::hal::pin::Gpio for Pin {
   }
 }
 
+impl ::hal::pin::GpioInterrupt for Pin {
+  fn enable_interrupt(&self, trigger: ::hal::pin::GpioTrigger) {
+    use hal::pin::GpioTrigger::*;
+    use self::reg::Port_pcr_irqc as irqc;
+    let config = match trigger {
+      RisingEdge  => irqc::IRQ_RISING,
+      FallingEdge => irqc::IRQ_FALLING,
+      BothEdges   => irqc::IRQ_EITHER,
+      LowLevel    => irqc::IRQ_ZERO,
+      HighLevel   => irqc::IRQ_ONE,
+    };
+    self.clear_interrupt();
+    self.pcr().set_irqc(config);
+  }
+
+  fn disable_interrupt(&self) {
+    self.pcr().set_irqc(self::reg::Port_pcr_irqc::IRQ_NONE);
+  }
+
+  fn interrupt_pending(&self) -> bool {
+    self.portreg().isfr.isf(self.pin as uint)
+  }
+
+  fn clear_interrupt(&self) {
+    self.portreg().isfr.clear(1 << (self.pin as uint));
+  }
+}
+
 /// Register definitions
 pub mod reg {
   use util::volatile_cell::VolatileCell;
@@ -250,10 +284,18 @@ pub mod reg {
     }
 
     0x88   => reg32 isfr {    //= Interrupt status
-      0..31  => isf
+      0..31  => isf[32]: ro
     }
   });
 
+  impl Port_isfr {
+    /// Clears the interrupt flags set in `mask`. Flags are cleared by writing
+    /// ones, so a read-modify-write would clear all of them.
+    pub fn clear(&self, mask: u32) {
+      self.value.set(mask);
+    }
+  }
+
   extern {
     #[link_name="k20_iomem_PORTA"] pub static PORT_A: Port;
     #[link_name="k20_iomem_PORTB"] pub static PORT_B: Port;
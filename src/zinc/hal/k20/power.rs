@@ -0,0 +1,183 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Dawid Ciężarkiewcz <dpc@ucore.info>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Power management for Kinetis K20.
+//!
+//! Wait mode stops the core clock only, and any enabled interrupt wakes the
+//! chip up. In very low power stop (VLPS) all clocks but the 1 kHz LPO are
+//! stopped and any enabled interrupt still wakes the chip. Low leakage stop
+//! (LLS) additionally powers down most of the logic, and only the low leakage
+//! wakeup unit (LLWU) can bring the chip back, from its wakeup pins or
+//! internal modules like the RTC and LPTMR.
+//!
+//! RAM and registers are kept in all of these modes. The MCG returns to the
+//! mode it was in by itself once the PLL has relocked, so no clock needs to be
+//! set up again. Interrupts are disabled while sleeping: the interrupt that woke
+//! the chip is taken once `sleep` returns.
+
+use core::intrinsics::abort;
+
+use hal::cortex_m4::irq::NoInterrupts;
+use hal::cortex_m4::scb;
+use hal::pin::GpioTrigger;
+use util::support::wfi;
+
+use self::SleepMode::*;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Low power modes the chip returns from.
+#[derive(Copy)]
+pub enum SleepMode {
+  /// Core clock stopped, peripherals keep running.
+  Wait,
+  /// Very low power stop, only the low power oscillators keep running.
+  VeryLowPowerStop,
+  /// Low leakage stop, only LLWU sources wake the chip up.
+  LowLeakageStop,
+}
+
+/// Puts the chip into `mode` until it is woken up.
+///
+/// Returns false if the chip could not enter the mode, for example because an
+/// interrupt was already pending.
+pub fn sleep(mode: SleepMode) -> bool {
+  use self::reg::SMC_pmctrl_stopm::*;
+  let _crit = NoInterrupts::new();
+
+  let stopm = match mode {
+    Wait             => {
+      wfi();
+      return true;
+    },
+    VeryLowPowerStop => VLPS,
+    LowLeakageStop   => LLS,
+  };
+
+  // PMPROT can only be written once after reset
+  reg::SMC.pmprot.set_avlp(true).set_alls(true);
+  reg::SMC.pmctrl.set_stopm(stopm);
+  // the mode has to be latched before WFI
+  reg::SMC.pmctrl.get();
+
+  scb::set_sleep_deep(true);
+  wfi();
+  scb::set_sleep_deep(false);
+
+  !reg::SMC.pmctrl.stopa()
+}
+
+/// Configures LLWU pin `pin` (0 to 15) to wake the chip up from LLS. The caller
+/// must enable the LLWU interrupt, `isr_llwu` (IRQ 21), for the chip to wake.
+///
+/// Level triggers are not supported.
+pub fn enable_wakeup_pin(pin: u8, trigger: GpioTrigger) {
+  let edges = match trigger {
+    GpioTrigger::RisingEdge  => 0b01,
+    GpioTrigger::FallingEdge => 0b10,
+    GpioTrigger::BothEdges   => 0b11,
+    _                        => unsafe { abort() },
+  };
+  set_pin(pin, edges);
+  clear_wakeup_pin(pin);
+}
+
+/// Stops LLWU pin `pin` from waking the chip up.
+pub fn disable_wakeup_pin(pin: u8) {
+  set_pin(pin, 0);
+}
+
+fn set_pin(pin: u8, edges: u8) {
+  if pin > 15 {
+    unsafe { abort() }
+  }
+  reg::LLWU.pe[pin as uint / 4].set_wupe(pin as uint % 4, edges);
+}
+
+/// Returns true if LLWU pin `pin` woke the chip up.
+pub fn wakeup_pin_pending(pin: u8) -> bool {
+  reg::LLWU.f[pin as uint / 8].wuf() & (1 << (pin as uint % 8)) != 0
+}
+
+/// Clears the wakeup flag of LLWU pin `pin`. This must be done from the LLWU
+/// interrupt, `isr_llwu`.
+pub fn clear_wakeup_pin(pin: u8) {
+  reg::LLWU.f[pin as uint / 8].clear(1 << (pin as uint % 8));
+}
+
+/// Allows internal module `module` (0 to 7, e.g. 0 for the LPTMR and 5 for the
+/// RTC alarm) to wake the chip up from LLS. Module flags are cleared in the
+/// module itself, the LLWU interrupt must be enabled as for wakeup pins.
+pub fn set_wakeup_module(module: u8, enabled: bool) {
+  if module > 7 {
+    unsafe { abort() }
+  }
+  reg::LLWU.me.set_wume(module as uint, enabled);
+}
+
+#[allow(dead_code)]
+mod reg {
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(SMC = {
+    0x0    => reg8 pmprot {  //! Power mode protection, write once
+      1      => avlls,
+      3      => alls,
+      5      => avlp,
+    },
+    0x1    => reg8 pmctrl {  //! Power mode control
+      0..2   => stopm {
+        0b000 => Normal,
+        0b010 => VLPS,
+        0b011 => LLS,
+        0b100 => VLLS,
+      },
+      3      => stopa: ro,
+      5..6   => runm,
+    },
+    0x3    => reg8 pmstat {  //! Power mode status
+      0..7   => stat: ro,
+    },
+  });
+
+  ioregs!(LLWU = {
+    0x0    => reg8 pe[4] {   //! Pin enable
+      0..7   => wupe[4],
+    },
+    0x4    => reg8 me {      //! Module enable
+      0..7   => wume[8],
+    },
+    0x5    => reg8 f[2] {    //! Pin flags
+      0..7   => wuf: ro,
+    },
+    0x7    => reg8 f3 {      //! Module flags
+      0..7   => mwuf: ro,
+    },
+  });
+
+  impl LLWU_f {
+    /// Clears the flags set in `mask`. Flags are cleared by writing ones, so
+    /// a read-modify-write would clear all of them.
+    pub fn clear(&self, mask: u8) {
+      self.value.set(mask);
+    }
+  }
+
+  extern {
+    #[link_name="k20_iomem_SMC"] pub static SMC: SMC;
+    #[link_name="k20_iomem_LLWU"] pub static LLWU: LLWU;
+  }
+}
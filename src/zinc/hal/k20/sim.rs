@@ -30,6 +30,13 @@ pub fn enable_PORT(port: pin::Port) {
   }
 }
 
+/// Enable clock to the DMA controller and DMA request multiplexer
+#[allow(non_snake_case)]
+pub fn enable_DMA() {
+  reg::SIM.scgc6.set_dmamux(true);
+  reg::SIM.scgc7.set_dma(true);
+}
+
 /// Registers
 #[allow(dead_code)]
 pub mod reg {
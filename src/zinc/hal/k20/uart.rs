@@ -18,8 +18,12 @@ UART configuration.
 */
 
 use core::intrinsics::abort;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
 
-use drivers::chario::CharIO;
+use drivers::chario::{CharIO, CharInput, InputError};
 use hal::uart;
 
 use self::UARTPeripheral::*;
@@ -117,7 +121,32 @@ impl UART {
 impl CharIO for UART {
   fn putc(&self, value: char) {
     wait_for!(self.reg.s1.tdre());
-    self.reg.d.set_re(value as u8);
+    self.reg.d.set_raw(value as u8);
+  }
+}
+
+impl CharInput for UART {
+  fn readable(&self) -> bool {
+    !self.reg.sfifo.rxempt()
+  }
+
+  fn try_getc(&self) -> Result<Option<char>, InputError> {
+    // reading S1 then D clears the error flags
+    let s1 = self.reg.s1.get();
+    if self.reg.sfifo.rxempt() {
+      return Ok(None);
+    }
+
+    let value = self.reg.d.re();
+    if s1.or() {
+      Err(InputError::Overrun)
+    } else if s1.pf() {
+      Err(InputError::Parity)
+    } else if s1.fe() {
+      Err(InputError::Framing)
+    } else {
+      Ok(Some(value as char))
+    }
   }
 }
 
@@ -311,6 +340,14 @@ pub mod reg {
     }
   }
 
+  impl UART_d {
+    /// Writes the register without reading it first, a read would pop the
+    /// receive FIFO.
+    pub fn set_raw(&self, value: u8) {
+      self.value.set(value);
+    }
+  }
+
   extern {
     #[link_name="k20_iomem_UART0"] pub static UART0: UART;
     #[link_name="k20_iomem_UART1"] pub static UART1: UART;
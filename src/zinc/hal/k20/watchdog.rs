@@ -15,6 +15,8 @@
 
 //! Watchdog for Kinetis SIM module.
 
+use hal::cortex_m4::irq::NoInterrupts;
+use hal::watchdog;
 use util::support::nop;
 
 #[path="../../util/ioreg.rs"] mod ioreg;
@@ -57,6 +59,52 @@ pub fn refresh() {
   reg::WDOG.refresh.set_refresh(RefreshSeq2);
 }
 
+/// The watchdog, as a `hal::watchdog::Watchdog`. It is clocked from the 1 kHz
+/// low power oscillator, so timeouts are rounded down to milliseconds.
+///
+/// The watchdog must have been left enabled with `init(State::Enabled)`, which
+/// keeps its configuration writable.
+#[derive(Copy)]
+pub struct Watchdog;
+
+impl watchdog::Watchdog for Watchdog {
+  fn set_timeout(&self, timeout_us: u32) {
+    let timeout_ms = timeout_us / 1000;
+    let timeout = if timeout_ms < MIN_TIMEOUT_MS {
+      MIN_TIMEOUT_MS
+    } else {
+      timeout_ms
+    };
+
+    // the unlock sequence and the update must happen within 256 bus cycles
+    let _crit = NoInterrupts::new();
+    unlock();
+    reg::WDOG.tovalh.set_toval((timeout >> 16) as u16);
+    reg::WDOG.tovall.set_toval(timeout as u16);
+    // the prescaler resets to divide by 5, TOVAL counts in LPO cycles
+    reg::WDOG.presc.set_prescval(0);
+  }
+
+  fn start(&self) {
+    let _crit = NoInterrupts::new();
+    unlock();
+    reg::WDOG.stctrlh.set_clksrc(false).set_allowupdate(true).set_en(true);
+  }
+
+  fn feed(&self) {
+    // the two refresh writes must happen within 20 bus cycles
+    let _crit = NoInterrupts::new();
+    refresh();
+  }
+
+  fn caused_reset(&self) -> bool {
+    reg::RCM.srs0.wdog()
+  }
+}
+
+/// Shortest timeout supported by the hardware, in milliseconds.
+const MIN_TIMEOUT_MS: u32 = 4;
+
 #[allow(dead_code)]
 mod reg {
   use util::volatile_cell::VolatileCell;
@@ -67,10 +115,21 @@ mod reg {
     0x0 => reg16 stctrlh
     {
       0 => en,             //= Watchdog enable
+      1 => clksrc,         //= Clock source (0: LPO, 1: alternate clock)
       4 => allowupdate     //= Enables updates to watchdog write-once registers,
                            //= after the reset-triggered initial configuration window
     },
 
+    /// Time-out Value Register High
+    0x4 => reg16 tovalh {
+      0..15 => toval,
+    },
+
+    /// Time-out Value Register Low
+    0x6 => reg16 tovall {
+      0..15 => toval,
+    },
+
     /// Refresh Register
     0xc => reg16 refresh {
       0..15 => refresh: wo
@@ -89,10 +148,22 @@ mod reg {
       },
     },
 
+    /// Prescaler Register
+    0x16 => reg16 presc {
+      8..10 => prescval,   //= Clock divided by prescval + 1
+    },
   });
 
 
+  ioregs!(RCM = {
+    /// System Reset Status Register 0
+    0x0 => reg8 srs0 {
+      5 => wdog: ro,       //= Reset caused by the watchdog
+    },
+  });
+
   extern {
     #[link_name="k20_iomem_WDOG"] pub static WDOG: WDOG;
+    #[link_name="k20_iomem_RCM"] pub static RCM: RCM;
   }
 }
@@ -0,0 +1,204 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+ADC configuration.
+
+The ADC has eight 12-bit channels, AD0.0 to AD0.7. Continuous conversion uses
+the burst mode, the ADC interrupt (`isr_adc`) is raised when a channel has a new
+result, so `process()` can be called from the bound handler.
+*/
+
+use core::cell::Cell;
+use core::intrinsics::abort;
+use core::iter::range;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::slice::SliceExt;
+
+use hal::adc;
+use hal::lpc17xx::peripheral_clock::PeripheralClock::ADCClock;
+use hal::lpc17xx::pin;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Maximum ADC clock frequency.
+static MAX_ADC_CLOCK: u32 = 13_000_000;
+
+/// Structure describing the ADC.
+pub struct ADC {
+  reg: &'static reg::ADC,
+  callback: Cell<Option<adc::Callback>>,
+}
+
+impl ADC {
+  /// Create and setup the ADC.
+  pub fn new() -> ADC {
+    ADCClock.enable();
+
+    let adc = ADC {
+      reg: &reg::ADC,
+      callback: Cell::new(None),
+    };
+
+    let pclk = ADCClock.frequency();
+    let clkdiv = (pclk + MAX_ADC_CLOCK - 1) / MAX_ADC_CLOCK - 1;
+    adc.reg.set_ADINTEN(0);
+    adc.reg.set_ADCR((clkdiv << ADCRClkDivShift) | ADCRPowerUp);
+
+    adc
+  }
+
+  /// Configures the pin of the channel for analog input.
+  pub fn setup_channel(&self, channel: u8) -> pin::Pin {
+    let (port, index, function) = match channel {
+      0 => (pin::Port::Port0, 23, pin::Function::AltFunction1),
+      1 => (pin::Port::Port0, 24, pin::Function::AltFunction1),
+      2 => (pin::Port::Port0, 25, pin::Function::AltFunction1),
+      3 => (pin::Port::Port0, 26, pin::Function::AltFunction1),
+      4 => (pin::Port::Port1, 30, pin::Function::AltFunction3),
+      5 => (pin::Port::Port1, 31, pin::Function::AltFunction3),
+      6 => (pin::Port::Port0,  3, pin::Function::AltFunction2),
+      7 => (pin::Port::Port0,  2, pin::Function::AltFunction2),
+      _ => unsafe { abort() },
+    };
+    pin::Pin::new(port, index, function, None)
+  }
+
+  fn result(&self, channel: u8) -> Option<u16> {
+    let value = self.reg.ADDR(channel as uint);
+    if value & ADDRDone != 0 {
+      Some(((value >> ADDRResultShift) & ADDRResultMask) as u16)
+    } else {
+      None
+    }
+  }
+}
+
+impl adc::ADC for ADC {
+  fn resolution(&self) -> u8 {
+    12
+  }
+
+  fn read(&self, channel: u8) -> u16 {
+    if channel > 7 {
+      unsafe { abort() }
+    }
+    let adcr = self.reg.ADCR() & !(ADCRSelMask | ADCRStartMask | ADCRBurst);
+    self.reg.set_ADCR(adcr | (1 << channel as uint) | ADCRStartNow);
+
+    loop {
+      match self.result(channel) {
+        Some(value) => {
+          self.reg.set_ADCR(adcr);
+          return value;
+        },
+        None => (),
+      }
+    }
+  }
+
+  fn start_continuous(&self, channels: &[u8], callback: adc::Callback) {
+    let mut sel = 0u32;
+    for ch in channels.iter() {
+      if *ch > 7 {
+        unsafe { abort() }
+      }
+      sel |= 1 << (*ch as uint);
+    }
+
+    self.callback.set(Some(callback));
+    self.reg.set_ADINTEN(sel);
+    let adcr = self.reg.ADCR() & !(ADCRSelMask | ADCRStartMask);
+    self.reg.set_ADCR(adcr | sel | ADCRBurst);
+  }
+
+  fn stop_continuous(&self) {
+    let adcr = self.reg.ADCR() & !ADCRBurst;
+    self.reg.set_ADCR(adcr);
+    self.reg.set_ADINTEN(0);
+    self.callback.set(None);
+  }
+
+  fn process(&self) {
+    let callback = match self.callback.get() {
+      Some(callback) => callback,
+      None => return,
+    };
+    let sel = self.reg.ADCR() & ADCRSelMask;
+    for channel in range(0u8, 8) {
+      if sel & (1 << channel as uint) == 0 {
+        continue;
+      }
+      // reading the data register clears the done flag
+      match self.result(channel) {
+        Some(value) => callback(channel, value),
+        None => (),
+      }
+    }
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static ADCRSelMask: u32 = 0xff;
+#[allow(non_upper_case_globals)]
+static ADCRClkDivShift: uint = 8;
+#[allow(non_upper_case_globals)]
+static ADCRBurst: u32 = 1 << 16;
+#[allow(non_upper_case_globals)]
+static ADCRPowerUp: u32 = 1 << 21;
+#[allow(non_upper_case_globals)]
+static ADCRStartMask: u32 = 0b111 << 24;
+#[allow(non_upper_case_globals)]
+static ADCRStartNow: u32 = 0b001 << 24;
+
+#[allow(non_upper_case_globals)]
+static ADDRResultShift: uint = 4;
+#[allow(non_upper_case_globals)]
+static ADDRResultMask: u32 = 0xfff;
+#[allow(non_upper_case_globals)]
+static ADDRDone: u32 = 1 << 31;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(ADC: u32, ADCR, ADGDR, _pad_0, ADINTEN, ADDR0, ADDR1, ADDR2, ADDR3,
+      ADDR4, ADDR5, ADDR6, ADDR7, ADSTAT);
+  reg_rw!(ADC, u32, ADCR,    set_ADCR,    ADCR);
+  reg_r!( ADC, u32, ADGDR,                ADGDR);
+  reg_rw!(ADC, u32, ADINTEN, set_ADINTEN, ADINTEN);
+  reg_r!( ADC, u32, ADSTAT,               ADSTAT);
+
+  impl ADC {
+    /// Returns the data register of the channel.
+    #[allow(non_snake_case)]
+    pub fn ADDR(&self, channel: uint) -> u32 {
+      match channel {
+        0 => self.ADDR0.get(),
+        1 => self.ADDR1.get(),
+        2 => self.ADDR2.get(),
+        3 => self.ADDR3.get(),
+        4 => self.ADDR4.get(),
+        5 => self.ADDR5.get(),
+        6 => self.ADDR6.get(),
+        _ => self.ADDR7.get(),
+      }
+    }
+  }
+
+  extern {
+    #[link_name="lpc17xx_iomem_ADC"] pub static ADC: ADC;
+  }
+}
@@ -0,0 +1,637 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+CAN controllers.
+
+Supports CAN1 and CAN2. The RDn and TDn pins must be configured with the
+respective `rdN` and `tdN` functions separately.
+
+Both controllers share the acceptance filter, which rejects all frames after
+reset: call `accept_all` or `set_filters` once. The filter needs CAN1, CAN2 and
+its own peripheral clock to run at the same frequency, which is the case with
+the default divisors.
+
+Received frames raise `isr_can`, see `enable_rx_interrupt`.
+*/
+
+use core::intrinsics::abort;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+use core::slice::SliceExt;
+
+use hal::can;
+use hal::can::{BitTiming, BusState, ErrorCounters, Frame, Id};
+use hal::lpc17xx::peripheral_clock::PeripheralClock;
+use hal::lpc17xx::peripheral_clock::PeripheralClock::{CAN1Clock, CAN2Clock};
+
+use self::CANPeripheral::*;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Available CAN peripherals.
+#[allow(missing_docs)]
+#[derive(Copy)]
+pub enum CANPeripheral {
+  CAN1 = 0,
+  CAN2 = 1,
+}
+
+impl CANPeripheral {
+  fn reg(self) -> &'static reg::CAN {
+    match self {
+      CAN1 => &reg::CAN1,
+      CAN2 => &reg::CAN2,
+    }
+  }
+
+  fn peripheral_clock(self) -> PeripheralClock {
+    match self {
+      CAN1 => CAN1Clock,
+      CAN2 => CAN2Clock,
+    }
+  }
+}
+
+/// Structure describing a CAN controller.
+#[derive(Copy)]
+pub struct CAN {
+  reg: &'static reg::CAN,
+}
+
+impl CAN {
+  /// Create and setup a CAN controller running at `bitrate` bits per second.
+  ///
+  /// The bitrate must be an exact fraction of the peripheral clock.
+  pub fn new(peripheral: CANPeripheral, bitrate: u32) -> CAN {
+    let clock = peripheral.peripheral_clock();
+    clock.enable();
+
+    let can = CAN {
+      reg: peripheral.reg(),
+    };
+
+    let timing = match BitTiming::calculate(clock.frequency(), bitrate,
+        BTRMaxPrescaler, BTRMaxSegment1, BTRMaxSegment2) {
+      Some(timing) => timing,
+      None => unsafe { abort() },
+    };
+
+    // bit timing and error counters can only be written in reset mode
+    can.reg.set_MOD(MODReset);
+    can.reg.set_IER(0);
+    can.reg.set_GSR(0);
+    can.reg.set_BTR((timing.prescaler - 1) |
+        ((timing.sjw as u32 - 1) << 14) |
+        ((timing.segment1 as u32 - 1) << 16) |
+        ((timing.segment2 as u32 - 1) << 20));
+    can.reg.set_CMR(CMRReleaseRxBuffer | CMRClearDataOverrun);
+    can.reg.set_MOD(0);
+
+    can
+  }
+
+  /// Enables the receive interrupt, which stays pending until all received
+  /// frames are read.
+  ///
+  /// CAN1 and CAN2 share `isr_can`, IRQ 25, which the caller must enable.
+  pub fn enable_rx_interrupt(&self) {
+    let ier = self.reg.IER();
+    self.reg.set_IER(ier | IERReceive);
+  }
+
+  fn write_buffer(&self, buffer: uint, tfi: u32, tid: u32, tda: u32,
+      tdb: u32) {
+    match buffer {
+      0 => {
+        self.reg.set_TFI1(tfi);
+        self.reg.set_TID1(tid);
+        self.reg.set_TDA1(tda);
+        self.reg.set_TDB1(tdb);
+      },
+      1 => {
+        self.reg.set_TFI2(tfi);
+        self.reg.set_TID2(tid);
+        self.reg.set_TDA2(tda);
+        self.reg.set_TDB2(tdb);
+      },
+      2 => {
+        self.reg.set_TFI3(tfi);
+        self.reg.set_TID3(tid);
+        self.reg.set_TDA3(tda);
+        self.reg.set_TDB3(tdb);
+      },
+      _ => unsafe { abort() },
+    }
+  }
+}
+
+impl can::CAN for CAN {
+  fn send(&self, frame: &Frame) -> Result<(), can::Error> {
+    if self.reg.GSR() & GSRBusOff != 0 {
+      return Err(can::Error::BusOff);
+    }
+
+    let sr = self.reg.SR();
+    let buffer = if sr & SRTxBuffer1Free != 0 {
+      0
+    } else if sr & SRTxBuffer2Free != 0 {
+      1
+    } else if sr & SRTxBuffer3Free != 0 {
+      2
+    } else {
+      return Err(can::Error::Busy);
+    };
+
+    let (tid, extended) = match frame.id {
+      Id::Standard(id) => (id as u32 & 0x7ff, 0),
+      Id::Extended(id) => (id & 0x1fffffff, FrameExtended),
+    };
+    let remote = if frame.remote { FrameRemote } else { 0 };
+    // the DLC field is 4 bits wide and frames carry at most 8 bytes
+    let len = if frame.len > 8 { 8 } else { frame.len };
+    let tfi = ((len as u32) << 16) | remote | extended;
+    self.write_buffer(buffer, tfi, tid, pack(frame.data.slice(0, 4)),
+        pack(frame.data.slice(4, 8)));
+
+    // self reception sends the frame without requiring an acknowledge
+    let request = if self.reg.MOD() & MODSelfTest != 0 {
+      CMRSelfReception
+    } else {
+      CMRTransmit
+    };
+    self.reg.set_CMR(request | (CMRSelectTxBuffer1 << buffer));
+    Ok(())
+  }
+
+  fn receive(&self) -> Option<Frame> {
+    let gsr = self.reg.GSR();
+    if gsr & GSRReceiveBuffer == 0 {
+      return None;
+    }
+
+    let rfs = self.reg.RFS();
+    let rid = self.reg.RID();
+    let mut frame = Frame {
+      id: if rfs & FrameExtended != 0 {
+        Id::Extended(rid & 0x1fffffff)
+      } else {
+        Id::Standard((rid & 0x7ff) as u16)
+      },
+      remote: rfs & FrameRemote != 0,
+      len: ((rfs >> 16) & 0xf) as u8,
+      data: [0; 8],
+    };
+    if frame.len > 8 {
+      frame.len = 8;
+    }
+    unpack(self.reg.RDA(), frame.data.slice_mut(0, 4));
+    unpack(self.reg.RDB(), frame.data.slice_mut(4, 8));
+
+    let overrun = if gsr & GSRDataOverrun != 0 { CMRClearDataOverrun } else { 0 };
+    self.reg.set_CMR(CMRReleaseRxBuffer | overrun);
+    Some(frame)
+  }
+
+  fn state(&self) -> BusState {
+    let gsr = self.reg.GSR();
+    let counters = self.error_counters();
+    if gsr & GSRBusOff != 0 {
+      BusState::BusOff
+    } else if counters.transmit >= 128 || counters.receive >= 128 {
+      BusState::ErrorPassive
+    } else {
+      BusState::ErrorActive
+    }
+  }
+
+  fn error_counters(&self) -> ErrorCounters {
+    let gsr = self.reg.GSR();
+    ErrorCounters {
+      transmit: (gsr >> 24) as u8,
+      receive: (gsr >> 16) as u8,
+    }
+  }
+
+  fn recover(&self) {
+    // the controller enters reset mode when it goes bus off, leaving it starts
+    // the recovery
+    if self.reg.GSR() & GSRBusOff != 0 {
+      let mode = self.reg.MOD();
+      self.reg.set_MOD(mode & !MODReset);
+    }
+  }
+
+  fn set_loopback(&self, enabled: bool) {
+    let mode = self.reg.MOD();
+    let new_mode = if enabled {
+      mode | MODSelfTest
+    } else {
+      mode & !MODSelfTest
+    };
+    // the self test mode can only be changed in reset mode
+    self.reg.set_MOD(mode | MODReset);
+    self.reg.set_MOD(new_mode | MODReset);
+    self.reg.set_MOD(new_mode);
+  }
+}
+
+/// Packs 4 data bytes into a register, first byte lowest.
+fn pack(data: &[u8]) -> u32 {
+  data[0] as u32 | (data[1] as u32 << 8) | (data[2] as u32 << 16) |
+      (data[3] as u32 << 24)
+}
+
+fn unpack(value: u32, data: &mut [u8]) {
+  data[0] = value as u8;
+  data[1] = (value >> 8) as u8;
+  data[2] = (value >> 16) as u8;
+  data[3] = (value >> 24) as u8;
+}
+
+/// Receives all frames on both controllers.
+pub fn accept_all() {
+  reg::CANAF.set_AFMR(AFMRBypass);
+}
+
+/// Receives only the frames matching one of `filters` on the controller it's
+/// paired with, on both controllers.
+///
+/// The filter table holds 512 words: standard identifiers take half a word,
+/// extended identifiers and standard ranges one, and extended ranges two.
+/// Ranges must not be reversed.
+pub fn set_filters(filters: &[(CANPeripheral, can::Filter)]) {
+  // the filter rejects all frames while the table is updated
+  reg::CANAF.set_AFMR(AFMROff);
+
+  let sections = build_table(filters, &mut FilterRAM);
+  reg::CANAF.set_SFF_sa(0);
+  reg::CANAF.set_SFF_GRP_sa((sections.standard_range * 4) as u32);
+  reg::CANAF.set_EFF_sa((sections.extended_id * 4) as u32);
+  reg::CANAF.set_EFF_GRP_sa((sections.extended_range * 4) as u32);
+  reg::CANAF.set_ENDofTable((sections.end * 4) as u32);
+
+  reg::CANAF.set_AFMR(0);
+}
+
+/// Word offsets of the filter table sections, the standard identifiers start
+/// the table.
+#[derive(Copy)]
+struct TableSections {
+  standard_range: uint,
+  extended_id: uint,
+  extended_range: uint,
+  end: uint,
+}
+
+/// Destination of the filter table words.
+trait FilterTable {
+  fn set_entry(&mut self, word: uint, value: u32);
+}
+
+/// The acceptance filter RAM, only writable while the filter is off.
+struct FilterRAM;
+
+impl FilterTable for FilterRAM {
+  fn set_entry(&mut self, word: uint, value: u32) {
+    reg::CANAFRAM.entries[word].set(value);
+  }
+}
+
+/// Fills `table` with the entries for `filters`, aborts if they don't fit or
+/// a range is reversed.
+fn build_table<T: FilterTable>(filters: &[(CANPeripheral, can::Filter)],
+    table: &mut T) -> TableSections {
+  for &(_, filter) in filters.iter() {
+    let reversed = match filter {
+      can::Filter::StandardRange(from, to) => from > to,
+      can::Filter::ExtendedRange(from, to) => from > to,
+      can::Filter::Id(_) => false,
+    };
+    if reversed {
+      unsafe { abort() }
+    }
+  }
+
+  // standard identifiers are paired in words, the upper half first
+  let mut word = 0u;
+  let mut upper = None;
+  let mut last = None;
+  loop {
+    match next_entry(filters, Section::StandardId, last) {
+      Some(entry) => {
+        match upper {
+          Some(high) => {
+            write_entry(table, word, (high << 16) | entry as u32);
+            word += 1;
+            upper = None;
+          },
+          None => upper = Some(entry as u32),
+        }
+        last = Some(entry);
+      },
+      None => break,
+    }
+  }
+  match upper {
+    Some(high) => {
+      write_entry(table, word, (high << 16) | StandardDisabled);
+      word += 1;
+    },
+    None => (),
+  }
+
+  let standard_range = word;
+  let extended_id = write_section(filters, Section::StandardRange, table,
+      standard_range);
+  let extended_range = write_section(filters, Section::ExtendedId, table,
+      extended_id);
+  let end = write_section(filters, Section::ExtendedRange, table,
+      extended_range);
+  TableSections {
+    standard_range: standard_range,
+    extended_id: extended_id,
+    extended_range: extended_range,
+    end: end,
+  }
+}
+
+/// Sections of the filter table, in table order.
+#[derive(Copy, PartialEq)]
+enum Section {
+  StandardId,
+  StandardRange,
+  ExtendedId,
+  ExtendedRange,
+}
+
+fn standard_entry(peripheral: CANPeripheral, id: u16) -> u32 {
+  ((peripheral as u32) << 13) | (id as u32 & 0x7ff)
+}
+
+fn extended_entry(peripheral: CANPeripheral, id: u32) -> u32 {
+  ((peripheral as u32) << 29) | (id & 0x1fffffff)
+}
+
+/// Returns the table entry for `filter` if it belongs to `section`. Ranges
+/// take two entries, packed in one value. Values sort in the order the table
+/// requires.
+fn entry(section: Section, peripheral: CANPeripheral, filter: can::Filter)
+    -> Option<u64> {
+  match (section, filter) {
+    (Section::StandardId, can::Filter::Id(Id::Standard(id))) =>
+        Some(standard_entry(peripheral, id) as u64),
+    (Section::StandardRange, can::Filter::StandardRange(from, to)) =>
+        Some(((standard_entry(peripheral, from) << 16) |
+            standard_entry(peripheral, to)) as u64),
+    (Section::ExtendedId, can::Filter::Id(Id::Extended(id))) =>
+        Some(extended_entry(peripheral, id) as u64),
+    (Section::ExtendedRange, can::Filter::ExtendedRange(from, to)) =>
+        Some(((extended_entry(peripheral, from) as u64) << 32) |
+            extended_entry(peripheral, to) as u64),
+    _ => None,
+  }
+}
+
+/// Returns the smallest entry of `section` above `last`, the table can't hold
+/// duplicates.
+fn next_entry(filters: &[(CANPeripheral, can::Filter)], section: Section,
+    last: Option<u64>) -> Option<u64> {
+  let mut next = None;
+  for &(peripheral, filter) in filters.iter() {
+    match entry(section, peripheral, filter) {
+      Some(value) => {
+        let above = match last {
+          Some(l) => value > l,
+          None => true,
+        };
+        let below = match next {
+          Some(n) => value < n,
+          None => true,
+        };
+        if above && below {
+          next = Some(value);
+        }
+      },
+      None => (),
+    }
+  }
+  next
+}
+
+/// Writes the entries of `section` in order from `word`, returns the word
+/// following them.
+fn write_section<T: FilterTable>(filters: &[(CANPeripheral, can::Filter)],
+    section: Section, table: &mut T, word: uint) -> uint {
+  let mut word = word;
+  let mut last = None;
+  loop {
+    match next_entry(filters, section, last) {
+      Some(value) => {
+        if section == Section::ExtendedRange {
+          write_entry(table, word, (value >> 32) as u32);
+          word += 1;
+        }
+        write_entry(table, word, value as u32);
+        word += 1;
+        last = Some(value);
+      },
+      None => return word,
+    }
+  }
+}
+
+fn write_entry<T: FilterTable>(table: &mut T, word: uint, value: u32) {
+  if word >= AFRAMWords {
+    unsafe { abort() };
+  }
+  table.set_entry(word, value);
+}
+
+#[allow(non_upper_case_globals)]
+static BTRMaxPrescaler: u32 = 1024;
+#[allow(non_upper_case_globals)]
+static BTRMaxSegment1: u8 = 16;
+#[allow(non_upper_case_globals)]
+static BTRMaxSegment2: u8 = 8;
+
+#[allow(non_upper_case_globals)]
+static MODReset: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static MODSelfTest: u32 = 1 << 2;
+
+#[allow(non_upper_case_globals)]
+static CMRTransmit: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static CMRReleaseRxBuffer: u32 = 1 << 2;
+#[allow(non_upper_case_globals)]
+static CMRClearDataOverrun: u32 = 1 << 3;
+#[allow(non_upper_case_globals)]
+static CMRSelfReception: u32 = 1 << 4;
+#[allow(non_upper_case_globals)]
+static CMRSelectTxBuffer1: u32 = 1 << 5;
+
+#[allow(non_upper_case_globals)]
+static GSRReceiveBuffer: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static GSRDataOverrun: u32 = 1 << 1;
+#[allow(non_upper_case_globals)]
+static GSRBusOff: u32 = 1 << 7;
+
+#[allow(non_upper_case_globals)]
+static SRTxBuffer1Free: u32 = 1 << 2;
+#[allow(non_upper_case_globals)]
+static SRTxBuffer2Free: u32 = 1 << 10;
+#[allow(non_upper_case_globals)]
+static SRTxBuffer3Free: u32 = 1 << 18;
+
+#[allow(non_upper_case_globals)]
+static IERReceive: u32 = 1 << 0;
+
+#[allow(non_upper_case_globals)]
+static FrameRemote: u32 = 1 << 30;
+#[allow(non_upper_case_globals)]
+static FrameExtended: u32 = 1 << 31;
+
+#[allow(non_upper_case_globals)]
+static AFMROff: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static AFMRBypass: u32 = 1 << 1;
+
+/// Pads an odd number of standard identifiers, sorts after all of them.
+#[allow(non_upper_case_globals)]
+static StandardDisabled: u32 = 0xf7ff;
+
+#[allow(non_upper_case_globals)]
+static AFRAMWords: uint = 512;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(CAN: u32, MOD, CMR, GSR, ICR, IER, BTR, EWL, SR, RFS, RID, RDA,
+             RDB, TFI1, TID1, TDA1, TDB1, TFI2, TID2, TDA2, TDB2, TFI3, TID3,
+             TDA3, TDB3);
+  reg_rw!(CAN, u32, MOD,  set_MOD,  MOD);
+  reg_w!( CAN, u32,       set_CMR,  CMR);
+  reg_rw!(CAN, u32, GSR,  set_GSR,  GSR);
+  reg_rw!(CAN, u32, IER,  set_IER,  IER);
+  reg_rw!(CAN, u32, BTR,  set_BTR,  BTR);
+  reg_r!( CAN, u32, SR,             SR);
+  reg_r!( CAN, u32, RFS,            RFS);
+  reg_r!( CAN, u32, RID,            RID);
+  reg_r!( CAN, u32, RDA,            RDA);
+  reg_r!( CAN, u32, RDB,            RDB);
+  reg_w!( CAN, u32,       set_TFI1, TFI1);
+  reg_w!( CAN, u32,       set_TID1, TID1);
+  reg_w!( CAN, u32,       set_TDA1, TDA1);
+  reg_w!( CAN, u32,       set_TDB1, TDB1);
+  reg_w!( CAN, u32,       set_TFI2, TFI2);
+  reg_w!( CAN, u32,       set_TID2, TID2);
+  reg_w!( CAN, u32,       set_TDA2, TDA2);
+  reg_w!( CAN, u32,       set_TDB2, TDB2);
+  reg_w!( CAN, u32,       set_TFI3, TFI3);
+  reg_w!( CAN, u32,       set_TID3, TID3);
+  reg_w!( CAN, u32,       set_TDA3, TDA3);
+  reg_w!( CAN, u32,       set_TDB3, TDB3);
+
+  ioreg_old!(CANAF: u32, AFMR, SFF_sa, SFF_GRP_sa, EFF_sa, EFF_GRP_sa,
+             ENDofTable);
+  reg_rw!(CANAF, u32, AFMR,       set_AFMR,       AFMR);
+  reg_w!( CANAF, u32,             set_SFF_sa,     SFF_sa);
+  reg_w!( CANAF, u32,             set_SFF_GRP_sa, SFF_GRP_sa);
+  reg_w!( CANAF, u32,             set_EFF_sa,     EFF_sa);
+  reg_w!( CANAF, u32,             set_EFF_GRP_sa, EFF_GRP_sa);
+  reg_w!( CANAF, u32,             set_ENDofTable, ENDofTable);
+
+  /// Acceptance filter table.
+  pub struct CANAFRAM {
+    pub entries: [VolatileCell<u32>; 512],
+  }
+
+  extern {
+    #[link_name="lpc17xx_iomem_CAN1"] pub static CAN1: CAN;
+    #[link_name="lpc17xx_iomem_CAN2"] pub static CAN2: CAN;
+    #[link_name="lpc17xx_iomem_CANAF"] pub static CANAF: CANAF;
+    #[link_name="lpc17xx_iomem_CANAFRAM"] pub static CANAFRAM: CANAFRAM;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use hal::can::Filter;
+  use hal::can::Id::{Standard, Extended};
+  use hal::lpc17xx::can::{build_table, FilterTable};
+  use hal::lpc17xx::can::CANPeripheral::{CAN1, CAN2};
+
+  struct Table {
+    words: [u32; 512],
+  }
+
+  impl FilterTable for Table {
+    fn set_entry(&mut self, word: uint, value: u32) {
+      self.words[word] = value;
+    }
+  }
+
+  #[test]
+  fn pairs_sorted_standard_ids() {
+    let mut table = Table { words: [0; 512] };
+    let sections = build_table(&[
+      (CAN1, Filter::Id(Standard(0x300))),
+      (CAN1, Filter::Id(Standard(0x100))),
+      (CAN2, Filter::Id(Standard(0x100))),
+      (CAN1, Filter::Id(Standard(0x100))),
+    ], &mut table);
+
+    // duplicates are dropped, CAN2 sorts after CAN1, the odd entry is padded
+    assert!(table.words[0] == 0x0100_0300);
+    assert!(table.words[1] == 0x2100_f7ff);
+    assert!(sections.standard_range == 2);
+    assert!(sections.extended_id == 2);
+    assert!(sections.extended_range == 2);
+    assert!(sections.end == 2);
+  }
+
+  #[test]
+  fn lays_out_sections_in_order() {
+    let mut table = Table { words: [0; 512] };
+    let sections = build_table(&[
+      (CAN1, Filter::ExtendedRange(0x1000, 0x2000)),
+      (CAN2, Filter::Id(Extended(0x12345))),
+      (CAN1, Filter::StandardRange(0x10, 0x20)),
+      (CAN1, Filter::Id(Standard(0x7))),
+    ], &mut table);
+
+    assert!(table.words[0] == 0x0007_f7ff);
+    assert!(sections.standard_range == 1);
+    assert!(table.words[1] == 0x0010_0020);
+    assert!(sections.extended_id == 2);
+    assert!(table.words[2] == 0x2001_2345);
+    assert!(sections.extended_range == 3);
+    assert!(table.words[3] == 0x1000);
+    assert!(table.words[4] == 0x2000);
+    assert!(sections.end == 5);
+  }
+
+  #[test]
+  fn builds_empty_table() {
+    let mut table = Table { words: [0; 512] };
+    let sections = build_table(&[], &mut table);
+    assert!(sections.standard_range == 0);
+    assert!(sections.end == 0);
+  }
+}
@@ -0,0 +1,319 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+General purpose DMA controller.
+
+The GPDMA has eight channels, channel 0 having the highest priority. Channels
+are allocated with `allocate()` and released when dropped. All channels share
+the DMA interrupt (`isr_dma`), its handler should call `process()` to dispatch
+completion callbacks.
+
+Peripheral request lines are listed in the `request` module. A single transfer
+is limited to 4095 elements, longer ones can be split into a linked list.
+*/
+
+use core::intrinsics::abort;
+use core::iter::range;
+use core::ops::Drop;
+use core::option::Option;
+use core::option::Option::{Some, None};
+
+use hal::cortex_m3::irq::NoInterrupts;
+use hal::dma;
+use hal::lpc17xx::peripheral_clock::PeripheralClock::GPDMAClock;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Peripheral request lines.
+pub mod request {
+  #![allow(missing_docs)]
+  pub const SSP0_TX: u8 = 0;
+  pub const SSP0_RX: u8 = 1;
+  pub const SSP1_TX: u8 = 2;
+  pub const SSP1_RX: u8 = 3;
+  pub const ADC:     u8 = 4;
+  pub const I2S0:    u8 = 5;
+  pub const I2S1:    u8 = 6;
+  pub const DAC:     u8 = 7;
+  pub const UART0_TX: u8 = 8;
+  pub const UART0_RX: u8 = 9;
+  pub const UART1_TX: u8 = 10;
+  pub const UART1_RX: u8 = 11;
+  pub const UART2_TX: u8 = 12;
+  pub const UART2_RX: u8 = 13;
+  pub const UART3_TX: u8 = 14;
+  pub const UART3_RX: u8 = 15;
+}
+
+/// Number of DMA channels.
+pub static CHANNELS: uint = 8;
+
+/// Maximum number of elements in a single transfer.
+pub static MAX_TRANSFER: u32 = 0xfff;
+
+static mut ALLOCATED: u8 = 0;
+static mut CALLBACKS: [Option<dma::Callback>; 8] =
+    [None, None, None, None, None, None, None, None];
+
+/// Enables the DMA controller.
+pub fn init() {
+  GPDMAClock.enable();
+  reg::DMAC.set_IntTCClear(0xff);
+  reg::DMAC.set_IntErrClr(0xff);
+  reg::DMAC.set_Config(DMACConfigE);
+}
+
+/// Allocates a free channel, lowest numbers (highest priority) first.
+pub fn allocate() -> Option<Channel> {
+  let _crit = NoInterrupts::new();
+  for number in range(0u8, CHANNELS as u8) {
+    let bit = 1u8 << number as uint;
+    unsafe {
+      if ALLOCATED & bit == 0 {
+        ALLOCATED |= bit;
+        return Some(Channel {
+          number: number,
+          reg: channel_reg(number),
+        });
+      }
+    }
+  }
+  None
+}
+
+/// Clears the interrupt flags and calls the callbacks of finished transfers.
+pub fn process() {
+  let tc = reg::DMAC.IntTCStat();
+  let err = reg::DMAC.IntErrStat();
+  reg::DMAC.set_IntTCClear(tc);
+  reg::DMAC.set_IntErrClr(err);
+
+  for number in range(0u8, CHANNELS as u8) {
+    let bit = 1u32 << number as uint;
+    if (tc | err) & bit == 0 {
+      continue;
+    }
+    match unsafe { CALLBACKS[number as uint] } {
+      Some(callback) => callback(number, err & bit == 0),
+      None => (),
+    }
+  }
+}
+
+fn channel_reg(number: u8) -> &'static reg::DMACChannel {
+  match number {
+    0 => &reg::DMACC0,
+    1 => &reg::DMACC1,
+    2 => &reg::DMACC2,
+    3 => &reg::DMACC3,
+    4 => &reg::DMACC4,
+    5 => &reg::DMACC5,
+    6 => &reg::DMACC6,
+    _ => &reg::DMACC7,
+  }
+}
+
+fn control_word(transfer: &dma::Transfer, interrupt: bool) -> u32 {
+  if transfer.count > MAX_TRANSFER {
+    unsafe { abort() }
+  }
+
+  let width = match transfer.width {
+    dma::Width::Byte     => 0,
+    dma::Width::HalfWord => 1,
+    dma::Width::Word     => 2,
+  };
+  let increment = match transfer.direction {
+    dma::Direction::MemoryToMemory     => ControlSI | ControlDI,
+    dma::Direction::MemoryToPeripheral => ControlSI,
+    dma::Direction::PeripheralToMemory => ControlDI,
+  };
+
+  // single element bursts match the peripheral FIFO trigger levels
+  transfer.count |
+      (width << ControlSWidthShift) |
+      (width << ControlDWidthShift) |
+      increment |
+      if interrupt { ControlI } else { 0 }
+}
+
+fn config_word(transfer: &dma::Transfer) -> u32 {
+  let request = transfer.request as u32;
+  let (kind, src, dst) = match transfer.direction {
+    dma::Direction::MemoryToMemory     => (0, 0,       0),
+    dma::Direction::MemoryToPeripheral => (1, 0,       request),
+    dma::Direction::PeripheralToMemory => (2, request, 0),
+  };
+
+  (src << ConfigSrcPeripheralShift) |
+      (dst << ConfigDestPeripheralShift) |
+      (kind << ConfigTransferTypeShift) |
+      ConfigIE | ConfigITC
+}
+
+/// Linked list item, describing a transfer that runs after the previous one
+/// without CPU intervention.
+///
+/// All items of a list use the direction and request line of the transfer
+/// the list is started with.
+#[repr(C)]
+pub struct LinkedItem {
+  source: u32,
+  destination: u32,
+  next: u32,
+  control: u32,
+}
+
+impl LinkedItem {
+  /// Creates a list item, the last one (without `next`) raises the completion
+  /// interrupt.
+  pub fn new(transfer: &dma::Transfer, next: Option<&LinkedItem>)
+      -> LinkedItem {
+    LinkedItem {
+      source: transfer.source,
+      destination: transfer.destination,
+      next: match next {
+        Some(item) => item as *const LinkedItem as u32,
+        None => 0,
+      },
+      control: control_word(transfer, next.is_none()),
+    }
+  }
+}
+
+/// An allocated DMA channel.
+pub struct Channel {
+  number: u8,
+  reg: &'static reg::DMACChannel,
+}
+
+impl Channel {
+  /// Starts the transfer followed by the linked list, the callback is called
+  /// once the last item is done.
+  pub fn start_linked(&self, transfer: &dma::Transfer, next: &LinkedItem,
+      callback: Option<dma::Callback>) {
+    self.setup(transfer, next as *const LinkedItem as u32, false, callback);
+  }
+
+  fn setup(&self, transfer: &dma::Transfer, lli: u32, interrupt: bool,
+      callback: Option<dma::Callback>) {
+    let bit = 1u32 << self.number as uint;
+    unsafe { CALLBACKS[self.number as uint] = callback; }
+
+    reg::DMAC.set_IntTCClear(bit);
+    reg::DMAC.set_IntErrClr(bit);
+
+    self.reg.set_SrcAddr(transfer.source);
+    self.reg.set_DestAddr(transfer.destination);
+    self.reg.set_LLI(lli);
+    self.reg.set_Control(control_word(transfer, interrupt));
+    self.reg.set_Config(config_word(transfer) | ConfigE);
+  }
+
+  fn disable(&self) {
+    unsafe { CALLBACKS[self.number as uint] = None; }
+    let config = self.reg.Config();
+    self.reg.set_Config(config & !ConfigE);
+  }
+}
+
+impl dma::Channel for Channel {
+  fn number(&self) -> u8 {
+    self.number
+  }
+
+  fn start(&self, transfer: &dma::Transfer, callback: Option<dma::Callback>) {
+    self.setup(transfer, 0, true, callback);
+  }
+
+  fn busy(&self) -> bool {
+    reg::DMAC.EnbldChns() & (1 << self.number as uint) != 0
+  }
+
+  fn stop(&self) {
+    self.disable();
+  }
+}
+
+impl Drop for Channel {
+  fn drop(&mut self) {
+    self.disable();
+
+    let _crit = NoInterrupts::new();
+    unsafe { ALLOCATED &= !(1u8 << self.number as uint); }
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static DMACConfigE: u32 = 1 << 0;
+
+#[allow(non_upper_case_globals)]
+static ControlSWidthShift: uint = 18;
+#[allow(non_upper_case_globals)]
+static ControlDWidthShift: uint = 21;
+#[allow(non_upper_case_globals)]
+static ControlSI: u32 = 1 << 26;
+#[allow(non_upper_case_globals)]
+static ControlDI: u32 = 1 << 27;
+#[allow(non_upper_case_globals)]
+static ControlI: u32 = 1 << 31;
+
+#[allow(non_upper_case_globals)]
+static ConfigE: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static ConfigSrcPeripheralShift: uint = 1;
+#[allow(non_upper_case_globals)]
+static ConfigDestPeripheralShift: uint = 6;
+#[allow(non_upper_case_globals)]
+static ConfigTransferTypeShift: uint = 11;
+#[allow(non_upper_case_globals)]
+static ConfigIE: u32 = 1 << 14;
+#[allow(non_upper_case_globals)]
+static ConfigITC: u32 = 1 << 15;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(DMAC: u32, IntStat, IntTCStat, IntTCClear, IntErrStat, IntErrClr,
+      RawIntTCStat, RawIntErrStat, EnbldChns, SoftBReq, SoftSReq, SoftLBReq,
+      SoftLSReq, Config, Sync);
+  reg_r!( DMAC, u32, IntStat,                    IntStat);
+  reg_r!( DMAC, u32, IntTCStat,                  IntTCStat);
+  reg_w!( DMAC, u32,             set_IntTCClear, IntTCClear);
+  reg_r!( DMAC, u32, IntErrStat,                 IntErrStat);
+  reg_w!( DMAC, u32,             set_IntErrClr,  IntErrClr);
+  reg_r!( DMAC, u32, EnbldChns,                  EnbldChns);
+  reg_rw!(DMAC, u32, Config,     set_Config,     Config);
+
+  ioreg_old!(DMACChannel: u32, SrcAddr, DestAddr, LLI, Control, Config);
+  reg_rw!(DMACChannel, u32, SrcAddr,  set_SrcAddr,  SrcAddr);
+  reg_rw!(DMACChannel, u32, DestAddr, set_DestAddr, DestAddr);
+  reg_rw!(DMACChannel, u32, LLI,      set_LLI,      LLI);
+  reg_rw!(DMACChannel, u32, Control,  set_Control,  Control);
+  reg_rw!(DMACChannel, u32, Config,   set_Config,   Config);
+
+  extern {
+    #[link_name="lpc17xx_iomem_DMAC"] pub static DMAC: DMAC;
+    #[link_name="lpc17xx_iomem_DMACC0"] pub static DMACC0: DMACChannel;
+    #[link_name="lpc17xx_iomem_DMACC1"] pub static DMACC1: DMACChannel;
+    #[link_name="lpc17xx_iomem_DMACC2"] pub static DMACC2: DMACChannel;
+    #[link_name="lpc17xx_iomem_DMACC3"] pub static DMACC3: DMACChannel;
+    #[link_name="lpc17xx_iomem_DMACC4"] pub static DMACC4: DMACChannel;
+    #[link_name="lpc17xx_iomem_DMACC5"] pub static DMACC5: DMACChannel;
+    #[link_name="lpc17xx_iomem_DMACC6"] pub static DMACC6: DMACChannel;
+    #[link_name="lpc17xx_iomem_DMACC7"] pub static DMACC7: DMACChannel;
+  }
+}
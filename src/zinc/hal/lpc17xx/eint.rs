@@ -0,0 +1,108 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+External interrupts EINT0-3.
+
+Each external interrupt is attached to a dedicated pin (P2.10 to P2.13) and
+raises its own interrupt, `isr_eint_0` to `isr_eint_3`. Unlike GPIO interrupts
+they can trigger on levels, but not on both edges. Note that EINT3 shares its
+interrupt with GPIO interrupts.
+
+As with the other `GpioInterrupt` implementations, the NVIC line must be
+enabled separately. EINTn has no mask, disabling it hands its pin back to GPIO.
+*/
+
+use core::intrinsics::abort;
+use core::option::Option::{Some, None};
+
+use hal::lpc17xx::pin::{Pin, Port, Function};
+use hal::pin::{GpioInterrupt, GpioTrigger, In};
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Available external interrupts.
+#[allow(missing_docs)]
+#[derive(Copy)]
+pub enum ExtInt {
+  EInt0 = 0,
+  EInt1 = 1,
+  EInt2 = 2,
+  EInt3 = 3,
+}
+
+/// An external interrupt.
+#[derive(Copy)]
+pub struct ExternalInterrupt {
+  index: u8,
+}
+
+impl ExternalInterrupt {
+  /// Configures the pin of the given external interrupt.
+  pub fn new(eint: ExtInt) -> ExternalInterrupt {
+    let index = eint as u8;
+    Pin::new(Port::Port2, 10 + index, Function::AltFunction1, None);
+    ExternalInterrupt { index: index }
+  }
+}
+
+impl GpioInterrupt for ExternalInterrupt {
+  fn enable_interrupt(&self, trigger: GpioTrigger) {
+    let bit: u32 = 1 << (self.index as uint);
+    let (edge, high) = match trigger {
+      GpioTrigger::RisingEdge  => (true, true),
+      GpioTrigger::FallingEdge => (true, false),
+      GpioTrigger::HighLevel   => (false, true),
+      GpioTrigger::LowLevel    => (false, false),
+      GpioTrigger::BothEdges   => unsafe { abort() },
+    };
+
+    let mode: u32 = reg::EXTINT.EXTMODE() & !bit;
+    reg::EXTINT.set_EXTMODE(if edge { mode | bit } else { mode });
+    let polar: u32 = reg::EXTINT.EXTPOLAR() & !bit;
+    reg::EXTINT.set_EXTPOLAR(if high { polar | bit } else { polar });
+
+    Pin::new(Port::Port2, 10 + self.index, Function::AltFunction1, None);
+    // changing the mode may set the flag
+    self.clear_interrupt();
+  }
+
+  fn disable_interrupt(&self) {
+    // the flag is only set while the pin has the EINT function
+    Pin::new(Port::Port2, 10 + self.index, Function::Gpio, Some(In));
+    self.clear_interrupt();
+  }
+
+  fn interrupt_pending(&self) -> bool {
+    reg::EXTINT.EXTINT() & (1 << (self.index as uint)) != 0
+  }
+
+  fn clear_interrupt(&self) {
+    reg::EXTINT.set_EXTINT(1 << (self.index as uint));
+  }
+}
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(EXTINT: u32, EXTINT, _pad_0, EXTMODE, EXTPOLAR);
+  reg_rw!(EXTINT, u32, EXTINT,   set_EXTINT,   EXTINT);
+  reg_rw!(EXTINT, u32, EXTMODE,  set_EXTMODE,  EXTMODE);
+  reg_rw!(EXTINT, u32, EXTPOLAR, set_EXTPOLAR, EXTPOLAR);
+
+  extern {
+    #[link_name="lpc17xx_iomem_EXTINT"] pub static EXTINT: EXTINT;
+  }
+}
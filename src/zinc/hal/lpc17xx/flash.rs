@@ -0,0 +1,211 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Flash programming through the IAP (in-application programming) ROM routines.
+
+Sectors 0 to 15 are 4KB, sectors 16 to 29 are 32KB. Smaller parts only have
+the first sectors, and IAP fails with `InvalidArgument` beyond their flash.
+Pages are 256 bytes.
+
+IAP uses the top 32 bytes of the on-chip SRAM, which must not be used by the
+application while it runs. The clock must be initialised with `init_clock`, as
+IAP is told the CPU frequency to time the flash operations.
+*/
+
+use core::iter::range;
+use core::mem::transmute;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+use core::slice::SliceExt;
+
+use hal::cortex_m3::irq::NoInterrupts;
+use hal::flash;
+use hal::flash::Error;
+use hal::lpc17xx::system_clock::system_clock;
+
+/// Page size in bytes.
+const PAGE_SIZE: uint = 256;
+
+/// Number of sectors on the largest parts (512KB).
+const SECTOR_COUNT: uint = 30;
+
+/// The IAP entry point (thumb).
+const IAP_LOCATION: u32 = 0x1FFF1FF1;
+
+/// The flash, programmed through IAP.
+#[derive(Copy)]
+pub struct Flash;
+
+type IAPEntry = extern "C" fn(*const u32, *mut u32);
+
+/// Runs an IAP command, returns the status code and the first result word.
+fn iap(command: &[u32; 5]) -> (u32, u32) {
+  let mut result = [0u32; 5];
+  // the vector table is in flash, no interrupt may be taken while IAP runs
+  let _crit = NoInterrupts::new();
+  let entry: IAPEntry = unsafe { transmute(IAP_LOCATION) };
+  entry(command.as_ptr(), result.as_mut_ptr());
+  (result[0], result[1])
+}
+
+fn to_result(status: u32) -> Result<(), Error> {
+  match status {
+    IAP_SUCCESS                               => Ok(()),
+    IAP_SRC_ADDR_ERROR ... IAP_INVALID_SECTOR => Err(Error::InvalidArgument),
+    _                                         => Err(Error::Failed),
+  }
+}
+
+fn cclk_khz() -> u32 {
+  system_clock() / 1000
+}
+
+/// Prepares `sector` for erase or write, then runs `command`.
+fn prepare_and_run(sector: uint, command: &[u32; 5]) -> Result<(), Error> {
+  let (status, _) = iap(&[IAP_PREPARE, sector as u32, sector as u32, 0, 0]);
+  if status != IAP_SUCCESS {
+    return to_result(status);
+  }
+  let (status, _) = iap(command);
+  to_result(status)
+}
+
+impl flash::Flash for Flash {
+  fn sector_count(&self) -> uint {
+    SECTOR_COUNT
+  }
+
+  fn sector_address(&self, sector: uint) -> u32 {
+    if sector < 16 {
+      sector as u32 * 0x1000
+    } else {
+      0x10000 + (sector as u32 - 16) * 0x8000
+    }
+  }
+
+  fn sector_size(&self, sector: uint) -> u32 {
+    if sector < 16 { 0x1000 } else { 0x8000 }
+  }
+
+  fn page_size(&self) -> u32 {
+    PAGE_SIZE as u32
+  }
+
+  fn erase_sector(&self, sector: uint) -> Result<(), Error> {
+    if sector >= SECTOR_COUNT {
+      return Err(Error::InvalidArgument);
+    }
+    prepare_and_run(sector,
+        &[IAP_ERASE, sector as u32, sector as u32, cclk_khz(), 0])
+  }
+
+  fn program(&self, address: u32, data: &[u8]) -> Result<(), Error> {
+    if address as uint % PAGE_SIZE != 0 || data.len() % PAGE_SIZE != 0 {
+      return Err(Error::InvalidArgument);
+    }
+
+    // the source must be word aligned and in RAM
+    let mut page = [0u32; PAGE_SIZE / 4];
+    for index in range(0, data.len() / PAGE_SIZE) {
+      let offset = index * PAGE_SIZE;
+      let dst = address + offset as u32;
+      let sector = match flash::Flash::sector_at(self, dst) {
+        Some(sector) => sector,
+        None => return Err(Error::InvalidArgument),
+      };
+
+      copy_to_words(data.slice(offset, offset + PAGE_SIZE), &mut page);
+      let result = prepare_and_run(sector, &[IAP_COPY_RAM_TO_FLASH, dst,
+          page.as_ptr() as u32, PAGE_SIZE as u32, cclk_khz()]);
+      if result.is_err() {
+        return result;
+      }
+    }
+    Ok(())
+  }
+
+  fn verify(&self, address: u32, data: &[u8]) -> Result<(), Error> {
+    if address % 4 != 0 || data.len() % 4 != 0 {
+      return Err(Error::InvalidArgument);
+    }
+
+    let mut page = [0u32; PAGE_SIZE / 4];
+    let mut offset = 0;
+    while offset < data.len() {
+      let len = if data.len() - offset > PAGE_SIZE {
+        PAGE_SIZE
+      } else {
+        data.len() - offset
+      };
+      let dst = address + offset as u32;
+      copy_to_words(data.slice(offset, offset + len), &mut page);
+
+      match iap(&[IAP_COMPARE, dst, page.as_ptr() as u32, len as u32, 0]) {
+        (IAP_COMPARE_ERROR, mismatch) =>
+            return Err(Error::Mismatch(dst + mismatch)),
+        (status, _) => {
+          let result = to_result(status);
+          if result.is_err() {
+            return result;
+          }
+        },
+      }
+      offset += len;
+    }
+    Ok(())
+  }
+
+  fn blank_check(&self, sector: uint) -> Result<(), Error> {
+    if sector >= SECTOR_COUNT {
+      return Err(Error::InvalidArgument);
+    }
+
+    match iap(&[IAP_BLANK_CHECK, sector as u32, sector as u32, 0, 0]) {
+      (IAP_SECTOR_NOT_BLANK, offset) => Err(Error::Mismatch(
+          flash::Flash::sector_address(self, sector) + offset)),
+      (status, _) => to_result(status),
+    }
+  }
+
+  fn read(&self, address: u32, data: &mut [u8]) {
+    for i in range(0, data.len()) {
+      data[i] = unsafe { *((address + i as u32) as *const u8) };
+    }
+  }
+}
+
+/// Copies `data` into the start of `words`, in memory order.
+fn copy_to_words(data: &[u8], words: &mut [u32; PAGE_SIZE / 4]) {
+  for i in range(0, data.len() / 4) {
+    words[i] = data[i * 4] as u32 |
+               (data[i * 4 + 1] as u32 << 8) |
+               (data[i * 4 + 2] as u32 << 16) |
+               (data[i * 4 + 3] as u32 << 24);
+  }
+}
+
+const IAP_PREPARE:           u32 = 50;
+const IAP_COPY_RAM_TO_FLASH: u32 = 51;
+const IAP_ERASE:             u32 = 52;
+const IAP_BLANK_CHECK:       u32 = 53;
+const IAP_COMPARE:           u32 = 56;
+
+const IAP_SUCCESS:           u32 = 0;
+const IAP_SRC_ADDR_ERROR:    u32 = 2;
+const IAP_INVALID_SECTOR:    u32 = 7;
+const IAP_SECTOR_NOT_BLANK:  u32 = 8;
+const IAP_COMPARE_ERROR:     u32 = 10;
@@ -0,0 +1,267 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+I2C configuration.
+
+Supports I2C0, I2C1 and I2C2 in master mode. The SDA and SCL pins must be
+configured with the respective `sdaN` and `sclN` functions separately.
+*/
+
+use core::iter::range;
+use core::kinds::Copy;
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+use core::slice::SliceExt;
+
+use hal::i2c;
+use hal::i2c::Error::{AddressNack, DataNack, ArbitrationLost, BusError};
+use hal::lpc17xx::peripheral_clock::PeripheralClock;
+use hal::lpc17xx::peripheral_clock::PeripheralClock::{I2C0Clock, I2C1Clock, I2C2Clock};
+
+use self::I2CPeripheral::*;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+#[path="../../util/wait_for.rs"] mod wait_for;
+
+/// Available I2C peripherals.
+#[allow(missing_docs)]
+#[derive(Copy)]
+pub enum I2CPeripheral {
+  I2C0,
+  I2C1,
+  I2C2,
+}
+
+impl I2CPeripheral {
+  fn reg(self) -> &'static reg::I2C {
+    match self {
+      I2C0 => &reg::I2C0,
+      I2C1 => &reg::I2C1,
+      I2C2 => &reg::I2C2,
+    }
+  }
+
+  fn peripheral_clock(self) -> PeripheralClock {
+    match self {
+      I2C0 => I2C0Clock,
+      I2C1 => I2C1Clock,
+      I2C2 => I2C2Clock,
+    }
+  }
+}
+
+/// Structure describing an I2C instance.
+#[derive(Copy)]
+pub struct I2C {
+  reg: &'static reg::I2C,
+}
+
+impl I2C {
+  /// Create and setup an I2C master running at given SCL frequency in Hz.
+  pub fn new(peripheral: I2CPeripheral, frequency: u32) -> I2C {
+    let clock = peripheral.peripheral_clock();
+    clock.enable();
+
+    let i2c = I2C {
+      reg: peripheral.reg(),
+    };
+
+    // SCL high and low times are counted in peripheral clock cycles, use 50%
+    // duty cycle.
+    let half_period = clock.frequency() / frequency / 2;
+    i2c.reg.set_SCLH(half_period);
+    i2c.reg.set_SCLL(half_period);
+
+    i2c.reg.set_CONCLR(CONAA | CONSI | CONSTA | CONI2EN);
+    i2c.reg.set_CONSET(CONI2EN);
+
+    i2c
+  }
+
+  /// Clears SI to let the state machine proceed and waits for the next state.
+  fn step(&self) -> u32 {
+    self.reg.set_CONCLR(CONSI);
+    wait_for!(self.reg.CONSET() & CONSI == CONSI);
+    self.reg.STAT()
+  }
+
+  fn start(&self) -> Result<(), i2c::Error> {
+    self.reg.set_CONSET(CONSTA);
+    let status = self.step();
+    self.reg.set_CONCLR(CONSTA);
+    match status {
+      STATStart | STATRepeatedStart => Ok(()),
+      other => Err(status_to_error(other)),
+    }
+  }
+
+  fn send_address(&self, address: u8, read: bool) -> Result<(), i2c::Error> {
+    let rw: u8 = if read { 1 } else { 0 };
+    self.reg.set_DAT(((address << 1) | rw) as u32);
+    match self.step() {
+      STATAddressWriteAck | STATAddressReadAck => Ok(()),
+      STATAddressWriteNack | STATAddressReadNack => Err(AddressNack),
+      other => Err(status_to_error(other)),
+    }
+  }
+
+  fn send(&self, value: u8) -> Result<(), i2c::Error> {
+    self.reg.set_DAT(value as u32);
+    match self.step() {
+      STATDataSentAck => Ok(()),
+      STATDataSentNack => Err(DataNack),
+      other => Err(status_to_error(other)),
+    }
+  }
+
+  fn receive(&self, ack: bool) -> Result<u8, i2c::Error> {
+    if ack {
+      self.reg.set_CONSET(CONAA);
+    } else {
+      self.reg.set_CONCLR(CONAA);
+    }
+    match self.step() {
+      STATDataReceivedAck | STATDataReceivedNack => Ok(self.reg.DAT() as u8),
+      other => Err(status_to_error(other)),
+    }
+  }
+
+  fn stop(&self) {
+    self.reg.set_CONSET(CONSTO);
+    self.reg.set_CONCLR(CONSI);
+    wait_for!(self.reg.CONSET() & CONSTO == 0);
+  }
+
+  fn transfer(&self, address: u8, wdata: &[u8], rdata: &mut [u8])
+      -> Result<(), i2c::Error> {
+    if wdata.len() > 0 || rdata.len() == 0 {
+      match self.start().and_then(|()| self.send_address(address, false)) {
+        Ok(()) => (),
+        err => return err,
+      }
+      for b in wdata.iter() {
+        match self.send(*b) {
+          Ok(()) => (),
+          err => return err,
+        }
+      }
+    }
+
+    if rdata.len() > 0 {
+      // this is a repeated START if data was written above
+      match self.start().and_then(|()| self.send_address(address, true)) {
+        Ok(()) => (),
+        err => return err,
+      }
+      let last = rdata.len() - 1;
+      for i in range(0, rdata.len()) {
+        // the last byte is not acknowledged to signal the end of read
+        match self.receive(i != last) {
+          Ok(value) => rdata[i] = value,
+          Err(e) => return Err(e),
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  fn transfer_and_stop(&self, address: u8, wdata: &[u8], rdata: &mut [u8])
+      -> Result<(), i2c::Error> {
+    let result = self.transfer(address, wdata, rdata);
+    match result {
+      // the bus is owned by another master, just release the state machine
+      Err(ArbitrationLost) => self.reg.set_CONCLR(CONSI),
+      _ => self.stop(),
+    }
+    result
+  }
+}
+
+impl i2c::I2C for I2C {
+  fn write(&self, address: u8, data: &[u8]) -> Result<(), i2c::Error> {
+    self.transfer_and_stop(address, data, &mut [])
+  }
+
+  fn read(&self, address: u8, data: &mut [u8]) -> Result<(), i2c::Error> {
+    self.transfer_and_stop(address, &[], data)
+  }
+
+  fn write_read(&self, address: u8, wdata: &[u8], rdata: &mut [u8])
+      -> Result<(), i2c::Error> {
+    self.transfer_and_stop(address, wdata, rdata)
+  }
+}
+
+fn status_to_error(status: u32) -> i2c::Error {
+  match status {
+    STATArbitrationLost => ArbitrationLost,
+    _ => BusError,
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static CONAA:    u32 = 0x04;
+#[allow(non_upper_case_globals)]
+static CONSI:    u32 = 0x08;
+#[allow(non_upper_case_globals)]
+static CONSTO:   u32 = 0x10;
+#[allow(non_upper_case_globals)]
+static CONSTA:   u32 = 0x20;
+#[allow(non_upper_case_globals)]
+static CONI2EN:  u32 = 0x40;
+
+#[allow(non_upper_case_globals)]
+const STATStart:             u32 = 0x08;
+#[allow(non_upper_case_globals)]
+const STATRepeatedStart:     u32 = 0x10;
+#[allow(non_upper_case_globals)]
+const STATAddressWriteAck:   u32 = 0x18;
+#[allow(non_upper_case_globals)]
+const STATAddressWriteNack:  u32 = 0x20;
+#[allow(non_upper_case_globals)]
+const STATDataSentAck:       u32 = 0x28;
+#[allow(non_upper_case_globals)]
+const STATDataSentNack:      u32 = 0x30;
+#[allow(non_upper_case_globals)]
+const STATArbitrationLost:   u32 = 0x38;
+#[allow(non_upper_case_globals)]
+const STATAddressReadAck:    u32 = 0x40;
+#[allow(non_upper_case_globals)]
+const STATAddressReadNack:   u32 = 0x48;
+#[allow(non_upper_case_globals)]
+const STATDataReceivedAck:   u32 = 0x50;
+#[allow(non_upper_case_globals)]
+const STATDataReceivedNack:  u32 = 0x58;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(I2C: u32, CONSET, STAT, DAT, ADR0, SCLH, SCLL, CONCLR);
+  reg_rw!(I2C, u32, CONSET, set_CONSET, CONSET);
+  reg_r!( I2C, u32, STAT,               STAT);
+  reg_rw!(I2C, u32, DAT,    set_DAT,    DAT);
+  reg_rw!(I2C, u32, ADR0,   set_ADR0,   ADR0);
+  reg_rw!(I2C, u32, SCLH,   set_SCLH,   SCLH);
+  reg_rw!(I2C, u32, SCLL,   set_SCLL,   SCLL);
+  reg_w!( I2C, u32,         set_CONCLR, CONCLR);
+
+  extern {
+    #[link_name="lpc17xx_iomem_I2C0"] pub static I2C0: I2C;
+    #[link_name="lpc17xx_iomem_I2C1"] pub static I2C1: I2C;
+    #[link_name="lpc17xx_iomem_I2C2"] pub static I2C2: I2C;
+  }
+}
@@ -17,7 +17,17 @@
 
 pub mod system_clock;
 pub mod peripheral_clock;
+pub mod adc;
+pub mod can;
+pub mod dma;
+pub mod eint;
+pub mod flash;
+pub mod i2c;
 pub mod pin;
-// pub mod ssp;
+pub mod power;
+pub mod pwm;
+pub mod rtc;
+pub mod ssp;
 pub mod timer;
 pub mod uart;
+pub mod wdt;
@@ -18,6 +18,10 @@ Pin configuration.
 
 Some pins that could be configured here may be missing from actual MCU depending
 on the package.
+
+GPIO interrupts are only available on ports 0 and 2, trigger on edges only and
+are all raised through `isr_eint_3`. See `eint` for level-triggered external
+interrupts.
 */
 
 use core::intrinsics::abort;
@@ -100,6 +104,14 @@ impl Pin {
     }
   }
 
+  fn gpiointreg(&self) -> &reg::GpioInt {
+    match self.port {
+      Port0 => &reg::GPIOINT_0,
+      Port2 => &reg::GPIOINT_2,
+      _     => unsafe { abort() },
+    }
+  }
+
   fn get_pinsel_reg_and_offset(&self) -> (u8, &reg::PINSEL) {
     match self.port {
       Port0 => match self.pin {
@@ -165,6 +177,42 @@ impl ::hal::pin::Gpio for Pin {
   }
 }
 
+impl ::hal::pin::GpioInterrupt for Pin {
+  fn enable_interrupt(&self, trigger: ::hal::pin::GpioTrigger) {
+    use hal::pin::GpioTrigger::*;
+    let bit: u32 = 1 << (self.pin as uint);
+    let reg = self.gpiointreg();
+    let (rising, falling) = match trigger {
+      RisingEdge  => (true, false),
+      FallingEdge => (false, true),
+      BothEdges   => (true, true),
+      _           => unsafe { abort() },
+    };
+
+    let enr: u32 = reg.EnR() & !bit;
+    reg.set_EnR(if rising { enr | bit } else { enr });
+    let enf: u32 = reg.EnF() & !bit;
+    reg.set_EnF(if falling { enf | bit } else { enf });
+  }
+
+  fn disable_interrupt(&self) {
+    let mask: u32 = !(1 << (self.pin as uint));
+    let reg = self.gpiointreg();
+    reg.set_EnR(reg.EnR() & mask);
+    reg.set_EnF(reg.EnF() & mask);
+  }
+
+  fn interrupt_pending(&self) -> bool {
+    let bit: u32 = 1 << (self.pin as uint);
+    let reg = self.gpiointreg();
+    (reg.StatR() | reg.StatF()) & bit != 0
+  }
+
+  fn clear_interrupt(&self) {
+    self.gpiointreg().set_Clr(1 << (self.pin as uint));
+  }
+}
+
 /// Sets the state of trace port interface.
 pub fn set_trace_port_interface_enabled(enabled: bool) {
   let value: u32 = if enabled { 0b1000 } else { 0 };
@@ -202,4 +250,16 @@ mod reg {
     #[link_name="lpc17xx_iomem_GPIO3"] pub static GPIO_3: Gpio;
     #[link_name="lpc17xx_iomem_GPIO4"] pub static GPIO_4: Gpio;
   }
+
+  ioreg_old!(GpioInt: u32, StatR, StatF, Clr, EnR, EnF);
+  reg_r!( GpioInt, u32, StatR,          StatR);
+  reg_r!( GpioInt, u32, StatF,          StatF);
+  reg_w!( GpioInt, u32,        set_Clr, Clr);
+  reg_rw!(GpioInt, u32, EnR,   set_EnR, EnR);
+  reg_rw!(GpioInt, u32, EnF,   set_EnF, EnF);
+
+  extern {
+    #[link_name="lpc17xx_iomem_GPIOINT0"] pub static GPIOINT_0: GpioInt;
+    #[link_name="lpc17xx_iomem_GPIOINT2"] pub static GPIOINT_2: GpioInt;
+  }
 }
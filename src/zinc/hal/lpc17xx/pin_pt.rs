@@ -27,6 +27,7 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
     add_node_dependency(&node, port_node);
     for pin_node in port_node.subnodes().iter() {
       pin_node.materializer.set(Some(build_pin as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+      pin_node.schema.set(Some(pin_schema as fn() -> node::NodeSchema));
       add_node_dependency(port_node, pin_node);
       super::add_node_dependency_on_clock(builder, pin_node);
     }
@@ -37,6 +38,20 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
+// Pins used by peripherals get their direction and function from the
+// peripheral node mutators.
+fn pin_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("direction", node::StrAttribute,
+          node::Optional, node::Unrestricted),
+      node::AttributeSchema::new("function", node::StrAttribute,
+          node::Optional, node::Unrestricted),
+    ),
+    subnodes: Some(vec!()),
+  }
+}
+
 fn build_pin(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   let port_node = node.parent.clone().unwrap().upgrade().unwrap();
   let ref port_path = port_node.path;
@@ -60,7 +75,11 @@ fn build_pin(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   let direction_str = if node.get_string_attr("function").is_some() {
     "core::option::Option::None"
   } else {
-    match node.get_string_attr("direction").unwrap().as_slice() {
+    let direction = match node.get_required_string_attr(cx, "direction") {
+      Some(direction) => direction,
+      None => return,
+    };
+    match direction.as_slice() {
       "out" => "core::option::Option::Some(zinc::hal::pin::Out)",
       "in"  => "core::option::Option::Some(zinc::hal::pin::In)",
       other => {
@@ -23,6 +23,7 @@ mod system_clock_pt;
 mod timer_pt;
 mod pin_pt;
 mod uart_pt;
+mod watchdog_pt;
 
 mod pinmap;
 
@@ -36,6 +37,7 @@ pub fn attach(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
       "timer" => timer_pt::attach(builder, cx, sub.clone()),
       "uart"  => uart_pt::attach(builder, cx, sub.clone()),
       "gpio"  => pin_pt::attach(builder, cx, sub.clone()),
+      "watchdog" => watchdog_pt::attach(builder, cx, sub.clone()),
       _ => (),
     }
   }
@@ -43,7 +45,12 @@ pub fn attach(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
 
 fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
-  node.expect_subnodes(cx, &["clock", "timer", "uart", "gpio"]);
+  node.expect_subnodes(cx, &["clock", "timer", "uart", "gpio", "watchdog"]);
+  if node.get_by_path("watchdog").is_none() {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        "subnode `watchdog` must be present, use \
+         `watchdog { mode = \"disabled\"; }` to keep the watchdog off");
+  }
 }
 
 pub fn add_node_dependency_on_clock(builder: &mut Builder,
@@ -64,6 +71,17 @@ mod test {
     fails_to_build("lpc17xx@mcu { key = 1; }");
   }
 
+  #[test]
+  fn fails_to_build_without_watchdog() {
+    fails_to_build("lpc17xx@mcu {
+      clock {
+        source = \"main-oscillator\";
+        source_frequency = 12_000_000;
+        pll { m = 50; n = 3; divisor = 4; }
+      }
+    }");
+  }
+
   #[test]
   fn builds_lpc17xx_pt() {
     with_parsed("
@@ -103,6 +121,10 @@ mod test {
             led4@23 { direction = \"out\"; }
           }
         }
+
+        watchdog {
+          mode = \"disabled\";
+        }
       }
 
       os {
@@ -0,0 +1,112 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Power management.
+
+In sleep mode only the core clock is stopped. In deep-sleep and power-down
+modes the main oscillator and PLL0 are powered down too, and the chip wakes
+up running from the internal RC oscillator, so the clock configuration passed
+to `sleep` is applied again before returning. Power-down also turns off the
+flash and the internal RC oscillator, trading a longer wakeup time for lower
+current.
+
+Any enabled interrupt wakes the chip from sleep. From deep-sleep and
+power-down only EINT0-3, GPIO interrupts, the RTC, BOD, NMI and the USB and CAN
+activity interrupts can wake it up, so the wakeup sources are configured by
+enabling their interrupts. Interrupts are disabled while sleeping: the
+interrupt that woke the chip is only taken once the clock is restored.
+*/
+
+use hal::cortex_m3::irq::NoInterrupts;
+use hal::cortex_m3::scb;
+use hal::lpc17xx::system_clock::{Clock, init_clock};
+use util::support::wfi;
+
+use self::SleepMode::*;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Low power modes the chip returns from.
+#[derive(Copy)]
+pub enum SleepMode {
+  /// Core clock stopped, peripherals keep running.
+  Sleep,
+  /// Clocks stopped except for the RTC and watchdog oscillators.
+  DeepSleep,
+  /// Like deep-sleep, with the flash and internal RC oscillator powered down.
+  PowerDown,
+}
+
+/// Puts the chip into `mode` until an interrupt wakes it up, then restores
+/// `clock`.
+pub fn sleep(mode: SleepMode, clock: &Clock) {
+  let (pm, deep) = match mode {
+    Sleep     => (0, false),
+    DeepSleep => (0, true),
+    PowerDown => (PCONPowerDown, true),
+  };
+
+  let _crit = NoInterrupts::new();
+  reg::PCON.set_PCON(pm);
+  scb::set_sleep_deep(deep);
+  wfi();
+  scb::set_sleep_deep(false);
+
+  if deep {
+    init_clock(clock);
+  }
+}
+
+/// Enters deep power-down mode, in which everything but the RTC is off.
+///
+/// The chip only wakes up through the RESET pin or the RTC alarm, and restarts
+/// from the reset vector. RAM is lost but the RTC backup registers are kept.
+pub fn deep_power_down() -> ! {
+  let _crit = NoInterrupts::new();
+  reg::PCON.set_PCON(PCONDeepPowerDown);
+  scb::set_sleep_deep(true);
+  loop {
+    wfi();
+  }
+}
+
+/// Returns true if the chip was reset by waking up from deep power-down, and
+/// clears the flag.
+pub fn woke_from_deep_power_down() -> bool {
+  let woke = reg::PCON.PCON() & PCONDPDFlag != 0;
+  if woke {
+    reg::PCON.set_PCON(PCONDPDFlag);
+  }
+  woke
+}
+
+#[allow(non_upper_case_globals)]
+static PCONPowerDown: u32 = 0b01;
+#[allow(non_upper_case_globals)]
+static PCONDeepPowerDown: u32 = 0b11;
+#[allow(non_upper_case_globals)]
+static PCONDPDFlag: u32 = 1 << 11;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(PCON: u32, PCON);
+  reg_rw!(PCON, u32, PCON, set_PCON, PCON);
+
+  extern {
+    #[link_name="lpc17xx_iomem_PCON"] pub static PCON: PCON;
+  }
+}
@@ -0,0 +1,201 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+PWM configuration.
+
+This code supports the six single edge outputs of PWM1, PWM1.1 to PWM1.6. The
+counter ticks every microsecond and is reset by match register 0, which holds
+the period. Match registers are shadowed and latched through the latch enable
+register, so new values take effect when the next period starts.
+
+Active low channels match at `period - width`, so the pulse is at the end of
+the period.
+*/
+
+use core::cell::Cell;
+use core::intrinsics::abort;
+use core::iter::range;
+
+use hal::lpc17xx::peripheral_clock::PeripheralClock::PWM1Clock;
+use hal::pwm;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Number of PWM1 outputs.
+static CHANNELS: uint = 6;
+
+/// Structure describing the PWM1 peripheral.
+pub struct PWM {
+  reg: &'static reg::PWM,
+  period: Cell<u32>,
+  widths: Cell<[u32; 6]>,
+  active_low: Cell<u8>,
+}
+
+impl PWM {
+  /// Create and start PWM1 with all outputs disabled.
+  pub fn new(period_us: u32) -> PWM {
+    PWM1Clock.enable();
+
+    let pwm = PWM {
+      reg: &reg::PWM1,
+      period: Cell::new(period_us),
+      widths: Cell::new([0; 6]),
+      active_low: Cell::new(0),
+    };
+
+    pwm.reg.set_TCR(TCRCounterReset);
+    pwm.reg.set_CTCR(0);
+    pwm.reg.set_PR(PWM1Clock.frequency() / 1_000_000 - 1);
+    pwm.reg.set_MCR(MCRResetOnMR0);
+    pwm.reg.set_PCR(0);
+    pwm.reg.set_MR0(period_us);
+    for channel in range(1u8, CHANNELS as u8 + 1) {
+      pwm.update_match(channel);
+    }
+    pwm.reg.set_LER(0x7f);
+    pwm.reg.set_TCR(TCRCounterEnable | TCRPWMEnable);
+
+    pwm
+  }
+
+  fn check_channel(channel: u8) {
+    if channel < 1 || channel as uint > CHANNELS {
+      unsafe { abort() }
+    }
+  }
+
+  fn update_match(&self, channel: u8) {
+    let period = self.period.get();
+    let width = self.widths.get()[channel as uint - 1];
+    let width = if width > period { period } else { width };
+    let value = if self.active_low.get() & (1 << channel as uint) != 0 {
+      period - width
+    } else {
+      width
+    };
+    self.reg.set_MR(channel as uint, value);
+  }
+
+  fn latch(&self, mask: u32) {
+    let ler = self.reg.LER();
+    self.reg.set_LER(ler | mask);
+  }
+}
+
+impl pwm::PWM for PWM {
+  fn set_period_us(&self, period_us: u32) {
+    self.period.set(period_us);
+    self.reg.set_MR0(period_us);
+    for channel in range(1u8, CHANNELS as u8 + 1) {
+      self.update_match(channel);
+    }
+    self.latch(0x7f);
+  }
+
+  fn period_us(&self) -> u32 {
+    self.period.get()
+  }
+
+  fn set_pulse_width_us(&self, channel: u8, width_us: u32) {
+    PWM::check_channel(channel);
+    let mut widths = self.widths.get();
+    widths[channel as uint - 1] = width_us;
+    self.widths.set(widths);
+    self.update_match(channel);
+    self.latch(1 << channel as uint);
+  }
+
+  fn pulse_width_us(&self, channel: u8) -> u32 {
+    PWM::check_channel(channel);
+    let width = self.widths.get()[channel as uint - 1];
+    let period = self.period.get();
+    if width > period { period } else { width }
+  }
+
+  fn set_polarity(&self, channel: u8, polarity: pwm::Polarity) {
+    PWM::check_channel(channel);
+    let bit = 1u8 << channel as uint;
+    let active_low = self.active_low.get();
+    self.active_low.set(match polarity {
+      pwm::Polarity::ActiveHigh => active_low & !bit,
+      pwm::Polarity::ActiveLow  => active_low | bit,
+    });
+    self.update_match(channel);
+    self.latch(1 << channel as uint);
+  }
+
+  fn enable(&self, channel: u8) {
+    PWM::check_channel(channel);
+    let pcr = self.reg.PCR();
+    self.reg.set_PCR(pcr | (1 << (PCREnableShift + channel as uint)));
+  }
+
+  fn disable(&self, channel: u8) {
+    PWM::check_channel(channel);
+    let pcr = self.reg.PCR();
+    self.reg.set_PCR(pcr & !(1 << (PCREnableShift + channel as uint)));
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static TCRCounterEnable: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static TCRCounterReset: u32 = 1 << 1;
+#[allow(non_upper_case_globals)]
+static TCRPWMEnable: u32 = 1 << 3;
+
+#[allow(non_upper_case_globals)]
+static MCRResetOnMR0: u32 = 1 << 1;
+
+#[allow(non_upper_case_globals)]
+static PCREnableShift: uint = 8;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(PWM: u32, IR, TCR, TC, PR, PC, MCR, MR0, MR1, MR2, MR3, CCR, CR0,
+      CR1, CR2, CR3, _pad_0, MR4, MR5, MR6, PCR, LER, _pad_1, _pad_2, _pad_3,
+      _pad_4, _pad_5, _pad_6, _pad_7, CTCR);
+  reg_rw!(PWM, u32, IR,   set_IR,   IR);
+  reg_rw!(PWM, u32, TCR,  set_TCR,  TCR);
+  reg_rw!(PWM, u32, TC,   set_TC,   TC);
+  reg_rw!(PWM, u32, PR,   set_PR,   PR);
+  reg_rw!(PWM, u32, MCR,  set_MCR,  MCR);
+  reg_rw!(PWM, u32, MR0,  set_MR0,  MR0);
+  reg_rw!(PWM, u32, PCR,  set_PCR,  PCR);
+  reg_rw!(PWM, u32, LER,  set_LER,  LER);
+  reg_rw!(PWM, u32, CTCR, set_CTCR, CTCR);
+
+  impl PWM {
+    /// Sets the match register of the output.
+    #[allow(non_snake_case)]
+    pub fn set_MR(&self, channel: uint, value: u32) {
+      match channel {
+        1 => self.MR1.set(value),
+        2 => self.MR2.set(value),
+        3 => self.MR3.set(value),
+        4 => self.MR4.set(value),
+        5 => self.MR5.set(value),
+        _ => self.MR6.set(value),
+      }
+    }
+  }
+
+  extern {
+    #[link_name="lpc17xx_iomem_PWM1"] pub static PWM1: PWM;
+  }
+}
@@ -0,0 +1,181 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Real-time clock.
+
+The RTC runs from its own 32.768 kHz oscillator and keeps counting from the
+battery supply while the rest of the chip is off, as do the five general
+purpose backup registers. The alarm raises `isr_rtc`, IRQ 17, which the caller
+must enable.
+*/
+
+use core::intrinsics::abort;
+
+use hal::lpc17xx::peripheral_clock::PeripheralClock;
+use hal::rtc;
+use hal::rtc::DateTime;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// The real-time clock.
+#[derive(Copy)]
+pub struct RTC;
+
+impl RTC {
+  /// Powers the RTC interface and starts the clock if it is not running yet.
+  pub fn new() -> RTC {
+    PeripheralClock::RTCClock.enable();
+    reg::RTC.set_CIIR(0);
+    reg::RTC.set_CCR(CCRClockEnable);
+    RTC
+  }
+}
+
+impl rtc::RTC for RTC {
+  fn set_time(&self, time: &DateTime) {
+    reg::RTC.set_CCR(0);
+    reg::RTC.set_SEC(time.second as u32);
+    reg::RTC.set_MIN(time.minute as u32);
+    reg::RTC.set_HOUR(time.hour as u32);
+    reg::RTC.set_DOM(time.day as u32);
+    reg::RTC.set_DOW(time.day_of_week() as u32);
+    reg::RTC.set_DOY(time.day_of_year() as u32);
+    reg::RTC.set_MONTH(time.month as u32);
+    reg::RTC.set_YEAR(time.year as u32);
+    reg::RTC.set_CCR(CCRClockEnable);
+  }
+
+  fn time(&self) -> DateTime {
+    // The consolidated registers are not latched together, re-read the time
+    // if it rolled over in between.
+    let mut ctime0 = reg::RTC.CTIME0();
+    let mut ctime1 = reg::RTC.CTIME1();
+    while reg::RTC.CTIME0() != ctime0 {
+      ctime0 = reg::RTC.CTIME0();
+      ctime1 = reg::RTC.CTIME1();
+    }
+
+    DateTime {
+      year: ((ctime1 >> 16) & 0xfff) as u16,
+      month: ((ctime1 >> 8) & 0xf) as u8,
+      day: (ctime1 & 0x1f) as u8,
+      hour: ((ctime0 >> 16) & 0x1f) as u8,
+      minute: ((ctime0 >> 8) & 0x3f) as u8,
+      second: (ctime0 & 0x3f) as u8,
+    }
+  }
+
+  fn set_alarm(&self, time: &DateTime) {
+    reg::RTC.set_AMR(AMRMaskAll);
+    reg::RTC.set_ALSEC(time.second as u32);
+    reg::RTC.set_ALMIN(time.minute as u32);
+    reg::RTC.set_ALHOUR(time.hour as u32);
+    reg::RTC.set_ALDOM(time.day as u32);
+    reg::RTC.set_ALMON(time.month as u32);
+    reg::RTC.set_ALYEAR(time.year as u32);
+    self.clear_alarm();
+    reg::RTC.set_AMR(AMRMaskDOW | AMRMaskDOY);
+  }
+
+  fn disable_alarm(&self) {
+    reg::RTC.set_AMR(AMRMaskAll);
+  }
+
+  fn alarm_pending(&self) -> bool {
+    reg::RTC.ILR() & ILRAlarm != 0
+  }
+
+  fn clear_alarm(&self) {
+    reg::RTC.set_ILR(ILRAlarm);
+  }
+
+  fn backup_registers(&self) -> uint {
+    5
+  }
+
+  fn read_backup(&self, index: uint) -> u32 {
+    match index {
+      0 => reg::RTC.GPREG0(),
+      1 => reg::RTC.GPREG1(),
+      2 => reg::RTC.GPREG2(),
+      3 => reg::RTC.GPREG3(),
+      4 => reg::RTC.GPREG4(),
+      _ => unsafe { abort() },
+    }
+  }
+
+  fn write_backup(&self, index: uint, value: u32) {
+    match index {
+      0 => reg::RTC.set_GPREG0(value),
+      1 => reg::RTC.set_GPREG1(value),
+      2 => reg::RTC.set_GPREG2(value),
+      3 => reg::RTC.set_GPREG3(value),
+      4 => reg::RTC.set_GPREG4(value),
+      _ => unsafe { abort() },
+    }
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static CCRClockEnable: u32 = 1 << 0;
+
+#[allow(non_upper_case_globals)]
+static ILRAlarm: u32 = 1 << 1;
+
+#[allow(non_upper_case_globals)]
+static AMRMaskDOW: u32 = 1 << 4;
+#[allow(non_upper_case_globals)]
+static AMRMaskDOY: u32 = 1 << 5;
+#[allow(non_upper_case_globals)]
+static AMRMaskAll: u32 = 0xff;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(RTC: u32, ILR, _pad_0, CCR, CIIR, AMR, CTIME0, CTIME1, CTIME2,
+             SEC, MIN, HOUR, DOM, DOW, DOY, MONTH, YEAR, CALIBRATION,
+             GPREG0, GPREG1, GPREG2, GPREG3, GPREG4, RTC_AUXEN, RTC_AUX,
+             ALSEC, ALMIN, ALHOUR, ALDOM, ALDOW, ALDOY, ALMON, ALYEAR);
+  reg_rw!(RTC, u32, ILR,    set_ILR,    ILR);
+  reg_rw!(RTC, u32, CCR,    set_CCR,    CCR);
+  reg_rw!(RTC, u32, CIIR,   set_CIIR,   CIIR);
+  reg_rw!(RTC, u32, AMR,    set_AMR,    AMR);
+  reg_r!( RTC, u32, CTIME0,             CTIME0);
+  reg_r!( RTC, u32, CTIME1,             CTIME1);
+  reg_rw!(RTC, u32, SEC,    set_SEC,    SEC);
+  reg_rw!(RTC, u32, MIN,    set_MIN,    MIN);
+  reg_rw!(RTC, u32, HOUR,   set_HOUR,   HOUR);
+  reg_rw!(RTC, u32, DOM,    set_DOM,    DOM);
+  reg_rw!(RTC, u32, DOW,    set_DOW,    DOW);
+  reg_rw!(RTC, u32, DOY,    set_DOY,    DOY);
+  reg_rw!(RTC, u32, MONTH,  set_MONTH,  MONTH);
+  reg_rw!(RTC, u32, YEAR,   set_YEAR,   YEAR);
+  reg_rw!(RTC, u32, GPREG0, set_GPREG0, GPREG0);
+  reg_rw!(RTC, u32, GPREG1, set_GPREG1, GPREG1);
+  reg_rw!(RTC, u32, GPREG2, set_GPREG2, GPREG2);
+  reg_rw!(RTC, u32, GPREG3, set_GPREG3, GPREG3);
+  reg_rw!(RTC, u32, GPREG4, set_GPREG4, GPREG4);
+  reg_rw!(RTC, u32, ALSEC,  set_ALSEC,  ALSEC);
+  reg_rw!(RTC, u32, ALMIN,  set_ALMIN,  ALMIN);
+  reg_rw!(RTC, u32, ALHOUR, set_ALHOUR, ALHOUR);
+  reg_rw!(RTC, u32, ALDOM,  set_ALDOM,  ALDOM);
+  reg_rw!(RTC, u32, ALMON,  set_ALMON,  ALMON);
+  reg_rw!(RTC, u32, ALYEAR, set_ALYEAR, ALYEAR);
+
+  extern {
+    #[link_name="lpc17xx_iomem_RTC"] pub static RTC: RTC;
+  }
+}
@@ -18,21 +18,35 @@ SSP configuration.
 
 Currently supports only SPI mode. Note that `SPI` is not the same peripheral and
 it's currently not supported at all.
+
+Bulk transfers of 8-bit frames can be offloaded to the DMA controller with
+`write_dma()` and `transfer_dma()`.
 */
 
 use core::intrinsics::abort;
-
-use hal::lpc17xx::peripheral_clock::{PeripheralClock, SSP0Clock, SSP1Clock};
+use core::kinds::Copy;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::slice::SliceExt;
+
+use hal::dma;
+use hal::dma::Channel;
+use hal::lpc17xx::dma as gpdma;
+use hal::lpc17xx::peripheral_clock::PeripheralClock;
+use hal::lpc17xx::peripheral_clock::PeripheralClock::{SSP0Clock, SSP1Clock};
+use hal::lpc17xx::pin::{Pin, Port, Function};
 use hal::lpc17xx::system_clock::system_clock;
-use hal::pin::PinConf_;
 use hal::spi;
 
+use self::SSPPeripheral::*;
+
 #[path="../../util/ioreg.rs"] mod ioreg;
+#[path="../../util/wait_for.rs"] mod wait_for;
 
 /// SPI configuration.
 ///
 /// This configuration doesn't manage the chip-select pin, it must be configured
-/// and used externally via GPIOConf.
+/// and used externally as GPIO.
 pub struct SPIConf {
   /// Peripheral to use, mcu-specific.
   pub peripheral: SSPPeripheral,
@@ -45,12 +59,12 @@ pub struct SPIConf {
   /// The divisor is currently hardcoded and is equal to 1.
   pub frequency: u32,
 
-  /// MOSI pin to use, supports NotConnected pin.
-  pub mosi: PinConf_,
-  /// MISO pin to use, supports NotConnected pin.
-  pub miso: PinConf_,
-  /// SCLK pin to use, supports NotConnected pin.
-  pub sclk: PinConf_,
+  /// MOSI pin to use as port, index and SSP function, None if not connected.
+  pub mosi: Option<(Port, u8, Function)>,
+  /// MISO pin to use as port, index and SSP function, None if not connected.
+  pub miso: Option<(Port, u8, Function)>,
+  /// SCLK pin to use as port, index and SSP function, None if not connected.
+  pub sclk: Option<(Port, u8, Function)>,
 }
 
 impl SPIConf {
@@ -67,25 +81,35 @@ impl SPIConf {
     ssp.set_format(self.bits, self.mode);
     ssp.set_frequency(self.frequency);
 
-    self.mosi.setup();
-    self.miso.setup();
-    self.sclk.setup();
+    setup_pin(self.mosi);
+    setup_pin(self.miso);
+    setup_pin(self.sclk);
 
     ssp
   }
 }
 
+fn setup_pin(pin: Option<(Port, u8, Function)>) {
+  match pin {
+    Some((port, index, function)) => { Pin::new(port, index, function, None); },
+    None => (),
+  }
+}
+
 /// Opaque object that manages the configured peripheral.
-#[allow(dead_code)]
+#[derive(Copy)]
 pub struct SSP {
-  peripheral: SSPPeripheral, // TODO(farcaller): clean up the warning
+  peripheral: SSPPeripheral,
   reg: &'static reg::SSP,
 }
 
+/// Available SSP peripherals.
+#[allow(missing_docs)]
+#[derive(Copy)]
 pub enum SSPPeripheral {SSP0, SSP1}
 
 impl SSPPeripheral {
-  fn reg(self) -> &reg::SSP {
+  fn reg(self) -> &'static reg::SSP {
     match self {
       SSP0 => &reg::SSP0,
       SSP1 => &reg::SSP1,
@@ -98,6 +122,13 @@ impl SSPPeripheral {
       SSP1 => SSP1Clock,
     }
   }
+
+  fn dma_requests(self) -> (u8, u8) {
+    match self {
+      SSP0 => (gpdma::request::SSP0_TX, gpdma::request::SSP0_RX),
+      SSP1 => (gpdma::request::SSP1_TX, gpdma::request::SSP1_RX),
+    }
+  }
 }
 
 impl SSP {
@@ -198,8 +229,102 @@ impl SSP {
 
     (val & 0b10000) == 0
   }
+
+  fn data_address(&self) -> u32 {
+    self.reg as *const reg::SSP as u32 + DROffset
+  }
+
+  /// Drops the received data and the overrun flag.
+  fn flush_rx(&self) {
+    while self.readable() {
+      self.reg.DR();
+    }
+    self.reg.set_ICR(ICRRORIC);
+  }
+
+  /// Writes all of `data` using the DMA channel, the received data is dropped.
+  ///
+  /// Blocks until the last frame is sent.
+  pub fn write_dma(&self, channel: &gpdma::Channel, data: &[u8]) {
+    let (tx_request, _) = self.peripheral.dma_requests();
+    let mut offset = 0u;
+
+    self.reg.set_DMACR(DMACRTxEnable);
+    while offset < data.len() {
+      let count = min(data.len() - offset, gpdma::MAX_TRANSFER as uint);
+      channel.start(&dma::Transfer {
+        direction: dma::Direction::MemoryToPeripheral,
+        source: data.as_ptr() as u32 + offset as u32,
+        destination: self.data_address(),
+        count: count as u32,
+        width: dma::Width::Byte,
+        request: tx_request,
+      }, None);
+      channel.wait();
+      offset += count;
+    }
+    wait_for!(self.written());
+    self.reg.set_DMACR(0);
+
+    self.flush_rx();
+  }
+
+  /// Writes `wdata` and reads the same number of frames into `rdata` using a
+  /// DMA channel for each direction.
+  ///
+  /// Blocks until the last frame is received.
+  pub fn transfer_dma(&self, tx: &gpdma::Channel, rx: &gpdma::Channel,
+      wdata: &[u8], rdata: &mut [u8]) {
+    if wdata.len() != rdata.len() {
+      unsafe { abort() };
+    }
+
+    let (tx_request, rx_request) = self.peripheral.dma_requests();
+    let mut offset = 0u;
+
+    self.flush_rx();
+    self.reg.set_DMACR(DMACRRxEnable | DMACRTxEnable);
+    while offset < wdata.len() {
+      let count = min(wdata.len() - offset, gpdma::MAX_TRANSFER as uint);
+      // receive channel goes first, so that no frame is missed
+      rx.start(&dma::Transfer {
+        direction: dma::Direction::PeripheralToMemory,
+        source: self.data_address(),
+        destination: rdata.as_ptr() as u32 + offset as u32,
+        count: count as u32,
+        width: dma::Width::Byte,
+        request: rx_request,
+      }, None);
+      tx.start(&dma::Transfer {
+        direction: dma::Direction::MemoryToPeripheral,
+        source: wdata.as_ptr() as u32 + offset as u32,
+        destination: self.data_address(),
+        count: count as u32,
+        width: dma::Width::Byte,
+        request: tx_request,
+      }, None);
+      rx.wait();
+      offset += count;
+    }
+    self.reg.set_DMACR(0);
+  }
+}
+
+fn min(a: uint, b: uint) -> uint {
+  if a < b { a } else { b }
 }
 
+#[allow(non_upper_case_globals)]
+static DROffset: u32 = 0x08;
+
+#[allow(non_upper_case_globals)]
+static ICRRORIC: u32 = 1 << 0;
+
+#[allow(non_upper_case_globals)]
+static DMACRRxEnable: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static DMACRTxEnable: u32 = 1 << 1;
+
 impl spi::Spi for SSP {
   fn write(&self, value: u8) {
     loop { if self.writeable() {
@@ -224,17 +349,17 @@ impl spi::Spi for SSP {
 mod reg {
   use util::volatile_cell::VolatileCell;
 
-  ioreg_old!(SSP: u32, CR0, CR1, DR, SR, CPSR, IMSC, RIS, MIS, ICR, DMACR)
-  reg_rw!(SSP, u32, CR0,   set_CR0,   CR0)
-  reg_rw!(SSP, u32, CR1,   set_CR1,   CR1)
-  reg_rw!(SSP, u32, DR,    set_DR,    DR)
-  reg_r!( SSP, u32, SR,               SR)
-  reg_rw!(SSP, u32, CPSR,  set_CPSR,  CPSR)
-  reg_rw!(SSP, u32, IMSC,  set_IMSC,  IMSC)
-  reg_rw!(SSP, u32, RIS,   set_RIS,   RIS)
-  reg_rw!(SSP, u32, MIS,   set_MIS,   MIS)
-  reg_rw!(SSP, u32, ICR,   set_ICR,   ICR)
-  reg_rw!(SSP, u32, DMACR, set_DMACR, DMACR)
+  ioreg_old!(SSP: u32, CR0, CR1, DR, SR, CPSR, IMSC, RIS, MIS, ICR, DMACR);
+  reg_rw!(SSP, u32, CR0,   set_CR0,   CR0);
+  reg_rw!(SSP, u32, CR1,   set_CR1,   CR1);
+  reg_rw!(SSP, u32, DR,    set_DR,    DR);
+  reg_r!( SSP, u32, SR,               SR);
+  reg_rw!(SSP, u32, CPSR,  set_CPSR,  CPSR);
+  reg_rw!(SSP, u32, IMSC,  set_IMSC,  IMSC);
+  reg_rw!(SSP, u32, RIS,   set_RIS,   RIS);
+  reg_rw!(SSP, u32, MIS,   set_MIS,   MIS);
+  reg_rw!(SSP, u32, ICR,   set_ICR,   ICR);
+  reg_rw!(SSP, u32, DMACR, set_DMACR, DMACR);
 
   extern {
     #[link_name="lpc17xx_iomem_SSP0"] pub static SSP0: SSP;
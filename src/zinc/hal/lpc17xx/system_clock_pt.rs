@@ -26,7 +26,8 @@ pub fn attach(_: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
 
 fn build_clock(builder: &mut Builder, cx: &mut ExtCtxt,
     node: Rc<node::Node>) {
-  if !node.expect_attributes(cx, &[("source", node::StrAttribute)]) {
+  if !node.expect_attributes(cx, &[("source", node::StrAttribute)]) ||
+     !node.expect_unit(cx, "source_frequency", node::Frequency) {
     return;
   }
 
@@ -183,6 +184,15 @@ mod test {
     }}");
   }
 
+  #[test]
+  fn fails_to_parse_clock_with_bad_unit() {
+    fails_to_build("lpc17xx@mcu { clock {
+      source = \"main-oscillator\";
+      source_frequency = 12ms;
+      pll { m = 50; n = 3; divisor = 4; }
+    }}");
+  }
+
   #[test]
   fn fails_to_parse_no_pll_clock() {
     fails_to_build("lpc17xx@mcu { clock {
@@ -17,8 +17,22 @@
 Timer configuration.
 
 This code supports all four primary timers of the MCU.
+
+Each timer has four match channels used for alarms and two capture channels
+(CAPn.0 and CAPn.1, the pins must be configured separately). Interrupts are
+raised through `isr_timer_0` to `isr_timer_3`.
+
+Capture flags only latch with the capture interrupt enabled, so it is enabled
+for polled captures as well. The NVIC line is left to the caller: if the ISR
+runs, `handle_interrupt` keeps the polled captures for `capture()`.
 */
 
+use core::intrinsics::abort;
+use core::iter::range;
+use core::option::Option;
+use core::option::Option::{Some, None};
+
+use hal::cortex_m3::irq::NoInterrupts;
 use hal::timer;
 
 use self::TimerPeripheral::*;
@@ -49,9 +63,19 @@ pub struct TimerConf {
 /// Struct describing a timer instance.
 #[derive(Copy)]
 pub struct Timer {
+  index: u8,
   reg: &'static reg::TIMER,
 }
 
+/// Alarm periods, zero for one-shot alarms.
+static mut ALARM_PERIODS: [[u32; 4]; 4] = [[0; 4]; 4];
+static mut ALARM_CALLBACKS: [[Option<timer::AlarmCallback>; 4]; 4] =
+    [[None; 4]; 4];
+static mut CAPTURE_CALLBACKS: [[Option<timer::CaptureCallback>; 2]; 4] =
+    [[None; 2]; 4];
+/// Polled captures taken by the ISR.
+static mut CAPTURES: [[Option<u32>; 2]; 4] = [[None; 2]; 4];
+
 impl Timer {
   /// Create an start a timer.
   pub fn new(peripheral: TimerPeripheral, counter: u32, divisor: u8) -> Timer {
@@ -72,9 +96,78 @@ impl Timer {
     reg.set_TCR(1);
 
     Timer {
+      index: peripheral as u8,
       reg: reg,
     }
   }
+
+  /// Re-arms periodic alarms and calls the callbacks of fired alarms and
+  /// captures. Must be called from the timer ISR.
+  pub fn handle_interrupt(&self) {
+    let ir = self.reg.IR();
+    let index = self.index as uint;
+
+    for channel in range(0u, 4) {
+      let bit = 1u32 << channel;
+      if ir & bit == 0 {
+        continue;
+      }
+      match unsafe { ALARM_CALLBACKS[index][channel] } {
+        Some(callback) => {
+          self.reg.set_IR(bit);
+          let period = unsafe { ALARM_PERIODS[index][channel] };
+          if period == 0 {
+            timer::AlarmTimer::cancel_alarm(self, channel as u8);
+          } else {
+            self.set_match(channel, self.match_value(channel) + period);
+          }
+          callback(channel as u8);
+        },
+        None => (),
+      }
+    }
+
+    for channel in range(0u, 2) {
+      let bit = IRCapture0 << channel;
+      if ir & bit == 0 {
+        continue;
+      }
+      self.reg.set_IR(bit);
+      let value = self.capture_value(channel);
+      match unsafe { CAPTURE_CALLBACKS[index][channel] } {
+        Some(callback) => callback(channel as u8, value),
+        None => unsafe { CAPTURES[index][channel] = Some(value) },
+      }
+    }
+  }
+
+  fn match_value(&self, channel: uint) -> u32 {
+    match channel {
+      0 => self.reg.MR0(),
+      1 => self.reg.MR1(),
+      2 => self.reg.MR2(),
+      3 => self.reg.MR3(),
+      _ => unsafe { abort() },
+    }
+  }
+
+  fn set_match(&self, channel: uint, value: u32) {
+    match channel {
+      0 => self.reg.set_MR0(value),
+      1 => self.reg.set_MR1(value),
+      2 => self.reg.set_MR2(value),
+      3 => self.reg.set_MR3(value),
+      _ => unsafe { abort() },
+    }
+  }
+
+  fn capture_value(&self, channel: uint) -> u32 {
+    match channel {
+      0 => self.reg.CR0(),
+      1 => self.reg.CR1(),
+      _ => unsafe { abort() },
+    }
+  }
 }
 
 impl timer::Timer for Timer {
@@ -84,6 +177,118 @@ impl timer::Timer for Timer {
   }
 }
 
+impl timer::AlarmTimer for Timer {
+  fn set_alarm(&self, channel: u8, ticks: u32, mode: timer::AlarmMode,
+      callback: timer::AlarmCallback) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    let index = self.index as uint;
+    let period = match mode {
+      timer::AlarmMode::OneShot  => 0,
+      timer::AlarmMode::Periodic => ticks,
+    };
+    unsafe {
+      ALARM_PERIODS[index][ch] = period;
+      ALARM_CALLBACKS[index][ch] = Some(callback);
+    }
+
+    self.set_match(ch, self.reg.TC() + ticks);
+    self.reg.set_IR(1 << ch);
+    let mcr: u32 = self.reg.MCR() & !(MCRMask << (ch * 3));
+    self.reg.set_MCR(mcr | (MCRInterrupt << (ch * 3)));
+  }
+
+  fn cancel_alarm(&self, channel: u8) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    let mcr: u32 = self.reg.MCR();
+    self.reg.set_MCR(mcr & !(MCRMask << (ch * 3)));
+    unsafe {
+      ALARM_CALLBACKS[self.index as uint][ch] = None;
+    }
+  }
+}
+
+impl timer::CaptureTimer for Timer {
+  fn enable_capture(&self, channel: u8, edge: timer::CaptureEdge,
+      callback: Option<timer::CaptureCallback>) {
+    let ch = channel as uint;
+    if ch > 1 {
+      unsafe { abort() }
+    }
+    unsafe {
+      CAPTURE_CALLBACKS[self.index as uint][ch] = callback;
+    }
+
+    let bits = CCRInterrupt | match edge {
+      timer::CaptureEdge::Rising  => CCRRising,
+      timer::CaptureEdge::Falling => CCRFalling,
+      timer::CaptureEdge::Both    => CCRRising | CCRFalling,
+    };
+    self.reg.set_IR(IRCapture0 << ch);
+    unsafe {
+      CAPTURES[self.index as uint][ch] = None;
+    }
+    let ccr: u32 = self.reg.CCR() & !(CCRMask << (ch * 3));
+    self.reg.set_CCR(ccr | (bits << (ch * 3)));
+  }
+
+  fn disable_capture(&self, channel: u8) {
+    let ch = channel as uint;
+    if ch > 1 {
+      unsafe { abort() }
+    }
+    let ccr: u32 = self.reg.CCR();
+    self.reg.set_CCR(ccr & !(CCRMask << (ch * 3)));
+    unsafe {
+      CAPTURE_CALLBACKS[self.index as uint][ch] = None;
+    }
+  }
+
+  fn capture(&self, channel: u8) -> Option<u32> {
+    let ch = channel as uint;
+    if ch > 1 {
+      unsafe { abort() }
+    }
+    // the ISR may take the capture meanwhile
+    let _crit = NoInterrupts::new();
+    let bit = IRCapture0 << ch;
+    if self.reg.IR() & bit != 0 {
+      self.reg.set_IR(bit);
+      unsafe {
+        CAPTURES[self.index as uint][ch] = None;
+      }
+      return Some(self.capture_value(ch));
+    }
+    unsafe {
+      let value = CAPTURES[self.index as uint][ch];
+      CAPTURES[self.index as uint][ch] = None;
+      value
+    }
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static IRCapture0: u32 = 0b1_0000;
+
+#[allow(non_upper_case_globals)]
+static MCRInterrupt: u32 = 0b001;
+#[allow(non_upper_case_globals)]
+static MCRMask: u32 = 0b111;
+
+#[allow(non_upper_case_globals)]
+static CCRRising: u32 = 0b001;
+#[allow(non_upper_case_globals)]
+static CCRFalling: u32 = 0b010;
+#[allow(non_upper_case_globals)]
+static CCRInterrupt: u32 = 0b100;
+#[allow(non_upper_case_globals)]
+static CCRMask: u32 = 0b111;
+
 mod reg {
   use util::volatile_cell::VolatileCell;
 
@@ -23,6 +23,7 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(verify as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
   for timer_node in node.subnodes().iter() {
     timer_node.materializer.set(Some(build_timer as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+    timer_node.schema.set(Some(timer_schema as fn() -> node::NodeSchema));
     add_node_dependency(&node, timer_node);
     super::add_node_dependency_on_clock(builder, timer_node);
   }
@@ -32,13 +33,19 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
-fn build_timer(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
-  if !node.expect_attributes(cx, &[
-      ("counter", node::IntAttribute),
-      ("divisor", node::IntAttribute)]) {
-    return
+fn timer_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("counter", node::IntAttribute,
+          node::Required, node::InRange(1, 0xffff_ffff)),
+      node::AttributeSchema::new("divisor", node::IntAttribute,
+          node::Required, node::OneOfInt(vec!(1, 2, 4, 8))),
+    ),
+    subnodes: Some(vec!()),
   }
+}
 
+fn build_timer(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   if node.name.is_none() {
     cx.parse_sess().span_diagnostic.span_err(node.name_span,
         "timer node must have a name");
@@ -74,7 +81,7 @@ fn build_timer(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
 mod test {
   use std::ops::Deref;
   use builder::Builder;
-  use test_helpers::{assert_equal_source, with_parsed};
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
 
   #[test]
   fn builds_timer() {
@@ -95,4 +102,39 @@ mod test {
               zinc::hal::lpc17xx::timer::TimerPeripheral::Timer1, 25u32, 4u8);");
     });
   }
+
+  #[test]
+  fn fails_to_build_timer_with_bad_attributes() {
+    fails_to_build("lpc17xx@mcu {
+      clock {
+        source = \"main-oscillator\";
+        source_frequency = 12_000_000;
+        pll { m = 50; n = 3; divisor = 4; }
+      }
+      timer {
+        tim@1 {
+          counter = \"25\";
+          divisor = 16;
+          unknown = 1;
+        }
+      }
+    }");
+  }
+
+  #[test]
+  fn fails_to_build_timer_with_unsupported_divisor() {
+    fails_to_build("lpc17xx@mcu {
+      clock {
+        source = \"main-oscillator\";
+        source_frequency = 12_000_000;
+        pll { m = 50; n = 3; divisor = 4; }
+      }
+      timer {
+        tim@1 {
+          counter = 25;
+          divisor = 3;
+        }
+      }
+    }");
+  }
 }
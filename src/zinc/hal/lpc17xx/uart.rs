@@ -18,16 +18,27 @@ UART configuration.
 
 This code doesn't support UART1, while it really should (UART1 has more features
 than other UARTs in MCU).
+
+Bulk transfers can be offloaded to the DMA controller with `write_dma()` and
+`read_dma()`.
 */
 
 use core::intrinsics::abort;
 use core::kinds::Copy;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+use core::slice::SliceExt;
 
 use hal::lpc17xx::peripheral_clock::PeripheralClock;
 use hal::lpc17xx::peripheral_clock::PeripheralClock::UART0Clock;
 use hal::lpc17xx::peripheral_clock::PeripheralClock::UART2Clock;
 use hal::lpc17xx::peripheral_clock::PeripheralClock::UART3Clock;
-use drivers::chario::CharIO;
+use drivers::chario::{CharIO, CharInput, InputError};
+use hal::dma;
+use hal::dma::Channel;
+use hal::lpc17xx::dma as gpdma;
 use hal::uart;
 
 use self::UARTPeripheral::*;
@@ -112,7 +123,6 @@ enum FIFOEnabled {
   FEDisabled = 0b0,
 }
 
-#[allow(dead_code)]
 enum FIFODmaMode {
   FDEnabled  = 0b1_0_0_0,
   FDDisabled = 0b0_0_0_0,
@@ -129,8 +139,11 @@ enum FIFOTriggerLevel {
 /// Structure describing a UART instance.
 #[derive(Copy)]
 pub struct UART {
+  peripheral: UARTPeripheral,
   reg: &'static reg::UART,
   clock: PeripheralClock,
+  /// FIFO enable and trigger level bits of FCR, which is write-only.
+  fcr: u8,
 }
 
 impl UARTPeripheral {
@@ -149,6 +162,14 @@ impl UARTPeripheral {
       UART3 => UART3Clock,
     }
   }
+
+  fn dma_requests(self) -> (u8, u8) {
+    match self {
+      UART0 => (gpdma::request::UART0_TX, gpdma::request::UART0_RX),
+      UART2 => (gpdma::request::UART2_TX, gpdma::request::UART2_RX),
+      UART3 => (gpdma::request::UART3_TX, gpdma::request::UART3_RX),
+    }
+  }
 }
 
 impl Copy for UARTPeripheral {}
@@ -157,9 +178,11 @@ impl UART {
   /// Create ans setup a UART.
   pub fn new(peripheral: UARTPeripheral, baudrate: u32, word_len: u8,
       parity: uart::Parity, stop_bits: u8) -> UART {
-    let uart = UART {
+    let mut uart = UART {
+      peripheral: peripheral,
       reg: peripheral.reg(),
       clock: peripheral.peripheral_clock(),
+      fcr: 0,
     };
 
     uart.clock.enable();
@@ -171,6 +194,64 @@ impl UART {
     uart
   }
 
+  /// Enables the receive data available interrupt.
+  ///
+  /// The interrupt handler is expected to be bound via the platform tree
+  /// `on_rx` attribute.
+  pub fn enable_rx_interrupt(&self) {
+    let ier: u8 = self.reg.IER() as u8;
+    self.reg.set_IER((ier | IERRxDataAvailable) as u32);
+  }
+
+  /// Writes all of `data` using the DMA channel.
+  ///
+  /// Blocks until the last byte is in the transmit FIFO.
+  pub fn write_dma(&self, channel: &gpdma::Channel, data: &[u8]) {
+    let (tx_request, _) = self.peripheral.dma_requests();
+    self.transfer_dma(channel, dma::Direction::MemoryToPeripheral, tx_request,
+        data.as_ptr() as u32, data.len());
+  }
+
+  /// Fills `data` with received bytes using the DMA channel.
+  ///
+  /// Blocks until the buffer is full.
+  pub fn read_dma(&self, channel: &gpdma::Channel, data: &mut [u8]) {
+    let (_, rx_request) = self.peripheral.dma_requests();
+    self.transfer_dma(channel, dma::Direction::PeripheralToMemory, rx_request,
+        data.as_ptr() as u32, data.len());
+  }
+
+  fn transfer_dma(&self, channel: &gpdma::Channel, direction: dma::Direction,
+      request: u8, buffer: u32, len: uint) {
+    let data = self.reg as *const reg::UART as u32 + RBRTHROffset;
+    let mut offset = 0u;
+
+    self.set_fifo_dma_mode(true);
+    while offset < len {
+      let count = if len - offset > gpdma::MAX_TRANSFER as uint {
+        gpdma::MAX_TRANSFER as uint
+      } else {
+        len - offset
+      };
+      let memory = buffer + offset as u32;
+      let (source, destination) = match direction {
+        dma::Direction::PeripheralToMemory => (data, memory),
+        _                                  => (memory, data),
+      };
+      channel.start(&dma::Transfer {
+        direction: direction,
+        source: source,
+        destination: destination,
+        count: count as u32,
+        width: dma::Width::Byte,
+        request: request,
+      }, None);
+      channel.wait();
+      offset += count;
+    }
+    self.set_fifo_dma_mode(false);
+  }
+
   fn uart_clock(&self) -> u32 {
     self.clock.frequency()
   }
@@ -203,17 +284,29 @@ impl UART {
     (*(self.reg)).set_LCR(new_lcr as u32);
   }
 
-  fn set_fifo_enabled(&self, enabled: bool, reset: bool) {
+  fn set_fifo_enabled(&mut self, enabled: bool, reset: bool) {
     use self::FIFOEnabled::*;
     use self::FIFODmaMode::*;
     use self::FIFOTriggerLevel::*;
-    let val: u8 = match enabled {
+    self.fcr = match enabled {
       true => FEEnabled as u8,
       false => FEDisabled as u8
-    } | match reset {
+    } | FT1char as u8;
+    let val: u8 = self.fcr | match reset {
       true  => FIFOResetTx & FIFOResetRx,
       false => 0,
-    } | FDDisabled as u8 | FT1char as u8;
+    } | FDDisabled as u8;
+
+    (*(self.reg)).set_FCR(val as u32);
+  }
+
+  /// Switches the DMA mode, keeping the rest of the FIFO configuration.
+  fn set_fifo_dma_mode(&self, enabled: bool) {
+    use self::FIFODmaMode::*;
+    let val: u8 = self.fcr | match enabled {
+      true  => FDEnabled as u8,
+      false => FDDisabled as u8,
+    };
 
     (*(self.reg)).set_FCR(val as u32);
   }
@@ -289,6 +382,62 @@ impl CharIO for UART {
   }
 }
 
+impl CharInput for UART {
+  fn readable(&self) -> bool {
+    self.reg.LSR() as u8 & LSRRxDataReady == LSRRxDataReady
+  }
+
+  fn try_getc(&self) -> Result<Option<char>, InputError> {
+    // reading LSR clears the error flags
+    let lsr = self.reg.LSR() as u8;
+    if lsr & LSRRxDataReady == 0 {
+      return Ok(None);
+    }
+
+    let value = self.reg.RBR() as u8;
+    if lsr & LSROverrun != 0 {
+      Err(InputError::Overrun)
+    } else if lsr & LSRParityError != 0 {
+      Err(InputError::Parity)
+    } else if lsr & (LSRFramingError | LSRBreak) != 0 {
+      Err(InputError::Framing)
+    } else {
+      Ok(Some(value as char))
+    }
+  }
+}
+
+impl uart::InterruptUART for UART {
+  fn set_tx_interrupt(&self, enabled: bool) {
+    let ier: u8 = self.reg.IER() as u8;
+    let new_ier = match enabled {
+      true  => ier | IERTHREmpty,
+      false => ier & !IERTHREmpty,
+    };
+    self.reg.set_IER(new_ier as u32);
+  }
+
+  fn set_rx_interrupt(&self, enabled: bool) {
+    let ier: u8 = self.reg.IER() as u8;
+    let new_ier = match enabled {
+      true  => ier | IERRxDataAvailable | IERRxLineStatus,
+      false => ier & !(IERRxDataAvailable | IERRxLineStatus),
+    };
+    self.reg.set_IER(new_ier as u32);
+  }
+
+  fn writable(&self) -> bool {
+    self.reg.LSR() as u8 & LSRTHREmpty == LSRTHREmpty
+  }
+
+  fn write_char(&self, value: u8) {
+    self.reg.set_THR(value as u32);
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static RBRTHROffset: u32 = 0x00;
+
 #[allow(non_upper_case_globals)]
 static FIFOResetRx: u8 = 0b1_0;
 #[allow(non_upper_case_globals)]
@@ -297,9 +446,26 @@ static FIFOResetTx: u8 = 0b1_0_0;
 #[allow(non_upper_case_globals)]
 static LCRModeMask: u8 = 0b1_11_1_1_11;
 
+#[allow(non_upper_case_globals)]
+static LSRRxDataReady: u8 = 0x01;
+#[allow(non_upper_case_globals)]
+static LSROverrun: u8 = 0x02;
+#[allow(non_upper_case_globals)]
+static LSRParityError: u8 = 0x04;
+#[allow(non_upper_case_globals)]
+static LSRFramingError: u8 = 0x08;
+#[allow(non_upper_case_globals)]
+static LSRBreak: u8 = 0x10;
 #[allow(non_upper_case_globals)]
 static LSRTHREmpty: u8 = 0x20;
 
+#[allow(non_upper_case_globals)]
+static IERRxDataAvailable: u8 = 0b1;
+#[allow(non_upper_case_globals)]
+static IERTHREmpty: u8 = 0b10;
+#[allow(non_upper_case_globals)]
+static IERRxLineStatus: u8 = 0b100;
+
 mod reg {
   use util::volatile_cell::VolatileCell;
 
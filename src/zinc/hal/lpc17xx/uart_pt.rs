@@ -25,15 +25,19 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
 
   for sub in node.subnodes().iter() {
     add_node_dependency(&node, sub);
-    let tx_node_name = sub.get_ref_attr("tx").unwrap();
-    let rx_node_name = sub.get_ref_attr("rx").unwrap();
-    let tx_node = builder.pt().get_by_name(tx_node_name.as_slice()).unwrap();
-    let rx_node = builder.pt().get_by_name(rx_node_name.as_slice()).unwrap();
-    add_node_dependency(sub, &tx_node);
-    add_node_dependency(sub, &rx_node);
+    // missing pins are reported by the schema validation
+    match pin_node(builder, sub, "tx") {
+      Some(tx_node) => add_node_dependency(sub, &tx_node),
+      None => (),
+    }
+    match pin_node(builder, sub, "rx") {
+      Some(rx_node) => add_node_dependency(sub, &rx_node),
+      None => (),
+    }
     super::add_node_dependency_on_clock(builder, sub);
 
     sub.materializer.set(Some(build_uart as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+    sub.schema.set(Some(uart_schema as fn() -> node::NodeSchema));
     sub.mutator.set(Some(mutate_pins as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
   }
 }
@@ -42,14 +46,46 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
-pub fn mutate_pins(builder: &mut Builder, _: &mut ExtCtxt, sub: Rc<node::Node>) {
-  let tx_node_name = sub.get_ref_attr("tx").unwrap();
-  let rx_node_name = sub.get_ref_attr("rx").unwrap();
+fn uart_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("baud_rate", node::IntAttribute,
+          node::Required, node::Unrestricted).with_unit(node::BaudRate),
+      node::AttributeSchema::new("mode", node::StrAttribute,
+          node::Required, node::Unrestricted),
+      node::AttributeSchema::new("tx", node::RefAttribute,
+          node::Required, node::Unrestricted),
+      node::AttributeSchema::new("rx", node::RefAttribute,
+          node::Required, node::Unrestricted),
+      node::AttributeSchema::new("on_rx", node::IdentAttribute,
+          node::Optional, node::Unrestricted),
+      node::AttributeSchema::new("irq_priority", node::IntAttribute,
+          node::Optional, node::InRange(0, 31)),
+    ),
+    subnodes: Some(vec!()),
+  }
+}
 
-  build_uart_gpio(builder, sub.path.as_slice().parse().unwrap(),
-      tx_node_name.as_slice(), true);
-  build_uart_gpio(builder, sub.path.as_slice().parse().unwrap(),
-      rx_node_name.as_slice(), false);
+/// Returns the pin node referenced by the `attr` attribute of the UART node, if
+/// there is one.
+fn pin_node(builder: &Builder, sub: &Rc<node::Node>, attr: &str)
+    -> Option<Rc<node::Node>> {
+  sub.get_ref_attr(attr).and_then(|name| {
+    builder.pt().get_by_name(name.as_slice())
+  })
+}
+
+pub fn mutate_pins(builder: &mut Builder, _: &mut ExtCtxt, sub: Rc<node::Node>) {
+  // mutators run before validation, which reports the missing pins
+  let uart_idx = sub.path.as_slice().parse().unwrap();
+  match pin_node(builder, &sub, "tx") {
+    Some(tx_node) => build_uart_gpio(tx_node, uart_idx, true),
+    None => (),
+  }
+  match pin_node(builder, &sub, "rx") {
+    Some(rx_node) => build_uart_gpio(rx_node, uart_idx, false),
+    None => (),
+  }
 }
 
 pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
@@ -65,6 +101,11 @@ pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
         }
       });
   let uart_peripheral = TokenString(uart_peripheral_str);
+  let irqn: uint = match sub.path.as_slice() {
+    "0" => 5,
+    "2" => 7,
+    _   => 8,
+  };
 
   if sub.name.is_none() {
     cx.parse_sess().span_diagnostic.span_err(sub.name_span,
@@ -72,14 +113,6 @@ pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
     return
   }
 
-  if !sub.expect_attributes(cx, &[
-      ("baud_rate", node::IntAttribute),
-      ("mode", node::StrAttribute),
-      ("tx", node::RefAttribute),
-      ("rx", node::RefAttribute)]) {
-    return
-  }
-
   let baud_rate: u32 = sub.get_int_attr("baud_rate").unwrap() as u32;
   let mode = sub.get_string_attr("mode").unwrap();
 
@@ -107,11 +140,23 @@ pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
           $stop_bits)
   );
   builder.add_main_statement(st);
+
+  match sub.get_ident_attr("on_rx") {
+    Some(handler) => {
+      // LPC17xx implements only the upper 5 bits of priority
+      let priority = sub.get_int_attr("irq_priority").map(|p| (p << 3) as u8);
+      builder.add_main_statement(quote_stmt!(&*cx,
+          $uart_name.enable_rx_interrupt();
+      ));
+      builder.bind_interrupt(cx, "zinc::hal::cortex_m3::nvic",
+          format!("isr_uart_{}", sub.path).as_slice(), irqn, priority,
+          handler.as_slice(), sub.get_attr("on_rx").value_span);
+    },
+    None => (),
+  }
 }
 
-pub fn build_uart_gpio(builder: &Builder, uart_idx: uint, name: &str,
-    istx: bool) {
-  let node = builder.pt().get_by_name(name).unwrap();
+pub fn build_uart_gpio(node: Rc<node::Node>, uart_idx: uint, istx: bool) {
   let direction = (if istx {"out"} else {"in"}).to_string();
   let function = format!("{}{}", if istx {"txd"} else {"rxd"}, uart_idx);
   node.attributes.borrow_mut().insert("direction".to_string(),
@@ -170,4 +215,58 @@ mod test {
       assert!(rx_node.get_string_attr("function").unwrap() == "rxd0".to_string());
     });
   }
+
+  #[test]
+  fn builds_uart_with_rx_handler() {
+    with_parsed("
+      uart {
+        uart@2 {
+          baud_rate = 9600;
+          mode = \"8N1\";
+          tx = &uart_tx;
+          rx = &uart_rx;
+          on_rx = handle_rx;
+          irq_priority = 2;
+        }
+      }
+      gpio {
+        uart_tx@10;
+        uart_rx@11;
+      }
+      ", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_uart(&mut builder, cx, pt.get_by_name("uart").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 4);
+
+      assert_equal_source(builder.main_stmts()[1].deref(),
+          "uart.enable_rx_interrupt();");
+      assert_equal_source(builder.main_stmts()[2].deref(),
+          "zinc::hal::cortex_m3::nvic::set_priority(7u, 16u8);");
+      assert_equal_source(builder.main_stmts()[3].deref(),
+          "zinc::hal::cortex_m3::nvic::enable_irq(7u);");
+    });
+  }
+
+  #[test]
+  fn fails_to_build_uart_without_pins() {
+    with_parsed("
+      lpc17xx@mcu {
+        clock {
+          source = \"main-oscillator\";
+          source_frequency = 12_000_000;
+          pll { m = 50; n = 3; divisor = 4; }
+        }
+        uart {
+          uart@0 {
+            baud_rate = 9600;
+            mode = \"8N1\";
+          }
+        }
+      }
+      ", |cx, failed, pt| {
+      assert!(Builder::build(cx, pt).is_none());
+      assert!(unsafe{*failed} == true);
+    });
+  }
 }
@@ -0,0 +1,165 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, TokenString};
+use node;
+
+pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(build_watchdog as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+  node.schema.set(Some(watchdog_schema as fn() -> node::NodeSchema));
+  super::add_node_dependency_on_clock(builder, &node);
+}
+
+fn watchdog_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("mode", node::StrAttribute,
+          node::Required, node::OneOf(vec!("disabled", "reset", "interrupt"))),
+      node::AttributeSchema::new("timeout", node::IntAttribute,
+          node::Optional, node::InRange(0xff, 0xffff_ffff))
+          .with_unit(node::Time),
+      node::AttributeSchema::new("on_timeout", node::IdentAttribute,
+          node::Optional, node::Unrestricted),
+      node::AttributeSchema::new("irq_priority", node::IntAttribute,
+          node::Optional, node::InRange(0, 31)),
+    ),
+    subnodes: Some(vec!()),
+  }
+}
+
+fn build_watchdog(builder: &mut Builder, cx: &mut ExtCtxt,
+    node: Rc<node::Node>) {
+  let mode = node.get_string_attr("mode").unwrap();
+  let action = match mode.as_slice() {
+    "disabled"  => return,
+    "reset"     => "Reset",
+    _           => "Interrupt",
+  };
+
+  let timeout = match node.get_required_int_attr(cx, "timeout") {
+    Some(timeout) => timeout as u32,
+    None => return,
+  };
+
+  match (action, node.get_ident_attr("on_timeout")) {
+    ("Interrupt", Some(handler)) => {
+      // LPC17xx implements only the upper 5 bits of priority
+      let priority = node.get_int_attr("irq_priority").map(|p| (p << 3) as u8);
+      builder.bind_interrupt(cx, "zinc::hal::cortex_m3::nvic", "isr_wdt", 0,
+          priority, handler.as_slice(), node.get_attr("on_timeout").value_span);
+    },
+    ("Interrupt", None) => {
+      cx.parse_sess().span_diagnostic.span_err(node.name_span,
+          "required attribute `on_timeout` is missing for interrupt mode");
+      return;
+    },
+    (_, Some(_)) => {
+      cx.parse_sess().span_diagnostic.span_err(
+          node.get_attr("on_timeout").key_span,
+          "attribute `on_timeout` is only used in interrupt mode");
+      return;
+    },
+    (_, None) => (),
+  }
+
+  let action_ident = TokenString(action.to_string());
+  builder.add_main_statement(quote_stmt!(&*cx,
+      zinc::hal::lpc17xx::wdt::init(
+          $timeout,
+          zinc::hal::lpc17xx::wdt::Action::$action_ident);
+  ));
+  builder.add_refresh_statement(quote_stmt!(&*cx,
+      zinc::hal::lpc17xx::wdt::feed();
+  ));
+}
+
+#[cfg(test)]
+mod test {
+  use std::ops::Deref;
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed};
+
+  #[test]
+  fn builds_reset_watchdog() {
+    with_parsed("
+      watchdog {
+        mode = \"reset\";
+        timeout = 500ms;
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_watchdog(&mut builder, cx, pt.get_by_path("watchdog").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+      assert!(builder.has_refresh_statements());
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "zinc::hal::lpc17xx::wdt::init(
+              500000u32,
+              zinc::hal::lpc17xx::wdt::Action::Reset);");
+    });
+  }
+
+  #[test]
+  fn builds_interrupt_watchdog() {
+    with_parsed("
+      watchdog {
+        mode = \"interrupt\";
+        timeout = 1s;
+        on_timeout = handle_timeout;
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_watchdog(&mut builder, cx, pt.get_by_path("watchdog").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 2);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "zinc::hal::cortex_m3::nvic::enable_irq(0u);");
+      assert_equal_source(builder.main_stmts()[1].deref(),
+          "zinc::hal::lpc17xx::wdt::init(
+              1000000u32,
+              zinc::hal::lpc17xx::wdt::Action::Interrupt);");
+    });
+  }
+
+  #[test]
+  fn builds_disabled_watchdog() {
+    with_parsed("
+      watchdog {
+        mode = \"disabled\";
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_watchdog(&mut builder, cx, pt.get_by_path("watchdog").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 0);
+      assert!(!builder.has_refresh_statements());
+    });
+  }
+
+  #[test]
+  fn fails_to_build_interrupt_watchdog_without_handler() {
+    with_parsed("
+      watchdog {
+        mode = \"interrupt\";
+        timeout = 1s;
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_watchdog(&mut builder, cx, pt.get_by_path("watchdog").unwrap());
+      assert!(unsafe{*failed} == true);
+    });
+  }
+}
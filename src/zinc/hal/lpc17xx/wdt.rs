@@ -0,0 +1,135 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Watchdog timer.
+
+The watchdog is clocked from the internal RC oscillator, so its timeout doesn't
+depend on the system clock configuration. Once started, the watchdog can only be
+stopped by a reset.
+*/
+
+use hal::cortex_m3::irq::NoInterrupts;
+use hal::watchdog;
+
+use self::Action::*;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Action taken when the watchdog times out.
+#[derive(Copy)]
+pub enum Action {
+  /// Reset the MCU.
+  Reset,
+  /// Raise the watchdog interrupt.
+  Interrupt,
+}
+
+/// The shortest timeout supported by the hardware, in microseconds.
+pub static MIN_TIMEOUT_US: u32 = 0xff;
+
+static WDMOD_WDEN:    u32 = 1 << 0;
+static WDMOD_WDRESET: u32 = 1 << 1;
+static WDMOD_WDTOF:   u32 = 1 << 2;
+
+static WDCLKSEL_IRC:  u32 = 0;
+
+/// Configures and starts the watchdog.
+///
+/// The counter is clocked at IRC / 4 = 1MHz, so timeout is given in
+/// microseconds. Timeouts shorter than MIN_TIMEOUT_US are extended to it.
+pub fn init(timeout_us: u32, action: Action) {
+  set_timeout(timeout_us);
+  reg::WDT.set_WDMOD(match action {
+    Reset     => WDMOD_WDEN | WDMOD_WDRESET,
+    Interrupt => WDMOD_WDEN,
+  });
+
+  // watchdog starts running after the first feed
+  feed();
+}
+
+fn set_timeout(timeout_us: u32) {
+  let timeout = if timeout_us < MIN_TIMEOUT_US {
+    MIN_TIMEOUT_US
+  } else {
+    timeout_us
+  };
+
+  reg::WDT.set_WDCLKSEL(WDCLKSEL_IRC);
+  reg::WDT.set_WDTC(timeout);
+}
+
+/// Feeds the watchdog, restarting the timeout.
+pub fn feed() {
+  // an access to any other watchdog register in between the two writes aborts
+  // the feed sequence, so it must not be interrupted.
+  let _crit = NoInterrupts::new();
+  reg::WDT.set_WDFEED(0xaa);
+  reg::WDT.set_WDFEED(0x55);
+}
+
+/// Returns true if the last reset was caused by the watchdog timeout and clears
+/// the flag.
+pub fn timed_out() -> bool {
+  let wdmod = reg::WDT.WDMOD();
+  if wdmod & WDMOD_WDTOF != 0 {
+    reg::WDT.set_WDMOD(wdmod & !WDMOD_WDTOF);
+    true
+  } else {
+    false
+  }
+}
+
+/// The watchdog in reset mode, as a `hal::watchdog::Watchdog`.
+///
+/// `caused_reset` clears the timeout flag, so it only returns true once after
+/// a watchdog reset.
+#[derive(Copy)]
+pub struct Watchdog;
+
+impl watchdog::Watchdog for Watchdog {
+  fn set_timeout(&self, timeout_us: u32) {
+    set_timeout(timeout_us);
+  }
+
+  fn start(&self) {
+    reg::WDT.set_WDMOD(WDMOD_WDEN | WDMOD_WDRESET);
+    feed();
+  }
+
+  fn feed(&self) {
+    feed();
+  }
+
+  fn caused_reset(&self) -> bool {
+    timed_out()
+  }
+}
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(WDT: u32, WDMOD, WDTC, WDFEED, WDTV, WDCLKSEL);
+  reg_rw!(WDT, u32, WDMOD, set_WDMOD, WDMOD);
+  reg_rw!(WDT, u32, WDTC, set_WDTC, WDTC);
+  reg_w!(WDT, u32, set_WDFEED, WDFEED);
+  reg_r!(WDT, u32, WDTV, WDTV);
+  reg_rw!(WDT, u32, WDCLKSEL, set_WDCLKSEL, WDCLKSEL);
+
+  extern {
+    #[link_name="lpc17xx_iomem_WDT"] pub static WDT: WDT;
+  }
+}
@@ -19,6 +19,11 @@ HAL provides abstractions for specific MCU hardware.
 Each peripheral in `hal` has a `xxxConf` struct that can be defined statically,
 and each such struct has a `setup()` method that configures the hardware
 (returning the object to interact with it where applicable).
+
+Drivers only enable the interrupt sources of their own peripheral and never
+touch the NVIC, as interrupt lines are often shared. The caller enables the IRQ
+with the `nvic` module of the MCU core; the platform tree does it for the
+interrupts it binds handlers to.
 */
 
 
@@ -32,9 +37,17 @@ mod cortex_common;
 pub mod cortex_m3;
 pub mod cortex_m4;
 
+pub mod adc;
+pub mod can;
+pub mod dma;
+pub mod flash;
+pub mod i2c;
 pub mod mem_init;
 pub mod pin;
+pub mod pwm;
+pub mod rtc;
 pub mod spi;
 pub mod stack;
 pub mod timer;
 pub mod uart;
+pub mod watchdog;
@@ -51,3 +51,41 @@ pub trait Gpio {
   /// for reading or writing respectively.
   fn set_direction(&self, new_mode: GpioDirection);
 }
+
+/// Condition raising a GPIO interrupt.
+#[derive(PartialEq, Copy)]
+pub enum GpioTrigger {
+  /// Low to high transition.
+  RisingEdge,
+  /// High to low transition.
+  FallingEdge,
+  /// Any transition.
+  BothEdges,
+  /// Logic low, for as long as it lasts.
+  LowLevel,
+  /// Logic high, for as long as it lasts.
+  HighLevel,
+}
+
+/// Pin able to raise an interrupt.
+///
+/// The interrupt handler is defined by the user and is expected to check
+/// `interrupt_pending()` on the pins sharing it and clear the ones it served.
+/// Triggers the hardware doesn't support make the MCU abort.
+///
+/// As everywhere in the HAL, implementations don't touch the NVIC: the caller
+/// must enable the IRQ of the pin's interrupt line.
+pub trait GpioInterrupt {
+  /// Configures the trigger and enables the interrupt of the pin.
+  fn enable_interrupt(&self, trigger: GpioTrigger);
+
+  /// Disables the interrupt of the pin, the NVIC line stays enabled.
+  fn disable_interrupt(&self);
+
+  /// Returns true if the trigger condition has been met since the interrupt
+  /// was last cleared.
+  fn interrupt_pending(&self) -> bool;
+
+  /// Clears the pending interrupt.
+  fn clear_interrupt(&self);
+}
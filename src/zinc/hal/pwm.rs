@@ -0,0 +1,75 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+PWM interface.
+
+PWMConf is a MCU-specific struct.
+
+A PWM peripheral drives several channels that share a common period, each of
+them having its own pulse width and polarity. Channels are numbered as in the
+MCU reference manual. Period and pulse width changes are latched at the end of
+the current period, so the outputs never glitch.
+
+Output pins must be configured for the PWM function separately.
+*/
+
+/// Output polarity of a PWM channel.
+#[derive(Copy)]
+pub enum Polarity {
+  /// The output is high during the pulse and low for the rest of the period.
+  ActiveHigh,
+  /// The output is low during the pulse and high for the rest of the period.
+  ActiveLow,
+}
+
+/// PWM trait.
+pub trait PWM {
+  /// Sets the period shared by all channels, in microseconds.
+  ///
+  /// Pulse widths are kept, so a servo keeps its position.
+  fn set_period_us(&self, period_us: u32);
+
+  /// Returns the current period, in microseconds.
+  fn period_us(&self) -> u32;
+
+  /// Sets the pulse width of the channel, in microseconds.
+  ///
+  /// The width is kept as given and clamped to the period when it is applied,
+  /// so it comes back in effect if the period grows again.
+  fn set_pulse_width_us(&self, channel: u8, width_us: u32);
+
+  /// Returns the pulse width in effect on the channel, in microseconds, that
+  /// is clamped to the period.
+  fn pulse_width_us(&self, channel: u8) -> u32;
+
+  /// Sets the output polarity of the channel.
+  fn set_polarity(&self, channel: u8, polarity: Polarity);
+
+  /// Enables the output of the channel.
+  fn enable(&self, channel: u8);
+
+  /// Disables the output of the channel.
+  fn disable(&self, channel: u8);
+
+  /// Sets the duty cycle of the channel, in 1/1000 of the period.
+  fn set_duty_permille(&self, channel: u8, permille: u16) {
+    let period = self.period_us();
+    let permille = if permille > 1000 { 1000 } else { permille as u32 };
+    // avoid overflowing on long periods
+    let width = period / 1000 * permille + period % 1000 * permille / 1000;
+    self.set_pulse_width_us(channel, width);
+  }
+}
@@ -0,0 +1,199 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Real-time clock interface.
+
+The RTC keeps calendar time, usually from a 32.768 kHz crystal in a battery
+backed domain along with a few backup registers, so that both survive resets
+and brownouts.
+*/
+
+use core::iter::range;
+
+/// Seconds in a day.
+const SECS_PER_DAY: u32 = 86400;
+
+/// Days before each month in a non-leap year.
+static DAYS_BEFORE_MONTH: [u16; 12] =
+    [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+/// Calendar date and time.
+#[derive(Copy, PartialEq)]
+pub struct DateTime {
+  /// Full year, e.g. 2015.
+  pub year: u16,
+  /// Month, 1 to 12.
+  pub month: u8,
+  /// Day of the month, 1 to 31.
+  pub day: u8,
+  /// Hour, 0 to 23.
+  pub hour: u8,
+  /// Minute, 0 to 59.
+  pub minute: u8,
+  /// Second, 0 to 59.
+  pub second: u8,
+}
+
+fn is_leap_year(year: u16) -> bool {
+  (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_year(year: u16) -> u32 {
+  if is_leap_year(year) { 366 } else { 365 }
+}
+
+impl DateTime {
+  /// Converts seconds since 1970-01-01 00:00:00 UTC.
+  pub fn from_unix(timestamp: u32) -> DateTime {
+    let mut days = timestamp / SECS_PER_DAY;
+    let secs = timestamp % SECS_PER_DAY;
+
+    let mut year = 1970u16;
+    while days >= days_in_year(year) {
+      days -= days_in_year(year);
+      year += 1;
+    }
+
+    let mut month = 12u8;
+    loop {
+      let mut before = DAYS_BEFORE_MONTH[month as uint - 1] as u32;
+      if month > 2 && is_leap_year(year) {
+        before += 1;
+      }
+      if days >= before {
+        days -= before;
+        break;
+      }
+      month -= 1;
+    }
+
+    DateTime {
+      year: year,
+      month: month,
+      day: days as u8 + 1,
+      hour: (secs / 3600) as u8,
+      minute: (secs / 60 % 60) as u8,
+      second: (secs % 60) as u8,
+    }
+  }
+
+  /// Converts to seconds since 1970-01-01 00:00:00 UTC.
+  pub fn to_unix(&self) -> u32 {
+    let mut days = 0u32;
+    for year in range(1970u16, self.year) {
+      days += days_in_year(year);
+    }
+    days += self.day_of_year() as u32 - 1;
+
+    days * SECS_PER_DAY + self.hour as u32 * 3600 + self.minute as u32 * 60 +
+        self.second as u32
+  }
+
+  /// Returns the day of the year, 1 to 366.
+  pub fn day_of_year(&self) -> u16 {
+    let mut day = DAYS_BEFORE_MONTH[self.month as uint - 1] + self.day as u16;
+    if self.month > 2 && is_leap_year(self.year) {
+      day += 1;
+    }
+    day
+  }
+
+  /// Returns the day of the week, 0 for Sunday to 6 for Saturday.
+  pub fn day_of_week(&self) -> u8 {
+    // 1970-01-01 was a Thursday
+    ((self.to_unix() / SECS_PER_DAY + 4) % 7) as u8
+  }
+}
+
+/// Real-time clock.
+///
+/// The alarm raises the RTC interrupt once the clock reaches its date and
+/// time. Some RTCs compare only the day of the month, not the month and year,
+/// see the MCU implementation. The alarm only enables the interrupt in the RTC,
+/// the caller must enable its IRQ in the NVIC.
+pub trait RTC {
+  /// Sets the current date and time.
+  fn set_time(&self, time: &DateTime);
+
+  /// Returns the current date and time.
+  fn time(&self) -> DateTime;
+
+  /// Arms the alarm to fire at `time`.
+  fn set_alarm(&self, time: &DateTime);
+
+  /// Disarms the alarm.
+  fn disable_alarm(&self);
+
+  /// Returns true if the alarm has fired since it was last cleared.
+  fn alarm_pending(&self) -> bool;
+
+  /// Clears the alarm flag.
+  fn clear_alarm(&self);
+
+  /// Returns the number of 32-bit backup registers.
+  fn backup_registers(&self) -> uint;
+
+  /// Reads a backup register.
+  fn read_backup(&self, index: uint) -> u32;
+
+  /// Writes a backup register.
+  fn write_backup(&self, index: uint, value: u32);
+}
+
+#[cfg(test)]
+mod test {
+  use hal::rtc::DateTime;
+
+  fn date(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8)
+      -> DateTime {
+    DateTime {
+      year: year,
+      month: month,
+      day: day,
+      hour: hour,
+      minute: minute,
+      second: second,
+    }
+  }
+
+  #[test]
+  fn converts_the_epoch() {
+    assert!(DateTime::from_unix(0) == date(1970, 1, 1, 0, 0, 0));
+    assert!(date(1970, 1, 1, 0, 0, 0).to_unix() == 0);
+  }
+
+  #[test]
+  fn converts_unix_timestamps() {
+    let t = date(2015, 3, 14, 9, 26, 53);
+    assert!(t.to_unix() == 1426325213);
+    assert!(DateTime::from_unix(1426325213) == t);
+  }
+
+  #[test]
+  fn handles_leap_days() {
+    let leap_day = date(2016, 2, 29, 23, 59, 59);
+    assert!(leap_day.to_unix() == 1456790399);
+    assert!(DateTime::from_unix(1456790400) == date(2016, 3, 1, 0, 0, 0));
+    assert!(date(2016, 12, 31, 0, 0, 0).day_of_year() == 366);
+    assert!(date(2100, 3, 1, 0, 0, 0).day_of_year() == 60);
+  }
+
+  #[test]
+  fn computes_the_day_of_week() {
+    assert!(date(1970, 1, 1, 0, 0, 0).day_of_week() == 4);
+    assert!(date(2015, 3, 14, 0, 0, 0).day_of_week() == 6);
+  }
+}
@@ -0,0 +1,99 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! External interrupt/event controller (EXTI) for ST STM32F4.
+//!
+//! Line N can be routed to pin N of any single port. Lines 0 to 4 have their
+//! own interrupt, lines 5 to 9 and 10 to 15 share one each. EXTI only triggers
+//! on edges.
+
+use core::intrinsics::abort;
+
+use hal::pin::GpioTrigger;
+use hal::stm32f4::peripheral_clock::PeripheralClock::SYSCFGClock;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+
+/// Routes `line` to the port with the given index (0 for port A) and enables
+/// its interrupt.
+pub fn enable(port: u8, line: u8, trigger: GpioTrigger) {
+  let bit: u32 = 1 << (line as uint);
+  let (rising, falling) = match trigger {
+    GpioTrigger::RisingEdge  => (true, false),
+    GpioTrigger::FallingEdge => (false, true),
+    GpioTrigger::BothEdges   => (true, true),
+    _                        => unsafe { abort() },
+  };
+
+  SYSCFGClock.enable();
+  let exticr = match line / 4 {
+    0 => &reg::SYSCFG_EXTICR1,
+    1 => &reg::SYSCFG_EXTICR2,
+    2 => &reg::SYSCFG_EXTICR3,
+    3 => &reg::SYSCFG_EXTICR4,
+    _ => unsafe { abort() },
+  };
+  let offset = (line as uint % 4) * 4;
+  let val: u32 = exticr.value() & !(0xf << offset);
+  exticr.set_value(val | ((port as u32) << offset));
+
+  let rtsr: u32 = reg::EXTI.RTSR() & !bit;
+  reg::EXTI.set_RTSR(if rising { rtsr | bit } else { rtsr });
+  let ftsr: u32 = reg::EXTI.FTSR() & !bit;
+  reg::EXTI.set_FTSR(if falling { ftsr | bit } else { ftsr });
+
+  clear(line);
+  reg::EXTI.set_IMR(reg::EXTI.IMR() | bit);
+}
+
+/// Masks the interrupt of `line`.
+pub fn disable(line: u8) {
+  reg::EXTI.set_IMR(reg::EXTI.IMR() & !(1 << (line as uint)));
+}
+
+/// Returns true if the trigger of `line` has fired.
+pub fn is_pending(line: u8) -> bool {
+  reg::EXTI.PR() & (1 << (line as uint)) != 0
+}
+
+/// Clears the pending flag of `line`.
+pub fn clear(line: u8) {
+  // flags are cleared by writing ones
+  reg::EXTI.set_PR(1 << (line as uint));
+}
+
+#[allow(dead_code)]
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(EXTI: u32, IMR, EMR, RTSR, FTSR, SWIER, PR);
+  reg_rw!(EXTI, u32, IMR,   set_IMR,   IMR);
+  reg_rw!(EXTI, u32, EMR,   set_EMR,   EMR);
+  reg_rw!(EXTI, u32, RTSR,  set_RTSR,  RTSR);
+  reg_rw!(EXTI, u32, FTSR,  set_FTSR,  FTSR);
+  reg_rw!(EXTI, u32, SWIER, set_SWIER, SWIER);
+  reg_rw!(EXTI, u32, PR,    set_PR,    PR);
+
+  ioreg_old!(EXTICR: u32, value);
+  reg_rw!(EXTICR, u32, value, set_value, value);
+
+  extern {
+    #[link_name="stm32f4_iomem_EXTI"] pub static EXTI: EXTI;
+    #[link_name="stm32f4_iomem_SYSCFG_EXTICR1"] pub static SYSCFG_EXTICR1: EXTICR;
+    #[link_name="stm32f4_iomem_SYSCFG_EXTICR2"] pub static SYSCFG_EXTICR2: EXTICR;
+    #[link_name="stm32f4_iomem_SYSCFG_EXTICR3"] pub static SYSCFG_EXTICR3: EXTICR;
+    #[link_name="stm32f4_iomem_SYSCFG_EXTICR4"] pub static SYSCFG_EXTICR4: EXTICR;
+  }
+}
@@ -0,0 +1,116 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Independent watchdog (IWDG) for ST STM32F4.
+//!
+//! The IWDG is clocked from the ~32 kHz LSI oscillator, which is started along
+//! with the watchdog, so timeouts are approximate. Once started, it can only
+//! be stopped by a reset.
+
+use core::iter::range;
+
+use hal::stm32f4::init::reg::RCC;
+use hal::watchdog;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+#[path="../../util/wait_for.rs"] mod wait_for;
+
+/// LSI frequency in Hz.
+const LSI_FREQUENCY: u64 = 32_000;
+
+/// Largest reload value.
+const MAX_RELOAD: u64 = 0xfff;
+
+/// The independent watchdog.
+///
+/// `caused_reset` clears all the reset flags in RCC_CSR, so it only returns
+/// true once after a watchdog reset.
+///
+/// Before the watchdog is started only the first `set_timeout` takes effect,
+/// as the update of the registers is pending until LSI runs.
+#[derive(Copy)]
+pub struct IWDG;
+
+impl watchdog::Watchdog for IWDG {
+  fn set_timeout(&self, timeout_us: u32) {
+    let ticks = timeout_us as u64 * LSI_FREQUENCY / 1_000_000;
+
+    // The prescaler divides LSI by 4 << prescaler, up to 256.
+    let mut prescaler = 6;
+    let mut reload = MAX_RELOAD;
+    for shift in range(0u, 7) {
+      if ticks / (4 << shift) <= MAX_RELOAD {
+        prescaler = shift;
+        reload = ticks / (4 << shift);
+        break;
+      }
+    }
+    if reload == 0 {
+      reload = 1;
+    }
+
+    // a previous update only completes while LSI runs, which it doesn't
+    // before the watchdog is started
+    if RCC.CSR() & CSR_LSIRDY != 0 {
+      wait_for!(reg::IWDG.SR() & (SR_PVU | SR_RVU) == 0);
+    }
+    reg::IWDG.set_KR(KEY_UNLOCK);
+    reg::IWDG.set_PR(prescaler as u32);
+    reg::IWDG.set_RLR(reload as u32);
+    reg::IWDG.set_KR(KEY_RELOAD);
+  }
+
+  fn start(&self) {
+    reg::IWDG.set_KR(KEY_START);
+  }
+
+  fn feed(&self) {
+    reg::IWDG.set_KR(KEY_RELOAD);
+  }
+
+  fn caused_reset(&self) -> bool {
+    let csr = RCC.CSR();
+    let caused = csr & CSR_IWDGRSTF != 0;
+    if caused {
+      RCC.set_CSR(csr | CSR_RMVF);
+    }
+    caused
+  }
+}
+
+const KEY_UNLOCK: u32 = 0x5555;
+const KEY_RELOAD: u32 = 0xAAAA;
+const KEY_START:  u32 = 0xCCCC;
+
+const SR_PVU: u32 = 1 << 0;
+const SR_RVU: u32 = 1 << 1;
+
+const CSR_LSIRDY:   u32 = 1 << 1;
+const CSR_RMVF:     u32 = 1 << 24;
+const CSR_IWDGRSTF: u32 = 1 << 29;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+
+  ioreg_old!(IWDG: u32, KR, PR, RLR, SR);
+  reg_w!(IWDG, u32,           set_KR,  KR);
+  reg_rw!(IWDG, u32, PR,  set_PR,  PR);
+  reg_rw!(IWDG, u32, RLR, set_RLR, RLR);
+  reg_r!(IWDG, u32, SR,           SR);
+
+  extern {
+    #[link_name="stm32f4_iomem_IWDG"] pub static IWDG: IWDG;
+  }
+}
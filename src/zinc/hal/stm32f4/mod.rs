@@ -16,7 +16,10 @@
 //! HAL for STM32F4.
 
 pub mod init;
+pub mod exti;
 pub mod gpio;
+pub mod iwdg;
 pub mod peripheral_clock;
 pub mod pin;
+pub mod pwm;
 pub mod timer;
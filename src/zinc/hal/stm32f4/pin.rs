@@ -17,7 +17,10 @@
 //!
 //! Some pins that could be configured here may be missing from actual MCU
 //! depending on the package.
+//!
+//! Pin interrupts are provided by the EXTI, see `exti`.
 
+use super::exti;
 use super::peripheral_clock;
 use core::intrinsics::abort;
 
@@ -141,6 +144,24 @@ impl PinConf {
   }
 }
 
+impl ::hal::pin::GpioInterrupt for PinConf {
+  fn enable_interrupt(&self, trigger: ::hal::pin::GpioTrigger) {
+    exti::enable(self.port as u8, self.pin, trigger);
+  }
+
+  fn disable_interrupt(&self) {
+    exti::disable(self.pin);
+  }
+
+  fn interrupt_pending(&self) -> bool {
+    exti::is_pending(self.pin)
+  }
+
+  fn clear_interrupt(&self) {
+    exti::clear(self.pin);
+  }
+}
+
 #[allow(dead_code)]
 mod reg {
   use util::volatile_cell::VolatileCell;
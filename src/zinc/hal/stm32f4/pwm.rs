@@ -0,0 +1,185 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timer based PWM for ST STM32F4.
+//!
+//! Supports the four channels of general purpose timers TIM2 to TIM5. The
+//! counter ticks every microsecond, the auto-reload register holds the period
+//! and the capture/compare registers the pulse widths. All of them are
+//! preloaded and transferred on the update event at the end of the period.
+//!
+//! TIM3 and TIM4 are 16 bit, so their period is limited to 65535us.
+
+use core::cell::Cell;
+use core::intrinsics::abort;
+use core::iter::range;
+
+use super::init;
+use super::peripheral_clock::PeripheralClock;
+use super::timer::reg;
+use hal::pwm;
+
+/// Available PWM timer peripherals.
+#[allow(missing_docs)]
+#[derive(Copy)]
+pub enum PwmPeripheral {
+  Timer2,
+  Timer3,
+  Timer4,
+  Timer5,
+}
+
+/// Structure describing a timer PWM.
+pub struct Pwm {
+  reg: &'static reg::TIM2To5,
+  wide: bool,
+  widths: Cell<[u32; 4]>,
+}
+
+/// CCMR output compare mode: PWM mode 1 with preload.
+static CCMR_PWM1_PRELOAD: u32 = 0b110_1000;
+/// CR1 counter enable.
+static CR1_CEN: u32 = 1 << 0;
+/// CR1 auto-reload preload enable.
+static CR1_ARPE: u32 = 1 << 7;
+/// EGR update generation.
+static EGR_UG: u32 = 1 << 0;
+
+impl Pwm {
+  /// Create and start a timer PWM with all outputs disabled.
+  pub fn new(peripheral: PwmPeripheral, period_us: u32) -> Pwm {
+    use self::PwmPeripheral::*;
+    let (clock, reg, wide) = match peripheral {
+      Timer2 => (PeripheralClock::TIM2Clock, &reg::TIM2, true),
+      Timer3 => (PeripheralClock::TIM3Clock, &reg::TIM3, false),
+      Timer4 => (PeripheralClock::TIM4Clock, &reg::TIM4, false),
+      Timer5 => (PeripheralClock::TIM5Clock, &reg::TIM5, true),
+    };
+
+    clock.enable();
+
+    let pwm = Pwm {
+      reg: reg,
+      wide: wide,
+      widths: Cell::new([0; 4]),
+    };
+
+    // APB1 timers run at twice the bus clock when the bus is divided.
+    let apb = init::apb_low_clock();
+    let timer_clock = if apb == init::system_clock() { apb } else { apb * 2 };
+
+    reg.set_CR1(0);
+    reg.set_CCER(0);
+    reg.set_PSC(timer_clock / 1_000_000 - 1);
+    reg.set_CCMR1(CCMR_PWM1_PRELOAD | (CCMR_PWM1_PRELOAD << 8));
+    reg.set_CCMR2(CCMR_PWM1_PRELOAD | (CCMR_PWM1_PRELOAD << 8));
+    pwm.set_auto_reload(period_us);
+    for channel in range(1u8, 5) {
+      pwm.update_compare(channel);
+    }
+    reg.set_EGR(EGR_UG);
+    reg.set_CR1(CR1_ARPE | CR1_CEN);
+
+    pwm
+  }
+
+  fn check_channel(channel: u8) {
+    if channel < 1 || channel > 4 {
+      unsafe { abort() }
+    }
+  }
+
+  fn set_auto_reload(&self, period_us: u32) {
+    if period_us == 0 || (!self.wide && period_us > 0xffff) {
+      unsafe { abort() }
+    }
+    self.reg.set_ARR(period_us - 1);
+  }
+
+  /// Writes the width of the channel, clamped to the period. A compare value
+  /// equal to the period keeps the output active.
+  fn update_compare(&self, channel: u8) {
+    let period = self.reg.ARR() + 1;
+    let width = self.widths.get()[channel as uint - 1];
+    self.set_compare(channel, if width > period { period } else { width });
+  }
+
+  fn set_compare(&self, channel: u8, value: u32) {
+    match channel {
+      1 => self.reg.set_CCR1(value),
+      2 => self.reg.set_CCR2(value),
+      3 => self.reg.set_CCR3(value),
+      _ => self.reg.set_CCR4(value),
+    }
+  }
+
+  fn compare(&self, channel: u8) -> u32 {
+    match channel {
+      1 => self.reg.CCR1(),
+      2 => self.reg.CCR2(),
+      3 => self.reg.CCR3(),
+      _ => self.reg.CCR4(),
+    }
+  }
+
+  fn set_ccer_bit(&self, bit: uint, value: bool) {
+    let ccer = self.reg.CCER() & !(1 << bit);
+    self.reg.set_CCER(ccer | if value { 1 << bit } else { 0 });
+  }
+}
+
+impl pwm::PWM for Pwm {
+  fn set_period_us(&self, period_us: u32) {
+    self.set_auto_reload(period_us);
+    for channel in range(1u8, 5) {
+      self.update_compare(channel);
+    }
+  }
+
+  fn period_us(&self) -> u32 {
+    self.reg.ARR() + 1
+  }
+
+  fn set_pulse_width_us(&self, channel: u8, width_us: u32) {
+    Pwm::check_channel(channel);
+    let mut widths = self.widths.get();
+    widths[channel as uint - 1] = width_us;
+    self.widths.set(widths);
+    self.update_compare(channel);
+  }
+
+  fn pulse_width_us(&self, channel: u8) -> u32 {
+    Pwm::check_channel(channel);
+    self.compare(channel)
+  }
+
+  fn set_polarity(&self, channel: u8, polarity: pwm::Polarity) {
+    Pwm::check_channel(channel);
+    self.set_ccer_bit((channel as uint - 1) * 4 + 1, match polarity {
+      pwm::Polarity::ActiveHigh => false,
+      pwm::Polarity::ActiveLow  => true,
+    });
+  }
+
+  fn enable(&self, channel: u8) {
+    Pwm::check_channel(channel);
+    self.set_ccer_bit((channel as uint - 1) * 4, true);
+  }
+
+  fn disable(&self, channel: u8) {
+    Pwm::check_channel(channel);
+    self.set_ccer_bit((channel as uint - 1) * 4, false);
+  }
+}
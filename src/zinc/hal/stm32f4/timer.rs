@@ -16,6 +16,15 @@
 //! Timer configuration for ST STM32F4.
 //!
 //! This code supports only TIM2 at the moment.
+//!
+//! Each of the four capture/compare channels (0 for CH1) can be used either
+//! for an alarm or for input capture, in which case its pin must be configured
+//! separately. Interrupts are raised through the TIM2 ISR.
+
+use core::intrinsics::abort;
+use core::iter::range;
+use core::option::Option;
+use core::option::Option::{Some, None};
 
 use super::peripheral_clock;
 use hal::timer;
@@ -35,6 +44,11 @@ pub struct Timer {
   reg: &'static reg::TIM2To5,
 }
 
+/// Alarm periods, zero for one-shot alarms.
+static mut ALARM_PERIODS: [u32; 4] = [0; 4];
+static mut ALARM_CALLBACKS: [Option<timer::AlarmCallback>; 4] = [None; 4];
+static mut CAPTURE_CALLBACKS: [Option<timer::CaptureCallback>; 4] = [None; 4];
+
 impl Timer {
   /// Create and start a Timer.
   pub fn new(peripheral: TimerPeripheral, counter: u32) -> Timer {
@@ -53,6 +67,79 @@ impl Timer {
       reg: reg,
     }
   }
+
+  /// Re-arms periodic alarms and calls the callbacks of fired alarms and
+  /// captures. Must be called from the timer ISR.
+  pub fn handle_interrupt(&self) {
+    let sr = self.reg.SR();
+
+    for channel in range(0u, 4) {
+      let bit = SRCC1IF << channel;
+      if sr & bit == 0 {
+        continue;
+      }
+      match unsafe { (ALARM_CALLBACKS[channel], CAPTURE_CALLBACKS[channel]) } {
+        (Some(callback), _) => {
+          self.clear_flag(bit);
+          let period = unsafe { ALARM_PERIODS[channel] };
+          if period == 0 {
+            timer::AlarmTimer::cancel_alarm(self, channel as u8);
+          } else {
+            self.set_compare(channel, self.compare(channel) + period);
+          }
+          callback(channel as u8);
+        },
+        (None, Some(callback)) => {
+          self.clear_flag(bit);
+          callback(channel as u8, self.compare(channel));
+        },
+        (None, None) => (),
+      }
+    }
+  }
+
+  fn compare(&self, channel: uint) -> u32 {
+    match channel {
+      0 => self.reg.CCR1(),
+      1 => self.reg.CCR2(),
+      2 => self.reg.CCR3(),
+      3 => self.reg.CCR4(),
+      _ => unsafe { abort() },
+    }
+  }
+
+  fn set_compare(&self, channel: uint, value: u32) {
+    match channel {
+      0 => self.reg.set_CCR1(value),
+      1 => self.reg.set_CCR2(value),
+      2 => self.reg.set_CCR3(value),
+      3 => self.reg.set_CCR4(value),
+      _ => unsafe { abort() },
+    }
+  }
+
+  /// Sets the CCxS/OCxM/ICxF byte of the channel in CCMR1 or CCMR2.
+  fn set_channel_mode(&self, channel: uint, mode: u32) {
+    let offset = (channel % 2) * 8;
+    if channel < 2 {
+      let val: u32 = self.reg.CCMR1() & !(0xff << offset);
+      self.reg.set_CCMR1(val | (mode << offset));
+    } else {
+      let val: u32 = self.reg.CCMR2() & !(0xff << offset);
+      self.reg.set_CCMR2(val | (mode << offset));
+    }
+  }
+
+  fn set_interrupt(&self, channel: uint, enabled: bool) {
+    let bit = DIERCC1IE << channel;
+    let dier: u32 = self.reg.DIER() & !bit;
+    self.reg.set_DIER(if enabled { dier | bit } else { dier });
+  }
+
+  fn clear_flag(&self, bit: u32) {
+    // flags are cleared by writing zeroes, ones are ignored
+    self.reg.set_SR(!bit);
+  }
 }
 
 impl timer::Timer for Timer {
@@ -62,7 +149,110 @@ impl timer::Timer for Timer {
   }
 }
 
-mod reg {
+impl timer::AlarmTimer for Timer {
+  fn set_alarm(&self, channel: u8, ticks: u32, mode: timer::AlarmMode,
+      callback: timer::AlarmCallback) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    unsafe {
+      ALARM_PERIODS[ch] = match mode {
+        timer::AlarmMode::OneShot  => 0,
+        timer::AlarmMode::Periodic => ticks,
+      };
+      ALARM_CALLBACKS[ch] = Some(callback);
+      CAPTURE_CALLBACKS[ch] = None;
+    }
+
+    self.set_channel_mode(ch, CCMROutputFrozen);
+    self.set_compare(ch, self.reg.CNT() + ticks);
+    self.clear_flag(SRCC1IF << ch);
+    self.set_interrupt(ch, true);
+  }
+
+  fn cancel_alarm(&self, channel: u8) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    self.set_interrupt(ch, false);
+    unsafe {
+      ALARM_CALLBACKS[ch] = None;
+    }
+  }
+}
+
+impl timer::CaptureTimer for Timer {
+  fn enable_capture(&self, channel: u8, edge: timer::CaptureEdge,
+      callback: Option<timer::CaptureCallback>) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    unsafe {
+      ALARM_CALLBACKS[ch] = None;
+      CAPTURE_CALLBACKS[ch] = callback;
+    }
+
+    let bits = match edge {
+      timer::CaptureEdge::Rising  => CCERCC1E,
+      timer::CaptureEdge::Falling => CCERCC1E | CCERCC1P,
+      timer::CaptureEdge::Both    => CCERCC1E | CCERCC1P | CCERCC1NP,
+    };
+    let ccer: u32 = self.reg.CCER() & !(CCERMask << (ch * 4));
+    self.reg.set_CCER(ccer);
+    self.set_channel_mode(ch, CCMRInputTI);
+    self.reg.set_CCER(ccer | (bits << (ch * 4)));
+    self.clear_flag(SRCC1IF << ch);
+    self.set_interrupt(ch, callback.is_some());
+  }
+
+  fn disable_capture(&self, channel: u8) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    self.set_interrupt(ch, false);
+    let ccer: u32 = self.reg.CCER();
+    self.reg.set_CCER(ccer & !(CCERMask << (ch * 4)));
+    unsafe {
+      CAPTURE_CALLBACKS[ch] = None;
+    }
+  }
+
+  fn capture(&self, channel: u8) -> Option<u32> {
+    let ch = channel as uint;
+    let bit = SRCC1IF << ch;
+    if self.reg.SR() & bit == 0 {
+      return None;
+    }
+    self.clear_flag(bit);
+    Some(self.compare(ch))
+  }
+}
+
+#[allow(non_upper_case_globals)]
+static SRCC1IF: u32 = 0b10;
+#[allow(non_upper_case_globals)]
+static DIERCC1IE: u32 = 0b10;
+
+#[allow(non_upper_case_globals)]
+static CCMROutputFrozen: u32 = 0b00;
+#[allow(non_upper_case_globals)]
+static CCMRInputTI: u32 = 0b01;
+
+#[allow(non_upper_case_globals)]
+static CCERCC1E: u32 = 0b0001;
+#[allow(non_upper_case_globals)]
+static CCERCC1P: u32 = 0b0010;
+#[allow(non_upper_case_globals)]
+static CCERCC1NP: u32 = 0b1000;
+#[allow(non_upper_case_globals)]
+static CCERMask: u32 = 0b1011;
+
+pub mod reg {
+  //! Timer registers definition
   use util::volatile_cell::VolatileCell;
 
   ioreg_old!(TIM2To5: u32, CR1, CR2, SMCR, DIER, SR, EGR, CCMR1, CCMR2, CCER, CNT,
@@ -90,5 +280,8 @@ mod reg {
 
   extern {
     #[link_name="stm32f4_iomem_TIM2"] pub static TIM2: TIM2To5;
+    #[link_name="stm32f4_iomem_TIM3"] pub static TIM3: TIM2To5;
+    #[link_name="stm32f4_iomem_TIM4"] pub static TIM4: TIM2To5;
+    #[link_name="stm32f4_iomem_TIM5"] pub static TIM5: TIM2To5;
   }
 }
@@ -0,0 +1,213 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Dzmitry "kvark" Malyshau <kvarkus@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Analog to digital converter for STM32L1.
+//!
+//! The ADC is clocked from HSI, which is turned on if it's not running yet.
+//! Pins used as ADC channels should be set up in `pin::Mode::Analog`.
+//! Continuous conversion supports up to six channels.
+
+use core::cell::Cell;
+use core::intrinsics::abort;
+use core::iter::range;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::slice::SliceExt;
+
+use hal::adc;
+use hal::stm32l1::init;
+
+#[path="../../util/wait_for.rs"] mod wait_for;
+
+/// Conversion resolution.
+#[allow(missing_docs)]
+#[repr(u8)]
+#[derive(Copy)]
+pub enum Resolution {
+  Bits12 = 0,
+  Bits10 = 1,
+  Bits8  = 2,
+  Bits6  = 3,
+}
+
+/// Structure describing the ADC instance.
+pub struct Adc {
+  reg: &'static reg::ADC,
+  resolution: Resolution,
+  callback: Cell<Option<adc::Callback>>,
+  /// Position in the regular sequence of the next continuous conversion.
+  index: Cell<u8>,
+  length: Cell<u8>,
+}
+
+impl Adc {
+  /// Create and power up the ADC.
+  pub fn new(resolution: Resolution) -> Adc {
+    use hal::stm32l1::peripheral_clock as clock;
+
+    let rcc = &init::reg::RCC;
+    if !rcc.cr.hsi_ready() {
+      rcc.cr.set_hsi_on(true);
+      wait_for!(rcc.cr.hsi_ready());
+    }
+
+    clock::PeripheralClock::Apb2(clock::BusApb2::Adc1).enable();
+
+    let reg = &reg::ADC1;
+    reg.cr1.set_resolution(resolution as u32);
+    reg.cr2.set_adc_on(true);
+    wait_for!(reg.sr.adc_on_status());
+
+    Adc {
+      reg: reg,
+      resolution: resolution,
+      callback: Cell::new(None),
+      index: Cell::new(0),
+      length: Cell::new(0),
+    }
+  }
+
+  fn check_channel(channel: u8) {
+    if channel > 25 {
+      unsafe { abort() }
+    }
+  }
+}
+
+impl adc::ADC for Adc {
+  fn resolution(&self) -> u8 {
+    match self.resolution {
+      Resolution::Bits12 => 12,
+      Resolution::Bits10 => 10,
+      Resolution::Bits8  => 8,
+      Resolution::Bits6  => 6,
+    }
+  }
+
+  fn read(&self, channel: u8) -> u16 {
+    Adc::check_channel(channel);
+    wait_for!(!self.reg.sr.regular_not_ready());
+
+    self.reg.cr1.set_scan_mode(false);
+    self.reg.cr2.set_continuous(false);
+    self.reg.sqr1.set_sequence_length(0);
+    self.reg.sqr5.set_sequence(channel as u32);
+    self.reg.cr2.set_start_regular(true);
+
+    wait_for!(self.reg.sr.end_of_conversion());
+    // reading the data register clears the EOC flag
+    self.reg.dr.data() as u16
+  }
+
+  fn start_continuous(&self, channels: &[u8], callback: adc::Callback) {
+    if channels.len() == 0 || channels.len() > 6 {
+      unsafe { abort() }
+    }
+    let mut sequence = 0u32;
+    for i in range(0, channels.len()) {
+      Adc::check_channel(channels[i]);
+      sequence |= (channels[i] as u32) << (i * 5);
+    }
+
+    self.callback.set(Some(callback));
+    self.index.set(0);
+    self.length.set(channels.len() as u8);
+
+    self.reg.sqr1.set_sequence_length(channels.len() as u32 - 1);
+    self.reg.sqr5.set_sequence(sequence);
+    self.reg.cr1.set_scan_mode(true);
+    // raise EOC after each conversion, not at the end of sequence
+    self.reg.cr2.set_eoc_selection(true);
+    self.reg.cr1.set_int_end_of_conversion_enable(true);
+    self.reg.cr2.set_continuous(true);
+    self.reg.cr2.set_start_regular(true);
+  }
+
+  fn stop_continuous(&self) {
+    self.reg.cr2.set_continuous(false);
+    self.reg.cr1.set_int_end_of_conversion_enable(false);
+    self.reg.cr2.set_eoc_selection(false);
+    self.callback.set(None);
+  }
+
+  fn process(&self) {
+    let callback = match self.callback.get() {
+      Some(callback) => callback,
+      None => return,
+    };
+
+    if self.reg.sr.overrun() {
+      // a result was lost, the sequence position is unknown, so restart it
+      self.reg.sr.set_overrun(false);
+      self.index.set(0);
+      self.reg.cr2.set_start_regular(true);
+      return;
+    }
+
+    while self.reg.sr.end_of_conversion() {
+      let index = self.index.get();
+      let channel = ((self.reg.sqr5.sequence() >> (index as uint * 5)) & 0x1f) as u8;
+      let value = self.reg.dr.data() as u16;
+      self.index.set((index + 1) % self.length.get());
+      callback(channel, value);
+    }
+  }
+}
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(ADC = {
+    0x00 => reg32 sr {  // status
+      0 => analog_watchdog : rw,
+      1 => end_of_conversion : rw,
+      2 => injected_end_of_conversion : rw,
+      3 => injected_started : rw,
+      4 => regular_started : rw,
+      5 => overrun : rw,
+      6 => adc_on_status : ro,
+      8 => regular_not_ready : ro,
+      9 => injected_not_ready : ro,
+    },
+    0x04 => reg32 cr1 { // control 1
+      4..0 => watchdog_channel : rw,
+      5 => int_end_of_conversion_enable : rw,
+      8 => scan_mode : rw,
+      25..24 => resolution : rw,
+      26 => int_overrun_enable : rw,
+    },
+    0x08 => reg32 cr2 { // control 2
+      0 => adc_on : rw,
+      1 => continuous : rw,
+      10 => eoc_selection : rw,
+      11 => align_left : rw,
+      30 => start_regular : rw,
+    },
+    0x30 => reg32 sqr1 { // regular sequence 1
+      24..20 => sequence_length : rw,
+    },
+    0x40 => reg32 sqr5 { // regular sequence 5, conversions 1 to 6
+      29..0 => sequence : rw,
+    },
+    0x58 => reg32 dr { // regular data
+      15..0 => data : ro,
+    },
+  });
+
+  extern {
+    #[link_name="stm32l1_iomem_ADC1"] pub static ADC1: ADC;
+  }
+}
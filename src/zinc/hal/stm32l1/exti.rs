@@ -0,0 +1,125 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Dzmitry "kvark" Malyshau <kvarkus@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! External interrupt/event controller (EXTI) for ST STM32L1.
+//!
+//! Line N can be routed to pin N of any single port. Lines 0 to 4 have their
+//! own interrupt, lines 5 to 9 and 10 to 15 share one each. EXTI only triggers
+//! on edges.
+
+use core::intrinsics::abort;
+
+use hal::pin::GpioTrigger;
+use hal::stm32l1::peripheral_clock::{PeripheralClock, BusApb2};
+
+/// Routes `line` to the port with the given index (0 for port A) and enables
+/// its interrupt.
+pub fn enable(port: u8, line: u8, trigger: GpioTrigger) {
+  PeripheralClock::Apb2(BusApb2::SysCfg).enable();
+  let exticr = &reg::SYSCFG.exticr[line as uint / 4];
+  let offset = (line as uint % 4) * 4;
+  let val: u32 = exticr.exti() & !(0xf << offset);
+  exticr.set_exti(val | ((port as u32) << offset));
+
+  enable_line(line, trigger);
+}
+
+/// Enables the interrupt of `line` without routing it to a pin. Lines 16 and
+/// up are connected to internal peripherals, like the RTC alarm on line 17.
+pub fn enable_line(line: u8, trigger: GpioTrigger) {
+  let bit: u32 = 1 << (line as uint);
+  let (rising, falling) = match trigger {
+    GpioTrigger::RisingEdge  => (true, false),
+    GpioTrigger::FallingEdge => (false, true),
+    GpioTrigger::BothEdges   => (true, true),
+    _                        => unsafe { abort() },
+  };
+
+  let rtsr: u32 = reg::EXTI.rtsr.tr() & !bit;
+  reg::EXTI.rtsr.set_tr(if rising { rtsr | bit } else { rtsr });
+  let ftsr: u32 = reg::EXTI.ftsr.tr() & !bit;
+  reg::EXTI.ftsr.set_tr(if falling { ftsr | bit } else { ftsr });
+
+  clear(line);
+  let imr: u32 = reg::EXTI.imr.mr();
+  reg::EXTI.imr.set_mr(imr | bit);
+}
+
+/// Masks the interrupt of `line`.
+pub fn disable(line: u8) {
+  let imr: u32 = reg::EXTI.imr.mr();
+  reg::EXTI.imr.set_mr(imr & !(1 << (line as uint)));
+}
+
+/// Returns true if the trigger of `line` has fired.
+pub fn is_pending(line: u8) -> bool {
+  reg::EXTI.pr.pr() & (1 << (line as uint)) != 0
+}
+
+/// Clears the pending flag of `line`.
+pub fn clear(line: u8) {
+  reg::EXTI.pr.clear(1 << (line as uint));
+}
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(EXTI = {
+    0x00 => reg32 imr {   // interrupt mask
+      22..0 => mr : rw,
+    },
+    0x04 => reg32 emr {   // event mask
+      22..0 => mr : rw,
+    },
+    0x08 => reg32 rtsr {  // rising trigger selection
+      22..0 => tr : rw,
+    },
+    0x0C => reg32 ftsr {  // falling trigger selection
+      22..0 => tr : rw,
+    },
+    0x10 => reg32 swier { // software interrupt event
+      22..0 => swier : rw,
+    },
+    0x14 => reg32 pr {    // pending
+      22..0 => pr : ro,
+    },
+  });
+
+  impl EXTI_pr {
+    /// Clears the flags set in `mask`. Flags are cleared by writing ones, so
+    /// a read-modify-write would clear all of them.
+    pub fn clear(&self, mask: u32) {
+      self.value.set(mask);
+    }
+  }
+
+  ioregs!(SYSCFG = {
+    0x00 => reg32 memrmp { // memory remap
+      1..0 => mem_mode : rw,
+    },
+    0x04 => reg32 pmc {    // peripheral mode configuration
+      0 => usb_pu : rw,
+    },
+    0x08 => reg32 exticr[4] { // external interrupt configuration
+      15..0 => exti : rw,
+    },
+  });
+
+  extern {
+    #[link_name="stm32l1_iomem_EXTI"]   pub static EXTI:   EXTI;
+    #[link_name="stm32l1_iomem_SYSCFG"] pub static SYSCFG: SYSCFG;
+  }
+}
@@ -0,0 +1,119 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Dzmitry "kvark" Malyshau <kvarkus@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Independent watchdog (IWDG) for ST STM32L1.
+//!
+//! The IWDG is clocked from the ~37 kHz LSI oscillator, which is started along
+//! with the watchdog, so timeouts are approximate. Once started, it can only
+//! be stopped by a reset.
+
+use core::iter::range;
+
+use hal::stm32l1::init::reg::RCC;
+use hal::watchdog;
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+#[path="../../util/wait_for.rs"] mod wait_for;
+
+/// LSI frequency in Hz.
+const LSI_FREQUENCY: u64 = 37_000;
+
+/// Largest reload value.
+const MAX_RELOAD: u64 = 0xfff;
+
+/// The independent watchdog.
+///
+/// `caused_reset` clears all the reset flags in RCC_CSR, so it only returns
+/// true once after a watchdog reset.
+///
+/// Before the watchdog is started only the first `set_timeout` takes effect,
+/// as the update of the registers is pending until LSI runs.
+#[derive(Copy)]
+pub struct IWDG;
+
+impl watchdog::Watchdog for IWDG {
+  fn set_timeout(&self, timeout_us: u32) {
+    let ticks = timeout_us as u64 * LSI_FREQUENCY / 1_000_000;
+
+    // The prescaler divides LSI by 4 << prescaler, up to 256.
+    let mut prescaler = 6;
+    let mut reload = MAX_RELOAD;
+    for shift in range(0u, 7) {
+      if ticks / (4 << shift) <= MAX_RELOAD {
+        prescaler = shift;
+        reload = ticks / (4 << shift);
+        break;
+      }
+    }
+    if reload == 0 {
+      reload = 1;
+    }
+
+    // a previous update only completes while LSI runs, which it doesn't
+    // before the watchdog is started
+    if RCC.csr.lsi_ready() {
+      wait_for!(!reg::IWDG.sr.pvu() && !reg::IWDG.sr.rvu());
+    }
+    reg::IWDG.kr.set_key(KEY_UNLOCK);
+    reg::IWDG.pr.set_pr(prescaler as u32);
+    reg::IWDG.rlr.set_rl(reload as u32);
+    reg::IWDG.kr.set_key(KEY_RELOAD);
+  }
+
+  fn start(&self) {
+    reg::IWDG.kr.set_key(KEY_START);
+  }
+
+  fn feed(&self) {
+    reg::IWDG.kr.set_key(KEY_RELOAD);
+  }
+
+  fn caused_reset(&self) -> bool {
+    let caused = RCC.csr.independent_watchdog_reset();
+    if caused {
+      RCC.csr.set_remove_reset(true);
+    }
+    caused
+  }
+}
+
+const KEY_UNLOCK: u32 = 0x5555;
+const KEY_RELOAD: u32 = 0xAAAA;
+const KEY_START:  u32 = 0xCCCC;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(IWDG = {
+    0x00 => reg32 kr {   // key
+      15..0 => key : wo,
+    },
+    0x04 => reg32 pr {   // prescaler
+      2..0 => pr : rw,
+    },
+    0x08 => reg32 rlr {  // reload
+      11..0 => rl : rw,
+    },
+    0x0C => reg32 sr {   // status
+      0 => pvu : ro,
+      1 => rvu : ro,
+    },
+  });
+
+  extern {
+    #[link_name="stm32l1_iomem_IWDG"] pub static IWDG: IWDG;
+  }
+}
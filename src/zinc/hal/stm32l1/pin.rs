@@ -17,7 +17,10 @@
 //!
 //! Some pins that could be configured here may be missing from actual MCU
 //! depending on the package.
+//!
+//! Pin interrupts are provided by the EXTI, see `exti`.
 
+use super::exti;
 use super::peripheral_clock;
 use core::intrinsics::abort;
 use self::Port::*;
@@ -109,6 +112,8 @@ pub enum Mode {
 pub struct Pin {
   /// Pin index.
   pub index: u8,
+  /// Port the pin is attached to.
+  port: Port,
   /// GPIO register
   reg: &'static reg::GPIO,
 }
@@ -182,6 +187,7 @@ impl Pin {
 
     Pin {
       index: pin_index,
+      port: port,
       reg: reg,
     }
   }
@@ -213,6 +219,35 @@ impl ::hal::pin::Gpio for Pin {
   }
 }
 
+impl ::hal::pin::GpioInterrupt for Pin {
+  fn enable_interrupt(&self, trigger: ::hal::pin::GpioTrigger) {
+    // SYSCFG_EXTICR port codes don't follow the port order past PortE
+    let port: u8 = match self.port {
+      PortA => 0,
+      PortB => 1,
+      PortC => 2,
+      PortD => 3,
+      PortE => 4,
+      PortH => 5,
+      PortF => 6,
+      PortG => 7,
+    };
+    exti::enable(port, self.index, trigger);
+  }
+
+  fn disable_interrupt(&self) {
+    exti::disable(self.index);
+  }
+
+  fn interrupt_pending(&self) -> bool {
+    exti::is_pending(self.index)
+  }
+
+  fn clear_interrupt(&self) {
+    exti::clear(self.index);
+  }
+}
+
 mod reg {
   use util::volatile_cell::VolatileCell;
   use core::ops::Drop;
@@ -0,0 +1,163 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Dzmitry "kvark" Malyshau <kvarkus@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Power management for ST STM32L1.
+//!
+//! Sleep stops the core clock only. In Stop mode all clocks are stopped and
+//! the regulator runs in low-power mode, RAM and registers are kept, and the
+//! chip wakes up running from MSI; the clock configuration passed to `sleep`
+//! is then set up again before returning. Any enabled interrupt wakes the chip
+//! from Sleep. From Stop only EXTI lines do, so wakeup sources are configured
+//! through `exti`: GPIO interrupts, or internal lines like the RTC alarm.
+//! Interrupts are disabled while sleeping, the interrupt that woke the chip
+//! is taken once the clock is restored.
+//!
+//! Standby turns off almost everything and only returns through a reset.
+//!
+//! Low-power run mode keeps the chip running from a slow MSI clock with the
+//! regulator in low-power mode.
+
+use core::intrinsics::abort;
+
+use hal::cortex_m3::irq::NoInterrupts;
+use hal::cortex_m3::scb;
+use hal::stm32l1::init::ClockConfig;
+use hal::stm32l1::init::reg::{RCC, PWR};
+use hal::stm32l1::peripheral_clock::{PeripheralClock, BusApb1};
+use util::support::wfi;
+
+use self::SleepMode::*;
+
+#[path="../../util/wait_for.rs"] mod wait_for;
+
+/// Low power modes the chip returns from.
+#[derive(Copy)]
+pub enum SleepMode {
+  /// Core clock stopped, peripherals keep running.
+  Sleep,
+  /// All clocks stopped, only EXTI lines wake the chip up.
+  Stop,
+}
+
+/// Wakeup pins that can bring the chip out of Standby.
+#[derive(Copy)]
+pub enum WakeupPin {
+  /// PA0.
+  WakeupPin1 = 1 << 8,
+  /// PC13.
+  WakeupPin2 = 1 << 9,
+  /// PE6.
+  WakeupPin3 = 1 << 10,
+}
+
+/// Puts the chip into `mode` until an interrupt wakes it up, then restores
+/// the clock with `config`.
+pub fn sleep(mode: SleepMode, config: &ClockConfig) {
+  PeripheralClock::Apb1(BusApb1::Pwr).enable();
+  let _crit = NoInterrupts::new();
+
+  match mode {
+    Sleep => wfi(),
+    Stop  => {
+      let cr: u32 = PWR.cr.control();
+      PWR.cr.set_control((cr & !PWRPowerDownDeepSleep) | PWRLowPowerRegulator |
+                         PWRUltraLowPower | PWRFastWakeup | PWRClearWakeupFlag);
+      scb::set_sleep_deep(true);
+      wfi();
+      scb::set_sleep_deep(false);
+      PWR.cr.set_control(cr);
+
+      config.setup();
+    },
+  }
+}
+
+/// Enters Standby mode. RAM and registers are lost, except for the RTC
+/// domain. The chip restarts from the reset vector on an enabled wakeup pin,
+/// an RTC alarm, the independent watchdog or the NRST pin.
+pub fn standby() -> ! {
+  PeripheralClock::Apb1(BusApb1::Pwr).enable();
+  let _crit = NoInterrupts::new();
+
+  let cr: u32 = PWR.cr.control();
+  PWR.cr.set_control(cr | PWRPowerDownDeepSleep | PWRUltraLowPower |
+                     PWRClearWakeupFlag);
+  scb::set_sleep_deep(true);
+  loop {
+    wfi();
+  }
+}
+
+/// Allows a rising edge on `pin` to wake the chip from Standby.
+pub fn set_wakeup_pin(pin: WakeupPin, enabled: bool) {
+  PeripheralClock::Apb1(BusApb1::Pwr).enable();
+  let csr: u32 = PWR.csr.status() & !(pin as u32);
+  PWR.csr.set_status(if enabled { csr | pin as u32 } else { csr });
+}
+
+/// Returns true if the chip was reset by waking up from Standby, and clears
+/// the flag.
+pub fn woke_from_standby() -> bool {
+  PeripheralClock::Apb1(BusApb1::Pwr).enable();
+  let woke = PWR.csr.status() & PWRStandbyFlag != 0;
+  if woke {
+    let cr: u32 = PWR.cr.control();
+    PWR.cr.set_control(cr | PWRClearStandbyFlag);
+  }
+  woke
+}
+
+/// Switches the regulator to low-power mode while running.
+///
+/// The system clock must be MSI at 131 kHz or lower (range 0 or 1).
+pub fn enter_low_power_run() {
+  if RCC.cfgr.system_clock_status() != 0 || RCC.icscr.msi_range() > 1 {
+    unsafe { abort() }
+  }
+
+  PeripheralClock::Apb1(BusApb1::Pwr).enable();
+  let cr: u32 = PWR.cr.control();
+  PWR.cr.set_control(cr | PWRLowPowerRegulator);
+  PWR.cr.set_control(cr | PWRLowPowerRegulator | PWRLowPowerRun);
+}
+
+/// Switches the regulator back to main mode, the clock can be raised again
+/// afterwards.
+pub fn exit_low_power_run() {
+  let cr: u32 = PWR.cr.control();
+  PWR.cr.set_control(cr & !PWRLowPowerRun);
+  wait_for!(PWR.csr.status() & PWRRegulatorLowPowerFlag == 0);
+  PWR.cr.set_control(cr & !(PWRLowPowerRun | PWRLowPowerRegulator));
+}
+
+#[allow(non_upper_case_globals)]
+static PWRLowPowerRegulator: u32 = 1 << 0;
+#[allow(non_upper_case_globals)]
+static PWRPowerDownDeepSleep: u32 = 1 << 1;
+#[allow(non_upper_case_globals)]
+static PWRClearWakeupFlag: u32 = 1 << 2;
+#[allow(non_upper_case_globals)]
+static PWRClearStandbyFlag: u32 = 1 << 3;
+#[allow(non_upper_case_globals)]
+static PWRUltraLowPower: u32 = 1 << 9;
+#[allow(non_upper_case_globals)]
+static PWRFastWakeup: u32 = 1 << 10;
+#[allow(non_upper_case_globals)]
+static PWRLowPowerRun: u32 = 1 << 14;
+
+#[allow(non_upper_case_globals)]
+static PWRStandbyFlag: u32 = 1 << 1;
+#[allow(non_upper_case_globals)]
+static PWRRegulatorLowPowerFlag: u32 = 1 << 5;
@@ -0,0 +1,308 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Dzmitry "kvark" Malyshau <kvarkus@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Real-time clock for ST STM32L1.
+//!
+//! The RTC lives in the backup domain together with 20 backup registers
+//! (32 on medium+ and high density parts, only the first 20 are exposed here),
+//! which survive resets but are cleared when the domain loses power. The
+//! calendar covers the years 2000 to 2099.
+//!
+//! The alarm (alarm A) is routed through EXTI line 17, which raises the
+//! RTC_Alarm interrupt, IRQ 41, which the caller must enable.
+
+use core::intrinsics::abort;
+
+use hal::pin::GpioTrigger;
+use hal::rtc;
+use hal::rtc::DateTime;
+use hal::stm32l1::exti;
+use hal::stm32l1::init::reg::{RCC, PWR};
+use hal::stm32l1::peripheral_clock::{PeripheralClock, BusApb1};
+
+#[path="../../util/ioreg.rs"] mod ioreg;
+#[path="../../util/wait_for.rs"] mod wait_for;
+
+/// Clock driving the RTC.
+#[derive(Copy)]
+pub enum RtcClockSource {
+  /// External 32.768 kHz crystal.
+  LSE,
+  /// Internal RC oscillator, around 37 kHz and quite inaccurate.
+  LSI,
+}
+
+/// The real-time clock.
+#[derive(Copy)]
+pub struct RTC {
+  sync_prescaler: u32,
+}
+
+/// EXTI line connected to the RTC alarm.
+const ALARM_EXTI_LINE: u8 = 17;
+
+/// Number of exposed backup registers.
+const BACKUP_REGISTERS: uint = 20;
+
+/// The calendar counts years from 2000.
+const BASE_YEAR: u16 = 2000;
+
+impl RTC {
+  /// Enables access to the backup domain and starts the RTC from `source` if
+  /// it is not running yet.
+  pub fn new(source: RtcClockSource) -> RTC {
+    PeripheralClock::Apb1(BusApb1::Pwr).enable();
+    let cr: u32 = PWR.cr.control();
+    PWR.cr.set_control(cr | PWRDisableBackupProtection);
+
+    // The prescalers divide the clock down to 1 Hz: the asynchronous one by
+    // 128, the synchronous one by the rest.
+    let (source_sel, sync_prescaler) = match source {
+      RtcClockSource::LSE => (1, 255),
+      RtcClockSource::LSI => (2, 288),
+    };
+
+    if !RCC.csr.rtc_on() {
+      match source {
+        RtcClockSource::LSE => {
+          RCC.csr.set_lse_on(true);
+          wait_for!(RCC.csr.lse_ready());
+        },
+        RtcClockSource::LSI => {
+          RCC.csr.set_lsi_on(true);
+          wait_for!(RCC.csr.lsi_ready());
+        },
+      }
+      RCC.csr.set_rtc_source(source_sel);
+      RCC.csr.set_rtc_on(true);
+    }
+
+    RTC { sync_prescaler: sync_prescaler }
+  }
+
+  fn unlock(&self) {
+    reg::RTC.wpr.set_key(0xCA);
+    reg::RTC.wpr.set_key(0x53);
+  }
+
+  fn lock(&self) {
+    reg::RTC.wpr.set_key(0xFF);
+  }
+}
+
+fn to_bcd(value: u32) -> (u32, u32) {
+  (value / 10, value % 10)
+}
+
+fn from_bcd(tens: u32, units: u32) -> u8 {
+  (tens * 10 + units) as u8
+}
+
+impl rtc::RTC for RTC {
+  fn set_time(&self, time: &DateTime) {
+    if time.year < BASE_YEAR || time.year > BASE_YEAR + 99 {
+      unsafe { abort() }
+    }
+
+    self.unlock();
+    reg::RTC.isr.set_init(true);
+    wait_for!(reg::RTC.isr.initf());
+
+    reg::RTC.prer.set_prediv_a(127).set_prediv_s(self.sync_prescaler);
+
+    let (ht, hu) = to_bcd(time.hour as u32);
+    let (mnt, mnu) = to_bcd(time.minute as u32);
+    let (st, su) = to_bcd(time.second as u32);
+    reg::RTC.tr.set_pm(false)
+        .set_ht(ht).set_hu(hu)
+        .set_mnt(mnt).set_mnu(mnu)
+        .set_st(st).set_su(su);
+
+    let (yt, yu) = to_bcd((time.year - BASE_YEAR) as u32);
+    let (mt, mu) = to_bcd(time.month as u32);
+    let (dt, du) = to_bcd(time.day as u32);
+    // Monday is 1, Sunday 7
+    let weekday = match time.day_of_week() {
+      0 => 7,
+      d => d as u32,
+    };
+    reg::RTC.dr.set_yt(yt).set_yu(yu)
+        .set_wdu(weekday)
+        .set_mt(mt != 0).set_mu(mu)
+        .set_dt(dt).set_du(du);
+
+    reg::RTC.cr.set_fmt(false);
+    reg::RTC.isr.set_init(false);
+
+    // The shadow registers are stale until the next synchronisation.
+    reg::RTC.isr.set_rsf(false);
+    wait_for!(reg::RTC.isr.rsf());
+    self.lock();
+  }
+
+  fn time(&self) -> DateTime {
+    // Reading TR locks the shadow registers until DR is read.
+    let tr = reg::RTC.tr.get();
+    let dr = reg::RTC.dr.get();
+
+    DateTime {
+      year: BASE_YEAR + from_bcd(dr.yt(), dr.yu()) as u16,
+      month: from_bcd(dr.mt() as u32, dr.mu()),
+      day: from_bcd(dr.dt(), dr.du()),
+      hour: from_bcd(tr.ht(), tr.hu()),
+      minute: from_bcd(tr.mnt(), tr.mnu()),
+      second: from_bcd(tr.st(), tr.su()),
+    }
+  }
+
+  fn set_alarm(&self, time: &DateTime) {
+    self.unlock();
+    reg::RTC.cr.set_alrae(false).set_alraie(false);
+    wait_for!(reg::RTC.isr.alrawf());
+
+    // The alarm compares the day of month, not the month or year, those are
+    // left to the caller.
+    let (dt, du) = to_bcd(time.day as u32);
+    let (ht, hu) = to_bcd(time.hour as u32);
+    let (mnt, mnu) = to_bcd(time.minute as u32);
+    let (st, su) = to_bcd(time.second as u32);
+    reg::RTC.alrmar
+        .set_msk4(false).set_wdsel(false).set_dt(dt).set_du(du)
+        .set_msk3(false).set_pm(false).set_ht(ht).set_hu(hu)
+        .set_msk2(false).set_mnt(mnt).set_mnu(mnu)
+        .set_msk1(false).set_st(st).set_su(su);
+
+    self.clear_alarm();
+    reg::RTC.cr.set_alrae(true).set_alraie(true);
+    self.lock();
+
+    exti::enable_line(ALARM_EXTI_LINE, GpioTrigger::RisingEdge);
+  }
+
+  fn disable_alarm(&self) {
+    self.unlock();
+    reg::RTC.cr.set_alrae(false).set_alraie(false);
+    self.lock();
+    exti::disable(ALARM_EXTI_LINE);
+  }
+
+  fn alarm_pending(&self) -> bool {
+    reg::RTC.isr.alraf()
+  }
+
+  fn clear_alarm(&self) {
+    reg::RTC.isr.clear_alraf();
+    exti::clear(ALARM_EXTI_LINE);
+  }
+
+  fn backup_registers(&self) -> uint {
+    BACKUP_REGISTERS
+  }
+
+  fn read_backup(&self, index: uint) -> u32 {
+    if index >= BACKUP_REGISTERS {
+      unsafe { abort() }
+    }
+    reg::RTC.bkpr[index].data()
+  }
+
+  fn write_backup(&self, index: uint, value: u32) {
+    if index >= BACKUP_REGISTERS {
+      unsafe { abort() }
+    }
+    reg::RTC.bkpr[index].set_data(value);
+  }
+}
+
+/// Disables write protection of the backup domain (DBP bit of PWR_CR).
+#[allow(non_upper_case_globals)]
+static PWRDisableBackupProtection: u32 = 1 << 8;
+
+mod reg {
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(RTC = {
+    0x00 => reg32 tr {     // time
+      22 => pm : rw,
+      21..20 => ht : rw,
+      19..16 => hu : rw,
+      14..12 => mnt : rw,
+      11..8 => mnu : rw,
+      6..4 => st : rw,
+      3..0 => su : rw,
+    },
+    0x04 => reg32 dr {     // date
+      23..20 => yt : rw,
+      19..16 => yu : rw,
+      15..13 => wdu : rw,
+      12 => mt : rw,
+      11..8 => mu : rw,
+      5..4 => dt : rw,
+      3..0 => du : rw,
+    },
+    0x08 => reg32 cr {     // control
+      6 => fmt : rw,
+      8 => alrae : rw,
+      12 => alraie : rw,
+    },
+    0x0C => reg32 isr {    // initialization and status
+      0 => alrawf : ro,
+      5 => rsf : rw,
+      6 => initf : ro,
+      7 => init : rw,
+      8 => alraf : rw,
+    },
+    0x10 => reg32 prer {   // prescaler
+      22..16 => prediv_a : rw,
+      12..0 => prediv_s : rw,
+    },
+    0x1C => reg32 alrmar { // alarm A
+      31 => msk4 : rw,
+      30 => wdsel : rw,
+      29..28 => dt : rw,
+      27..24 => du : rw,
+      23 => msk3 : rw,
+      22 => pm : rw,
+      21..20 => ht : rw,
+      19..16 => hu : rw,
+      15 => msk2 : rw,
+      14..12 => mnt : rw,
+      11..8 => mnu : rw,
+      7 => msk1 : rw,
+      6..4 => st : rw,
+      3..0 => su : rw,
+    },
+    0x24 => reg32 wpr {    // write protection
+      7..0 => key : wo,
+    },
+    0x50 => reg32 bkpr[20] { // backup
+      31..0 => data : rw,
+    },
+  });
+
+  impl RTC_isr {
+    /// Clears the alarm A flag. The flags are cleared by writing zeroes, so
+    /// the others are written as ones.
+    pub fn clear_alraf(&self) {
+      let val: u32 = self.value.get();
+      self.value.set((val | 0xff20) & !(1 << 8));
+    }
+  }
+
+  extern {
+    #[link_name="stm32l1_iomem_RTC"] pub static RTC: RTC;
+  }
+}
@@ -16,6 +16,17 @@
 //! Timer configuration for ST STM32L1.
 //!
 //! This code supports only TIM2 at the moment.
+//!
+//! Each of the four capture/compare channels (0 for CH1) can be used either
+//! for an alarm or for input capture, in which case its pin must be configured
+//! separately. Interrupts are raised through the TIM2 ISR.
+
+use core::intrinsics::abort;
+use core::iter::range;
+use core::option::Option;
+use core::option::Option::{Some, None};
+
+use hal::timer;
 
 #[path="../../util/ioreg.rs"] mod ioreg;
 
@@ -32,6 +43,14 @@ pub struct Timer {
   reg: &'static reg::TIMER,
 }
 
+/// Alarm periods, zero for one-shot alarms.
+static mut ALARM_PERIODS: [u32; 4] = [0; 4];
+static mut ALARM_CALLBACKS: [Option<timer::AlarmCallback>; 4] = [None; 4];
+static mut CAPTURE_CALLBACKS: [Option<timer::CaptureCallback>; 4] = [None; 4];
+
+/// The counter is 16 bits wide.
+const COUNTER_MASK: u32 = 0xffff;
+
 impl Timer {
   /// Create and start a Timer.
   pub fn new(peripheral: TimerPeripheral, counter: u32, div_shift: u16) -> Timer {
@@ -52,6 +71,63 @@ impl Timer {
       reg: reg,
     }
   }
+
+  /// Re-arms periodic alarms and calls the callbacks of fired alarms and
+  /// captures. Must be called from the timer ISR.
+  pub fn handle_interrupt(&self) {
+    let sr = self.reg.sr.status();
+
+    for channel in range(0u, 4) {
+      let bit = 0b10u16 << channel;
+      if sr & bit == 0 {
+        continue;
+      }
+      match unsafe { (ALARM_CALLBACKS[channel], CAPTURE_CALLBACKS[channel]) } {
+        (Some(callback), _) => {
+          self.clear_flag(bit);
+          let period = unsafe { ALARM_PERIODS[channel] };
+          if period == 0 {
+            timer::AlarmTimer::cancel_alarm(self, channel as u8);
+          } else {
+            let next = (self.compare(channel).cc() + period) & COUNTER_MASK;
+            self.compare(channel).set_cc(next);
+          }
+          callback(channel as u8);
+        },
+        (None, Some(callback)) => {
+          self.clear_flag(bit);
+          callback(channel as u8, self.compare(channel).cc());
+        },
+        (None, None) => (),
+      }
+    }
+  }
+
+  fn compare(&self, channel: uint) -> &reg::TIMER_ccr {
+    if channel > 3 {
+      unsafe { abort() }
+    }
+    &self.reg.ccr[channel]
+  }
+
+  /// Sets the CCxS/OCxM/ICxF byte of the channel in CCMR1 or CCMR2.
+  fn set_channel_mode(&self, channel: uint, mode: u32) {
+    let offset = (channel % 2) * 8;
+    let ccmr = &self.reg.ccmr[channel / 2];
+    let val: u32 = ccmr.mode() & !(0xff << offset);
+    ccmr.set_mode(val | (mode << offset));
+  }
+
+  fn set_interrupt(&self, channel: uint, enabled: bool) {
+    let bit = 0b10u16 << channel;
+    let dier: u16 = self.reg.dier.enable() & !bit;
+    self.reg.dier.set_enable(if enabled { dier | bit } else { dier });
+  }
+
+  fn clear_flag(&self, bit: u16) {
+    // flags are cleared by writing zeroes, ones are ignored
+    self.reg.sr.set_status(!bit);
+  }
 }
 
 impl ::hal::timer::Timer for Timer {
@@ -61,6 +137,93 @@ impl ::hal::timer::Timer for Timer {
   }
 }
 
+impl timer::AlarmTimer for Timer {
+  fn set_alarm(&self, channel: u8, ticks: u32, mode: timer::AlarmMode,
+      callback: timer::AlarmCallback) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    unsafe {
+      ALARM_PERIODS[ch] = match mode {
+        timer::AlarmMode::OneShot  => 0,
+        timer::AlarmMode::Periodic => ticks,
+      };
+      ALARM_CALLBACKS[ch] = Some(callback);
+      CAPTURE_CALLBACKS[ch] = None;
+    }
+
+    // frozen output compare, only raises the flag
+    self.set_channel_mode(ch, 0b00);
+    let target = (self.reg.cnt.counter() as u32 + ticks) & COUNTER_MASK;
+    self.compare(ch).set_cc(target);
+    self.clear_flag(0b10 << ch);
+    self.set_interrupt(ch, true);
+  }
+
+  fn cancel_alarm(&self, channel: u8) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    self.set_interrupt(ch, false);
+    unsafe {
+      ALARM_CALLBACKS[ch] = None;
+    }
+  }
+}
+
+impl timer::CaptureTimer for Timer {
+  fn enable_capture(&self, channel: u8, edge: timer::CaptureEdge,
+      callback: Option<timer::CaptureCallback>) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    unsafe {
+      ALARM_CALLBACKS[ch] = None;
+      CAPTURE_CALLBACKS[ch] = callback;
+    }
+
+    // CCxE, CCxP and CCxNP
+    let bits: u16 = match edge {
+      timer::CaptureEdge::Rising  => 0b0001,
+      timer::CaptureEdge::Falling => 0b0011,
+      timer::CaptureEdge::Both    => 0b1011,
+    };
+    let ccer: u16 = self.reg.ccer.enable() & !(0b1011 << (ch * 4));
+    self.reg.ccer.set_enable(ccer);
+    // input capture on TIx
+    self.set_channel_mode(ch, 0b01);
+    self.reg.ccer.set_enable(ccer | (bits << (ch * 4)));
+    self.clear_flag(0b10 << ch);
+    self.set_interrupt(ch, callback.is_some());
+  }
+
+  fn disable_capture(&self, channel: u8) {
+    let ch = channel as uint;
+    if ch > 3 {
+      unsafe { abort() }
+    }
+    self.set_interrupt(ch, false);
+    let ccer: u16 = self.reg.ccer.enable();
+    self.reg.ccer.set_enable(ccer & !(0b1011 << (ch * 4)));
+    unsafe {
+      CAPTURE_CALLBACKS[ch] = None;
+    }
+  }
+
+  fn capture(&self, channel: u8) -> Option<u32> {
+    let ch = channel as uint;
+    let bit = 0b10u16 << ch;
+    if self.reg.sr.status() & bit == 0 {
+      return None;
+    }
+    self.clear_flag(bit);
+    Some(self.compare(ch).cc())
+  }
+}
+
 mod reg {
   use util::volatile_cell::VolatileCell;
   use core::ops::Drop;
@@ -82,7 +245,7 @@ mod reg {
     0x08 => reg16 smcr {     // slave mode control
       15..0 => slave_control : rw,
     },
-    0x0A => reg16 dier {     // DMA/interrupt enable
+    0x0C => reg16 dier {     // DMA/interrupt enable
       15..0 => enable : rw,
     },
     0x10 => reg16 sr {       // status
@@ -91,10 +254,7 @@ mod reg {
     0x14 => reg16 egr {      // event generation
       15..0 => generate : wo,
     },
-    0x18 => reg16 ccmr1 {    // capture/compare mode 1
-      15..0 => mode : rw,
-    },
-    0x1C => reg16 ccmr2 {    // capture/compare mode 2
+    0x18 => reg32 ccmr[2] {  // capture/compare mode 1 and 2
       15..0 => mode : rw,
     },
     0x20 => reg16 ccer {     // capture/compare enable
@@ -109,16 +269,7 @@ mod reg {
     0x2C => reg32 arr {      // auto-reload
       31..0 => reload : rw,
     },
-    0x34 => reg32 ccr1 {     // capture/compare 1
-      31..0 => cc : rw,
-    },
-    0x38 => reg32 ccr2 {     // capture/compare 2
-      31..0 => cc : rw,
-    },
-    0x3C => reg32 ccr3 {     // capture/compare 3
-      31..0 => cc : rw,
-    },
-    0x40 => reg32 ccr4 {     // capture/compare 4
+    0x34 => reg32 ccr[4] {   // capture/compare 1 to 4
       31..0 => cc : rw,
     },
     0x48 => reg16 dcr {      // DMA control
@@ -19,10 +19,13 @@ Universal synchronous asynchronous receiver transmitter (USART).
 */
 
 use core::fmt;
+use core::option::Option;
+use core::option::Option::{Some, None};
 use core::result::Result;
+use core::result::Result::{Ok, Err};
 use core::intrinsics::abort;
 
-use drivers::chario::CharIO;
+use drivers::chario::{CharIO, CharInput, InputError};
 use hal::uart;
 use hal::stm32l1::init;
 
@@ -126,7 +129,53 @@ impl Usart {
 impl CharIO for Usart {
   fn putc(&self, value: char) {
     wait_for!(self.reg.sr.transmit_data_empty());
-    self.reg.dr.set_data(value as u16);
+    self.reg.dr.set_raw(value as u16);
+  }
+}
+
+impl CharInput for Usart {
+  fn readable(&self) -> bool {
+    self.reg.sr.read_data_not_empty()
+  }
+
+  fn try_getc(&self) -> Result<Option<char>, InputError> {
+    // reading SR then DR clears the error flags
+    let sr = self.reg.sr.get();
+    if !sr.read_data_not_empty() {
+      return Ok(None);
+    }
+
+    let value = self.reg.dr.data() as u8;
+    if sr.error_overrun() {
+      Err(InputError::Overrun)
+    } else if sr.error_parity() {
+      Err(InputError::Parity)
+    } else if sr.error_framing() {
+      Err(InputError::Framing)
+    } else {
+      Ok(Some(value as char))
+    }
+  }
+}
+
+impl uart::InterruptUART for Usart {
+  fn set_tx_interrupt(&self, enabled: bool) {
+    self.reg.cr1.set_int_transmission_data_empty_enable(enabled);
+  }
+
+  fn set_rx_interrupt(&self, enabled: bool) {
+    // framing and overrun errors are raised along with RXNE
+    self.reg.cr1
+      .set_int_read_data_not_empty_enable(enabled)
+      .set_int_pe_enable(enabled);
+  }
+
+  fn writable(&self) -> bool {
+    self.reg.sr.transmit_data_empty()
+  }
+
+  fn write_char(&self, value: u8) {
+    self.reg.dr.set_raw(value as u16);
   }
 }
 
@@ -135,9 +184,9 @@ impl fmt::Writer for Usart {
     use core::str::StrExt;
     for b in s.bytes() {
       wait_for!(self.reg.sr.transmit_data_empty());
-      self.reg.dr.set_data(b as u16);
+      self.reg.dr.set_raw(b as u16);
     }
-    Result::Ok(())
+    Ok(())
   }
 }
 
@@ -214,6 +263,14 @@ mod reg {
     },
   });
 
+  impl USART_dr {
+    /// Writes the register without reading it first, a read would clear the
+    /// received data.
+    pub fn set_raw(&self, value: u16) {
+      self.value.set(value);
+    }
+  }
+
   extern {
     #[link_name="stm32l1_iomem_USART1"] pub static USART1: USART;
     #[link_name="stm32l1_iomem_USART2"] pub static USART2: USART;
@@ -19,10 +19,45 @@ Timer interface.
 TimerConf is a MCU-specific struct.
 
 Timers provide a simple way to delay program execution for some time.
+
+Timers implementing `AlarmTimer` or `CaptureTimer` can also raise interrupts
+when their counter reaches a value or timestamp edges on a capture pin. The
+MCU timer's `handle_interrupt()` must then be called from its ISR to dispatch
+the callbacks.
 */
 
+use core::option::Option;
+
+use util::time::Duration;
+
 #[path="../util/wait_for.rs"] mod wait_for;
 
+/// Alarm repetition mode.
+#[derive(PartialEq, Copy)]
+pub enum AlarmMode {
+  /// The alarm fires once and is disarmed.
+  OneShot,
+  /// The alarm fires every period until cancelled.
+  Periodic,
+}
+
+/// Edges captured by an input capture channel.
+#[derive(PartialEq, Copy)]
+pub enum CaptureEdge {
+  /// Low to high transitions.
+  Rising,
+  /// High to low transitions.
+  Falling,
+  /// Any transition.
+  Both,
+}
+
+/// Called from the timer ISR with the channel of the alarm that fired.
+pub type AlarmCallback = fn(channel: u8);
+
+/// Called from the timer ISR with the channel and counter value of a capture.
+pub type CaptureCallback = fn(channel: u8, value: u32);
+
 /// Timer implementation.
 pub trait Timer {
   /// Implementation-specific method to wait a given number of microseconds.
@@ -46,4 +81,44 @@ pub trait Timer {
   fn wait(&self, s: u32) {
     self.wait_us(s * 1000000);
   }
+
+  /// Waits for the specified duration.
+  fn delay(&self, duration: Duration) {
+    // wait_us only takes 32-bit counts
+    let mut us = duration.as_us();
+    while us > MAX_WAIT_US {
+      self.wait_us(MAX_WAIT_US as u32);
+      us -= MAX_WAIT_US;
+    }
+    self.wait_us(us as u32);
+  }
+}
+
+const MAX_WAIT_US: u64 = 0x8000_0000;
+
+/// Timer with compare-match alarms.
+///
+/// Delays are counted in counter ticks, the unit of `get_counter()`.
+pub trait AlarmTimer: Timer {
+  /// Arms the alarm on compare `channel` to fire `ticks` ticks from now (and
+  /// every `ticks` ticks afterwards in periodic mode).
+  fn set_alarm(&self, channel: u8, ticks: u32, mode: AlarmMode,
+      callback: AlarmCallback);
+
+  /// Disarms the alarm on `channel`.
+  fn cancel_alarm(&self, channel: u8);
+}
+
+/// Timer with input capture.
+pub trait CaptureTimer: Timer {
+  /// Starts timestamping `edge` on capture `channel`. Captures are passed to
+  /// `callback` from the ISR if there is one, or polled with `capture()`.
+  fn enable_capture(&self, channel: u8, edge: CaptureEdge,
+      callback: Option<CaptureCallback>);
+
+  /// Stops capturing on `channel`.
+  fn disable_capture(&self, channel: u8);
+
+  /// Returns the counter value captured since the last call, if any.
+  fn capture(&self, channel: u8) -> Option<u32>;
 }
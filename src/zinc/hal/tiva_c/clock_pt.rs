@@ -22,6 +22,23 @@ use node;
 
 pub fn attach(_: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(build_clock as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+  node.schema.set(Some(clock_schema as fn() -> node::NodeSchema));
+}
+
+fn clock_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("source", node::StrAttribute,
+          node::Required, node::Unrestricted),
+      node::AttributeSchema::new("xtal", node::StrAttribute,
+          node::Optional, node::Unrestricted),
+      node::AttributeSchema::new("pll", node::BoolAttribute,
+          node::Optional, node::Unrestricted),
+      node::AttributeSchema::new("div", node::IntAttribute,
+          node::Optional, node::Unrestricted),
+    ),
+    subnodes: Some(vec!()),
+  }
 }
 
 fn build_clock(builder: &mut Builder,
@@ -52,3 +69,26 @@ fn build_clock(builder: &mut Builder,
   );
   builder.add_main_statement(cx.stmt_expr(ex));
 }
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::with_parsed;
+
+  #[test]
+  fn fails_to_build_clock_with_unit_divisor() {
+    with_parsed("
+      tiva_c@mcu {
+        clock {
+          source = \"MOSC\";
+          div = 5MHz;
+        }
+        watchdog {
+          mode = \"disabled\";
+        }
+      }", |cx, failed, pt| {
+      assert!(Builder::build(cx, pt).is_none());
+      assert!(unsafe{*failed} == true);
+    });
+  }
+}
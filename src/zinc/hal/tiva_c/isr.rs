@@ -13,7 +13,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::option::Option::{self, None};
+use core::option::Option::{self, Some, None};
+
+extern {
+  fn isr_wdt();
+}
 
 const ISRCOUNT: uint = 139;
 
@@ -38,7 +42,7 @@ pub static NVIC_VECTOR: [Option<unsafe extern fn()>; ISRCOUNT] = [
     None,                      // ADC Sequence 1
     None,                      // ADC Sequence 2
     None,                      // ADC Sequence 3
-    None,                      // Watchdog timer
+    Some(isr_wdt),             // Watchdog timer
     None,                      // Timer 0 subtimer A
     None,                      // Timer 0 subtimer B
     None,                      // Timer 1 subtimer A
@@ -18,7 +18,9 @@
 
 pub mod sysctl;
 pub mod pin;
+pub mod pwm;
 pub mod timer;
 pub mod uart;
+pub mod watchdog;
 
 #[path="../../util/ioreg.rs"] mod util;
@@ -16,8 +16,10 @@
 //! Pin configuration
 //! Allows GPIO configuration
 //! Pin muxing not implemented yet.
+//! Pin interrupts are raised through the interrupt of their port.
 
 use hal::pin::{Gpio, GpioDirection, In, Out, GpioLevel, High, Low};
+use hal::pin::{GpioInterrupt, GpioTrigger};
 use hal::tiva_c::sysctl;
 use util::support::get_reg_ref;
 
@@ -143,6 +145,42 @@ impl Gpio for Pin {
   }
 }
 
+impl GpioInterrupt for Pin {
+  fn enable_interrupt(&self, trigger: GpioTrigger) {
+    use self::reg::Port_is_is::{EDGE, LEVEL};
+    use self::reg::Port_iev_iev::{LOW_FALLING, HIGH_RISING};
+
+    let (sense, both, event) = match trigger {
+      GpioTrigger::RisingEdge  => (EDGE,  false, HIGH_RISING),
+      GpioTrigger::FallingEdge => (EDGE,  false, LOW_FALLING),
+      GpioTrigger::BothEdges   => (EDGE,  true,  LOW_FALLING),
+      GpioTrigger::LowLevel    => (LEVEL, false, LOW_FALLING),
+      GpioTrigger::HighLevel   => (LEVEL, false, HIGH_RISING),
+    };
+
+    // Mask the interrupt while reconfiguring, changing the sense could raise
+    // a spurious one
+    self.regs.im.set_ime(self.index, false);
+    self.regs.is.set_is(self.index, sense);
+    self.regs.ibe.set_ibe(self.index, both);
+    self.regs.iev.set_iev(self.index, event);
+    self.clear_interrupt();
+    self.regs.im.set_ime(self.index, true);
+  }
+
+  fn disable_interrupt(&self) {
+    self.regs.im.set_ime(self.index, false);
+  }
+
+  fn interrupt_pending(&self) -> bool {
+    self.regs.ris.ris(self.index)
+  }
+
+  fn clear_interrupt(&self) {
+    self.regs.icr.set_ic(self.index, true);
+  }
+}
+
 pub mod reg {
   //! Pin registers definition
   use util::volatile_cell::VolatileCell;
@@ -162,6 +200,47 @@ pub mod reg {
       }
     }
 
+    0x404 => reg32 is {
+      //! Interrupt sense
+      0..7 => is[8] {
+        0 => EDGE,
+        1 => LEVEL,
+      }
+    }
+
+    0x408 => reg32 ibe {
+      //! Interrupt on both edges
+      0..7 => ibe[8]
+    }
+
+    0x40C => reg32 iev {
+      //! Interrupt event
+      0..7 => iev[8] {
+        0 => LOW_FALLING,
+        1 => HIGH_RISING,
+      }
+    }
+
+    0x410 => reg32 im {
+      //! Interrupt mask
+      0..7 => ime[8]
+    }
+
+    0x414 => reg32 ris {
+      //! Raw interrupt status
+      0..7 => ris[8]: ro
+    }
+
+    0x418 => reg32 mis {
+      //! Masked interrupt status
+      0..7 => mis[8]: ro
+    }
+
+    0x41C => reg32 icr {
+      //! Interrupt clear
+      0..7 => ic[8]: wo
+    }
+
     0x420 => reg32 afsel {
       //! Pin alternate function
       0..7 => afsel[8] {
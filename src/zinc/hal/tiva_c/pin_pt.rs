@@ -26,6 +26,7 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
     add_node_dependency(&node, port_node);
     for pin_node in port_node.subnodes().iter() {
       pin_node.materializer.set(Some(build_pin as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+      pin_node.schema.set(Some(pin_schema as fn() -> node::NodeSchema));
       add_node_dependency(port_node, pin_node);
       super::add_node_dependency_on_clock(builder, pin_node);
     }
@@ -36,6 +37,18 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
+fn pin_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("direction", node::StrAttribute,
+          node::Required, node::OneOf(vec!("in", "out"))),
+      node::AttributeSchema::new("function", node::IntAttribute,
+          node::Optional, node::Unrestricted),
+    ),
+    subnodes: Some(vec!()),
+  }
+}
+
 fn get_port_id(s: &str) -> Option<char> {
     match s.len() {
         1 => match s.chars().nth(0).unwrap().to_uppercase() {
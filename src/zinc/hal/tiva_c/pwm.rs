@@ -0,0 +1,275 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PWM configuration
+//! Each PWM module has four generators counting down from a common load value,
+//! generator n drives outputs 2n (from comparator A) and 2n+1 (from comparator
+//! B), so a module has 8 channels numbered 0 to 7. Comparator and load values
+//! are updated when the counter reaches zero, at the end of the current period.
+//!
+//! Generators are 16 bit, so the PWM clock divisor is shared by both modules
+//! and picked to fit the period: creating a second module or changing its
+//! period changes the timing of the first one.
+
+use core::cell::Cell;
+use core::iter::range;
+
+use hal::tiva_c::sysctl;
+use hal::pwm;
+use util::support::get_reg_ref;
+
+/// There are 2 PWM modules
+#[allow(missing_docs)]
+#[derive(Copy)]
+pub enum PwmId {
+  Pwm0,
+  Pwm1,
+}
+
+// Generator actions on output
+const ACTION_NONE: u32 = 0;
+const ACTION_LOW:  u32 = 2;
+const ACTION_HIGH: u32 = 3;
+
+/// Structure describing a PWM module
+pub struct Pwm {
+  /// PWM register interface
+  regs:   &'static reg::Pwm,
+  /// Current period in microseconds
+  period: Cell<u32>,
+  /// Pulse widths in microseconds
+  widths: Cell<[u32; 8]>,
+}
+
+impl Pwm {
+  /// Create and start a PWM module with all outputs disabled
+  pub fn new(id: PwmId, period_us: u32) -> Pwm {
+    let (periph, regs) = match id {
+      PwmId::Pwm0 => (sysctl::periph::pwm::PWM_0, reg::PWM_0),
+      PwmId::Pwm1 => (sysctl::periph::pwm::PWM_1, reg::PWM_1),
+    };
+
+    periph.ensure_enabled();
+
+    let pwm = Pwm {
+      regs:   get_reg_ref(regs),
+      period: Cell::new(period_us),
+      widths: Cell::new([0; 8]),
+    };
+
+    for channel in range(0u, 8) {
+      pwm.regs.enable.set_en(channel, false);
+      pwm.regs.invert.set_inv(channel, false);
+    }
+
+    pwm.configure_clock(period_us);
+
+    for gen in range(0u, 4) {
+      // Count down, update comparators and load locally at zero
+      pwm.regs.gen[gen].ctl.set_enable(false).set_mode(false);
+      pwm.regs.gen[gen].load.set_load(pwm.load(period_us));
+    }
+
+    for channel in range(0u8, 8) {
+      pwm.update_channel(channel);
+    }
+
+    for gen in range(0u, 4) {
+      pwm.regs.gen[gen].ctl.set_enable(true);
+    }
+
+    pwm
+  }
+
+  /// Pick the smallest PWM clock divisor that fits the period in the 16 bit
+  /// generator counters
+  fn configure_clock(&self, period_us: u32) {
+    let sysclk_ticks = sysctl::clock::sysclk_get() as u64 * period_us as u64
+                       / 1_000_000;
+    let mut div = 1u;
+
+    while div < 64 && sysclk_ticks / div as u64 > 0x10000 {
+      div *= 2;
+    }
+
+    sysctl::clock::pwmclk_configure(div);
+  }
+
+  /// Convert a duration into PWM clock ticks
+  fn ticks(&self, us: u32) -> u32 {
+    let pwmclk = sysctl::clock::pwmclk_get() as u64;
+    let ticks = pwmclk * us as u64 / 1_000_000;
+
+    if ticks > 0x10000 {
+      panic!("PWM period is too long");
+    }
+
+    ticks as u32
+  }
+
+  /// Convert a period into the generator load value
+  fn load(&self, period_us: u32) -> u32 {
+    let ticks = self.ticks(period_us);
+
+    if ticks == 0 {
+      panic!("PWM period is too short");
+    }
+
+    ticks - 1
+  }
+
+  fn check_channel(channel: u8) {
+    if channel > 7 {
+      panic!("Invalid PWM channel");
+    }
+  }
+
+  /// Program the comparator and generator actions of a channel
+  fn update_channel(&self, channel: u8) {
+    let gen = &self.regs.gen[channel as uint / 2];
+    let load = gen.load.load();
+    let period = self.period.get();
+    let width = self.widths.get()[channel as uint];
+    let width = self.ticks(if width > period { period } else { width });
+
+    // Set high when the counter is reloaded, low when it reaches the
+    // comparator. A zero width or a full period pulse never toggles.
+    let (on_load, on_cmp, cmp) = if width == 0 {
+      (ACTION_LOW, ACTION_NONE, load)
+    } else if width > load {
+      (ACTION_HIGH, ACTION_NONE, 0)
+    } else {
+      (ACTION_HIGH, ACTION_LOW, load + 1 - width)
+    };
+
+    if channel % 2 == 0 {
+      gen.cmpa.set_cmpa(cmp);
+      gen.gena.set_actload(on_load).set_actcmpad(on_cmp);
+    } else {
+      gen.cmpb.set_cmpb(cmp);
+      gen.genb.set_actload(on_load).set_actcmpbd(on_cmp);
+    }
+  }
+}
+
+impl pwm::PWM for Pwm {
+  fn set_period_us(&self, period_us: u32) {
+    // a longer period may need a larger divisor, which changes the ticks of
+    // all the comparators
+    self.configure_clock(period_us);
+    let load = self.load(period_us);
+    self.period.set(period_us);
+
+    for gen in range(0u, 4) {
+      self.regs.gen[gen].load.set_load(load);
+    }
+
+    for channel in range(0u8, 8) {
+      self.update_channel(channel);
+    }
+  }
+
+  fn period_us(&self) -> u32 {
+    self.period.get()
+  }
+
+  fn set_pulse_width_us(&self, channel: u8, width_us: u32) {
+    Pwm::check_channel(channel);
+
+    let mut widths = self.widths.get();
+    widths[channel as uint] = width_us;
+    self.widths.set(widths);
+
+    self.update_channel(channel);
+  }
+
+  fn pulse_width_us(&self, channel: u8) -> u32 {
+    Pwm::check_channel(channel);
+    let width = self.widths.get()[channel as uint];
+    let period = self.period.get();
+    if width > period { period } else { width }
+  }
+
+  fn set_polarity(&self, channel: u8, polarity: pwm::Polarity) {
+    Pwm::check_channel(channel);
+    self.regs.invert.set_inv(channel as uint, match polarity {
+      pwm::Polarity::ActiveHigh => false,
+      pwm::Polarity::ActiveLow  => true,
+    });
+  }
+
+  fn enable(&self, channel: u8) {
+    Pwm::check_channel(channel);
+    self.regs.enable.set_en(channel as uint, true);
+  }
+
+  fn disable(&self, channel: u8) {
+    Pwm::check_channel(channel);
+    self.regs.enable.set_en(channel as uint, false);
+  }
+}
+
+pub mod reg {
+  //! PWM registers definition
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(Pwm = {
+    0x000 => reg32 ctl {
+      0 => globalsync0,    //= Update generator 0 parameters
+      1 => globalsync1,    //= Update generator 1 parameters
+      2 => globalsync2,    //= Update generator 2 parameters
+      3 => globalsync3,    //= Update generator 3 parameters
+    }
+    0x008 => reg32 enable {
+      0..7 => en[8],       //= Output enable
+    }
+    0x00C => reg32 invert {
+      0..7 => inv[8],      //= Output inversion
+    }
+    0x040 => group gen[4] {
+      0x00 => reg32 ctl {
+        0 => enable,       //= Generator enable
+        1 => mode,         //= Counter mode (0: down, 1: up/down)
+      }
+      0x10 => reg32 load {
+        0..15 => load,     //= Counter load value
+      }
+      0x14 => reg32 count {
+        0..15 => count: ro, //= Current counter value
+      }
+      0x18 => reg32 cmpa {
+        0..15 => cmpa,     //= Comparator A value
+      }
+      0x1C => reg32 cmpb {
+        0..15 => cmpb,     //= Comparator B value
+      }
+      0x20 => reg32 gena {
+        2..3   => actload,  //= Action when the counter is reloaded
+        6..7   => actcmpad, //= Action on comparator A match counting down
+      }
+      0x24 => reg32 genb {
+        2..3   => actload,  //= Action when the counter is reloaded
+        10..11 => actcmpbd, //= Action on comparator B match counting down
+      }
+      0x3C => reg32 minfltper {
+        0..15 => mfp,      //= Minimum fault period
+      }
+    }
+  });
+
+  pub const PWM_0: *const Pwm = 0x40028000 as *const Pwm;
+  pub const PWM_1: *const Pwm = 0x40029000 as *const Pwm;
+}
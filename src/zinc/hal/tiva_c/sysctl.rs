@@ -245,6 +245,41 @@ pub mod clock {
 
     div_freq / sysdiv as uint
   }
+
+  /// Configure the PWM unit clock divisor, it must be a power of two between 1
+  /// (PWM clock is sysclk) and 64.
+  pub fn pwmclk_configure(div: uint) {
+    let sysctl = super::sysctl_get();
+
+    let pwmdiv = match div {
+      1  => None,
+      2  => Some(0),
+      4  => Some(1),
+      8  => Some(2),
+      16 => Some(3),
+      32 => Some(4),
+      64 => Some(5),
+      _  => panic!("Invalid PWM clock divisor"),
+    };
+
+    match pwmdiv {
+      Some(d) => { sysctl.rcc.set_pwmdiv(d).set_usepwmdiv(true); },
+      None    => { sysctl.rcc.set_usepwmdiv(false); },
+    }
+  }
+
+  /// Retrieve the current PWM unit clock frequency
+  pub fn pwmclk_get() -> uint {
+    let rcc = super::sysctl_get().rcc.get();
+
+    match rcc.usepwmdiv() {
+      false => sysclk_get(),
+      true  => match rcc.pwmdiv() {
+        d if d < 5 => sysclk_get() >> (d as uint + 1),
+        _          => sysclk_get() / 64,
+      },
+    }
+  }
 }
 
 impl Copy for clock::ClockSource {}
@@ -310,6 +345,16 @@ pub mod periph {
     }
   }
 
+  pub mod watchdog {
+    //! Watchdog timer peripherals.
+    const CLASS: u8 = 0x0 / 4;
+
+    pub const WATCHDOG_0: super::PeripheralClock =
+      super::PeripheralClock { class: CLASS, id: 0 };
+    pub const WATCHDOG_1: super::PeripheralClock =
+      super::PeripheralClock { class: CLASS, id: 1 };
+  }
+
   pub mod gpio {
     //! GPIO system control peripherals. Split into ports of 8 GPIO each.
 
@@ -384,6 +429,16 @@ pub mod periph {
     pub const UART_7: super::PeripheralClock =
       super::PeripheralClock { class: CLASS, id: 7 };
   }
+
+  pub mod pwm {
+    //! PWM modules. Each module has four generators driving two outputs each.
+    const CLASS: u8 = 0x40 / 4;
+
+    pub const PWM_0: super::PeripheralClock =
+      super::PeripheralClock { class: CLASS, id: 0 };
+    pub const PWM_1: super::PeripheralClock =
+      super::PeripheralClock { class: CLASS, id: 1 };
+  }
 }
 
 pub mod reg {
@@ -401,6 +456,10 @@ pub mod reg {
       10 => vddaris:    ro,  //= VDDA Power OK event raw interrupt status
       11 => bor0ris:    ro,  //= VDD under BOR0 raw interrupt status
     }
+    0x05C => reg32 resc {
+      3  => wdt0,            //= Watchdog timer 0 reset
+      5  => wdt1,            //= Watchdog timer 1 reset
+    }
     0x060 => reg32 rcc {
       0      => mosdis,      //= Main oscillator disable
       4..5   => oscsrc,      //= Oscillator source
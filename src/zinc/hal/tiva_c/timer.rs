@@ -15,6 +15,11 @@
 
 //! Timer configuration
 //! This code should support both standand and wide timers
+//! Only timer A is used, so alarms and captures have a single channel, 0, and
+//! a timer can't do both at once.
+
+use core::option::Option;
+use core::option::Option::{Some, None};
 
 use hal::tiva_c::sysctl;
 use hal::timer;
@@ -67,8 +72,15 @@ pub struct Timer {
   wide    : bool,
   /// Current timer mode
   mode    : Mode,
+  /// Index in the callback tables
+  index   : u8,
 }
 
+/// Alarm periods, zero for one-shot alarms.
+static mut ALARM_PERIODS: [u32; 12] = [0; 12];
+static mut ALARM_CALLBACKS: [Option<timer::AlarmCallback>; 12] = [None; 12];
+static mut CAPTURE_CALLBACKS: [Option<timer::CaptureCallback>; 12] = [None; 12];
+
 impl Timer {
   /// Create and configure a Timer
   pub fn new(id:      TimerId,
@@ -103,7 +115,12 @@ impl Timer {
 
     periph.ensure_enabled();
 
-    let timer = Timer { regs: get_reg_ref(regs), wide: wide, mode: mode};
+    let timer = Timer {
+      regs: get_reg_ref(regs),
+      wide: wide,
+      mode: mode,
+      index: id as u8,
+    };
 
     timer.configure(prescale);
 
@@ -144,6 +161,54 @@ impl Timer {
     // Timer is now configured, we can enable it
     self.regs.ctl.set_taen(true);
   }
+
+  /// Re-arms a periodic alarm and calls the callbacks of a fired alarm or
+  /// capture. Must be called from the timer ISR.
+  pub fn handle_interrupt(&self) {
+    let index = self.index as uint;
+    let ris = self.regs.ris.get();
+
+    if ris.tamris() {
+      match unsafe { ALARM_CALLBACKS[index] } {
+        Some(callback) => {
+          self.regs.icr.set_tamcint(true);
+          let period = unsafe { ALARM_PERIODS[index] };
+          if period == 0 {
+            timer::AlarmTimer::cancel_alarm(self, 0);
+          } else {
+            // The match register holds the complement of the counter
+            let next = self.regs.tamatchr.tamr() - period;
+            self.regs.tamatchr.set_tamr(next);
+          }
+          callback(0);
+        },
+        None => (),
+      }
+    }
+
+    if ris.caeris() {
+      match unsafe { CAPTURE_CALLBACKS[index] } {
+        Some(callback) => {
+          self.regs.icr.set_caecint(true);
+          callback(0, !self.regs.tar.v());
+        },
+        None => (),
+      }
+    }
+  }
+
+  /// Switch timer A between its configured mode and edge-time capture
+  fn set_capture_mode(&self, capture: bool) {
+    self.regs.ctl.set_taen(false);
+    self.regs.amr
+      .set_mr(match (capture, self.mode) {
+        (true, _)              => reg::Timer_amr_mr::Capture,
+        (false, Mode::OneShot) => reg::Timer_amr_mr::OneShot,
+        (false, _)             => reg::Timer_amr_mr::Periodic,
+      })
+      .set_cmr(capture);
+    self.regs.ctl.set_taen(true);
+  }
 }
 
 impl timer::Timer for Timer {
@@ -156,6 +221,85 @@ impl timer::Timer for Timer {
   }
 }
 
+impl timer::AlarmTimer for Timer {
+  fn set_alarm(&self, channel: u8, ticks: u32, mode: timer::AlarmMode,
+      callback: timer::AlarmCallback) {
+    if channel != 0 {
+      panic!("Timers only have alarm channel 0");
+    }
+    let index = self.index as uint;
+    unsafe {
+      ALARM_PERIODS[index] = match mode {
+        timer::AlarmMode::OneShot  => 0,
+        timer::AlarmMode::Periodic => ticks,
+      };
+      ALARM_CALLBACKS[index] = Some(callback);
+    }
+
+    // We count down, so the match value is complemented like the counter
+    let target = timer::Timer::get_counter(self) + ticks;
+    self.regs.tamatchr.set_tamr(!target);
+    self.regs.tapmr.set_tapsmr(0);
+    self.regs.icr.set_tamcint(true);
+    self.regs.amr.set_mie(true);
+    self.regs.imr.set_tamim(true);
+  }
+
+  fn cancel_alarm(&self, channel: u8) {
+    if channel != 0 {
+      panic!("Timers only have alarm channel 0");
+    }
+    self.regs.imr.set_tamim(false);
+    self.regs.amr.set_mie(false);
+    unsafe {
+      ALARM_CALLBACKS[self.index as uint] = None;
+    }
+  }
+}
+
+impl timer::CaptureTimer for Timer {
+  fn enable_capture(&self, channel: u8, edge: timer::CaptureEdge,
+      callback: Option<timer::CaptureCallback>) {
+    if channel != 0 {
+      panic!("Timers only have capture channel 0");
+    }
+    unsafe {
+      CAPTURE_CALLBACKS[self.index as uint] = callback;
+    }
+
+    self.regs.ctl.set_taevent(match edge {
+      timer::CaptureEdge::Rising  => reg::Timer_ctl_taevent::PosEdge,
+      timer::CaptureEdge::Falling => reg::Timer_ctl_taevent::NegEdge,
+      timer::CaptureEdge::Both    => reg::Timer_ctl_taevent::AnyEdge,
+    });
+    self.set_capture_mode(true);
+    self.regs.icr.set_caecint(true);
+    self.regs.imr.set_caeim(callback.is_some());
+  }
+
+  fn disable_capture(&self, channel: u8) {
+    if channel != 0 {
+      panic!("Timers only have capture channel 0");
+    }
+    self.regs.imr.set_caeim(false);
+    self.set_capture_mode(false);
+    unsafe {
+      CAPTURE_CALLBACKS[self.index as uint] = None;
+    }
+  }
+
+  fn capture(&self, channel: u8) -> Option<u32> {
+    if channel != 0 {
+      panic!("Timers only have capture channel 0");
+    }
+    if !self.regs.ris.caeris() {
+      return None;
+    }
+    self.regs.icr.set_caecint(true);
+    Some(!self.regs.tar.v())
+  }
+}
+
 pub mod reg {
   //! Timer registers definition
   use util::volatile_cell::VolatileCell;
@@ -209,13 +353,40 @@ pub mod reg {
       13     => tbote,     //= Timer B output trigger enable
       14     => tbpwml,    //= Timer B PWM output level
     }
+    0x18 => reg32 imr {
+      0      => tatoim,    //= Timer A time-out interrupt mask
+      1      => camim,     //= Timer A capture mode match interrupt mask
+      2      => caeim,     //= Timer A capture mode event interrupt mask
+      4      => tamim,     //= Timer A match interrupt mask
+    }
+    0x1C => reg32 ris {
+      0      => tatoris: ro, //= Timer A time-out raw interrupt
+      1      => camris: ro,  //= Timer A capture mode match raw interrupt
+      2      => caeris: ro,  //= Timer A capture mode event raw interrupt
+      4      => tamris: ro,  //= Timer A match raw interrupt
+    }
+    0x24 => reg32 icr {
+      0      => tatocint: wo, //= Timer A time-out interrupt clear
+      1      => camcint: wo,  //= Timer A capture mode match interrupt clear
+      2      => caecint: wo,  //= Timer A capture mode event interrupt clear
+      4      => tamcint: wo,  //= Timer A match interrupt clear
+    }
     0x28 => reg32 tailr {
       0..31 => tailr,      //= Timer A interval load
     }
+    0x30 => reg32 tamatchr {
+      0..31 => tamr,       //= Timer A match value
+    }
     0x38 => reg32 apr {
       0..15 => psr,        //= Timer A prescale value
                            //= Only 8bit for 16/32bit timers
     }
+    0x40 => reg32 tapmr {
+      0..15 => tapsmr,     //= Timer A prescale match value
+    }
+    0x48 => reg32 tar {
+      0..31 => v: ro,      //= Timer A captured value
+    }
     0x50 => reg32 tav {
       0..31 => v,          // Timer A counter value
     }
@@ -24,6 +24,7 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(verify as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
   for timer_node in node.subnodes().iter() {
     timer_node.materializer.set(Some(build_timer as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+    timer_node.schema.set(Some(timer_schema as fn() -> node::NodeSchema));
     add_node_dependency(&node, timer_node);
     super::add_node_dependency_on_clock(builder, timer_node);
   }
@@ -33,6 +34,18 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
+fn timer_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("prescale", node::IntAttribute,
+          node::Required, node::Unrestricted),
+      node::AttributeSchema::new("mode", node::StrAttribute,
+          node::Required, node::Unrestricted),
+    ),
+    subnodes: Some(vec!()),
+  }
+}
+
 fn build_timer(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
 
   let error = | err: &str | {
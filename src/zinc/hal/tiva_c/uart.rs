@@ -15,10 +15,15 @@
 
 //! UART configuration
 
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+
 use hal::tiva_c::sysctl;
 use util::support::get_reg_ref;
 
-use drivers::chario::CharIO;
+use drivers::chario::{CharIO, CharInput, InputError};
 use hal::uart;
 
 #[path="../../util/ioreg.rs"] mod ioreg;
@@ -89,8 +94,8 @@ impl Uart {
       .set_uarten(false)
       // Enable TX
       .set_txe(true)
-      // Enable TX
-      .set_rxe(false)
+      // Enable RX
+      .set_rxe(true)
       // Disable High-Speed
       .set_hse(false);
 
@@ -130,7 +135,61 @@ impl CharIO for Uart {
 
     wait_for!(!self.regs.fr.txff());
 
-    self.regs.data.set_data(value as u32);
+    self.regs.data.set_raw(value as u32);
+  }
+}
+
+impl CharInput for Uart {
+  fn readable(&self) -> bool {
+    !self.regs.fr.rxfe()
+  }
+
+  fn try_getc(&self) -> Result<Option<char>, InputError> {
+    if self.regs.fr.rxfe() {
+      return Ok(None);
+    }
+
+    // Error flags are read along with the character they belong to
+    let data = self.regs.data.get();
+    // Error interrupts aren't cleared by the read
+    self.regs.icr
+      .set_rxic(true)
+      .set_feic(true)
+      .set_peic(true)
+      .set_beic(true)
+      .set_oeic(true);
+    if data.oe() {
+      Err(InputError::Overrun)
+    } else if data.pe() {
+      Err(InputError::Parity)
+    } else if data.fe() || data.be() {
+      Err(InputError::Framing)
+    } else {
+      Ok(Some(data.data() as u8 as char))
+    }
+  }
+}
+
+impl uart::InterruptUART for Uart {
+  fn set_tx_interrupt(&self, enabled: bool) {
+    self.regs.im.set_txim(enabled);
+  }
+
+  fn set_rx_interrupt(&self, enabled: bool) {
+    self.regs.im
+      .set_rxim(enabled)
+      .set_feim(enabled)
+      .set_peim(enabled)
+      .set_beim(enabled)
+      .set_oeim(enabled);
+  }
+
+  fn writable(&self) -> bool {
+    !self.regs.fr.txff()
+  }
+
+  fn write_char(&self, value: u8) {
+    self.regs.data.set_raw(value as u32);
   }
 }
 
@@ -141,7 +200,11 @@ pub mod reg {
 
   ioregs!(Uart = {
     0x00 => reg32 data {
-      0..11 => data,     //= RX/TX fifo data
+      0..7  => data,     //= RX/TX fifo data
+      8     => fe: ro,   //= framing error
+      9     => pe: ro,   //= parity error
+      10    => be: ro,   //= break error
+      11    => oe: ro,   //= overrun error
     }
     0x18 => reg32 fr {
       0     => ctx:  ro, //= clear-to-send signal is asserted
@@ -185,8 +248,32 @@ pub mod reg {
       14    => rtsen,    //= Enable Request-to-Send
       15    => ctsen,    //= Enable Clear-to-Send
     }
+    0x38 => reg32 im {
+      4     => rxim,     //= receive interrupt mask
+      5     => txim,     //= transmit interrupt mask
+      7     => feim,     //= framing error interrupt mask
+      8     => peim,     //= parity error interrupt mask
+      9     => beim,     //= break error interrupt mask
+      10    => oeim,     //= overrun error interrupt mask
+    }
+    0x44 => reg32 icr {
+      4     => rxic: wo, //= receive interrupt clear
+      5     => txic: wo, //= transmit interrupt clear
+      7     => feic: wo, //= framing error interrupt clear
+      8     => peic: wo, //= parity error interrupt clear
+      9     => beic: wo, //= break error interrupt clear
+      10    => oeic: wo, //= overrun error interrupt clear
+    }
   });
 
+  impl Uart_data {
+    /// Writes the register without reading it first, a read would pop the RX
+    /// FIFO.
+    pub fn set_raw(&self, value: u32) {
+      self.value.set(value);
+    }
+  }
+
   pub const UART_0: *const Uart = 0x4000C000 as *const Uart;
   pub const UART_1: *const Uart = 0x4000D000 as *const Uart;
   pub const UART_2: *const Uart = 0x4000E000 as *const Uart;
@@ -28,6 +28,7 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
     super::add_node_dependency_on_clock(builder, sub);
 
     sub.materializer.set(Some(build_uart as fn(&mut Builder, &mut ExtCtxt, Rc<node::Node>)));
+    sub.schema.set(Some(uart_schema as fn() -> node::NodeSchema));
   }
 }
 
@@ -35,6 +36,17 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
+// The baud rate is a part of the mode string.
+fn uart_schema() -> node::NodeSchema {
+  node::NodeSchema {
+    attributes: vec!(
+      node::AttributeSchema::new("mode", node::StrAttribute,
+          node::Required, node::Unrestricted),
+    ),
+    subnodes: Some(vec!()),
+  }
+}
+
 pub fn build_uart(builder: &mut Builder,
                   cx: &mut ExtCtxt,
                   sub: Rc<node::Node>) {
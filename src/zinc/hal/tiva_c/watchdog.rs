@@ -0,0 +1,135 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Lionel Flandrin <lionel@svkt.org>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watchdog timer
+//! Only watchdog 0 is used, it's clocked from the system clock so it must be
+//! configured after the clock tree.
+
+use hal::tiva_c::sysctl;
+use hal::watchdog;
+use util::support::get_reg_ref;
+
+/// Action taken when the watchdog times out.
+#[derive(Copy)]
+pub enum Action {
+  /// Reset the MCU.
+  Reset,
+  /// Raise the watchdog interrupt, `isr_wdt`. The handler must call `feed()`
+  /// to clear it.
+  Interrupt,
+}
+
+fn watchdog_get() -> &'static reg::Watchdog {
+  get_reg_ref(reg::WATCHDOG_0)
+}
+
+/// Configure and start the watchdog. Timeout is given in microseconds.
+///
+/// Once started, the watchdog can only be stopped by a reset.
+pub fn init(timeout_us: u32, action: Action) {
+  sysctl::periph::watchdog::WATCHDOG_0.ensure_enabled();
+  set_timeout(timeout_us, action);
+
+  watchdog_get().ctl
+    .set_resen(match action {
+      Action::Reset     => true,
+      Action::Interrupt => false,
+    })
+    // Enabling the interrupt starts the counter, this can't be undone
+    .set_inten(true);
+}
+
+fn set_timeout(timeout_us: u32, action: Action) {
+  let sysclk = sysctl::clock::sysclk_get() as u64;
+  let ticks = sysclk * timeout_us as u64 / 1_000_000;
+
+  let load = match action {
+    // The reset happens on the second timeout, the first one only raises the
+    // interrupt flag.
+    Action::Reset     => ticks / 2,
+    Action::Interrupt => ticks,
+  };
+
+  watchdog_get().load.set_load(
+      if load > 0xffffffff { 0xffffffff } else { load as u32 });
+}
+
+/// Feed the watchdog, reloading the counter.
+pub fn feed() {
+  // Any write to the interrupt clear register reloads the counter
+  watchdog_get().icr.set_icr(0);
+}
+
+/// The watchdog in reset mode, as a `hal::watchdog::Watchdog`.
+///
+/// `caused_reset` clears the reset cause, so it only returns true once after a
+/// watchdog reset.
+#[derive(Copy)]
+pub struct Watchdog;
+
+impl watchdog::Watchdog for Watchdog {
+  fn set_timeout(&self, timeout_us: u32) {
+    sysctl::periph::watchdog::WATCHDOG_0.ensure_enabled();
+    set_timeout(timeout_us, Action::Reset);
+  }
+
+  fn start(&self) {
+    sysctl::periph::watchdog::WATCHDOG_0.ensure_enabled();
+    watchdog_get().ctl.set_resen(true).set_inten(true);
+  }
+
+  fn feed(&self) {
+    feed();
+  }
+
+  fn caused_reset(&self) -> bool {
+    let resc = &get_reg_ref(sysctl::reg::SYSCTL).resc;
+    let caused = resc.wdt0();
+    if caused {
+      resc.set_wdt0(false);
+    }
+    caused
+  }
+}
+
+pub mod reg {
+  //! Watchdog registers definition
+  use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(Watchdog = {
+    0x000 => reg32 load {
+      0..31 => load,       //= Counter load value
+    }
+    0x004 => reg32 value {
+      0..31 => value: ro,  //= Current counter value
+    }
+    0x008 => reg32 ctl {
+      0 => inten,          //= Interrupt enable, starts the counter
+      1 => resen,          //= Reset enable
+      2 => inttype,        //= Interrupt type (0: standard, 1: NMI)
+      31 => wrc: ro,       //= Write complete (watchdog 1 only)
+    }
+    0x00C => reg32 icr {
+      0..31 => icr: wo,    //= Interrupt clear, reloads the counter
+    }
+    0x010 => reg32 ris {
+      0 => wdtris: ro,     //= Raw interrupt status
+    }
+  });
+
+  pub const WATCHDOG_0: *const Watchdog = 0x40000000 as *const Watchdog;
+  pub const WATCHDOG_1: *const Watchdog = 0x40001000 as *const Watchdog;
+}
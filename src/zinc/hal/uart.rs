@@ -18,9 +18,14 @@ UART interface.
 
 UARTConf is a MCU-specific struct.
 
-UART objects implement CharIO trait to perform actual data transmission.
+UART objects implement CharIO trait to perform actual data transmission, and
+CharInput trait to receive data. UARTs that implement InterruptUART can be
+wrapped in `drivers::buffered_uart::BufferedUART` to be serviced from their
+ISR.
 */
 
+use drivers::chario::CharInput;
+
 /// UART parity mode.
 #[derive(Copy)]
 pub enum Parity {
@@ -35,3 +40,20 @@ pub enum Parity {
   /// Partity bit forced to 0.
   Forced0,
 }
+
+/// Low-level access to a UART for interrupt-driven drivers.
+pub trait InterruptUART: CharInput {
+  /// Enables or disables the interrupt raised when a character can be
+  /// written.
+  fn set_tx_interrupt(&self, enabled: bool);
+
+  /// Enables or disables the interrupts raised when a character or a receive
+  /// error is available.
+  fn set_rx_interrupt(&self, enabled: bool);
+
+  /// Returns true if a character can be written without blocking.
+  fn writable(&self) -> bool;
+
+  /// Writes a character, the caller must check `writable()` first.
+  fn write_char(&self, value: u8);
+}
@@ -0,0 +1,38 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Watchdog interface.
+
+A watchdog resets the MCU unless it is fed regularly, so that a hung firmware
+recovers by itself. Once started, watchdogs can't be stopped except by a
+reset.
+*/
+
+/// A watchdog timer that resets the MCU on timeout.
+pub trait Watchdog {
+  /// Sets the timeout in microseconds. Timeouts outside of the range supported
+  /// by the hardware are clamped to it.
+  fn set_timeout(&self, timeout_us: u32);
+
+  /// Starts the watchdog.
+  fn start(&self);
+
+  /// Feeds the watchdog, restarting the timeout.
+  fn feed(&self);
+
+  /// Returns true if the last reset was caused by a watchdog timeout.
+  fn caused_reset(&self) -> bool;
+}
@@ -0,0 +1,144 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+System monotonic clock.
+
+The clock extends a 32-bit hardware counter to 64 bits, so that timestamps
+don't wrap around. It is driven by either:
+
+ * a `Timer` with a 32-bit counter ticking every microsecond, which gets a
+   periodic alarm to observe every overflow. The timer's `handle_interrupt()`
+   must be called from its ISR.
+ * the SysTick, in which case `handle_systick()` must be called from
+   `isr_systick`. The SysTick can't be used by the scheduler at the same time.
+
+The clock reads zero when it is started.
+*/
+
+use core::mem::transmute;
+
+use hal::cortex_m3::irq::NoInterrupts;
+use hal::cortex_m3::systick;
+use hal::timer::{Timer, AlarmTimer, AlarmMode};
+use util::time::{Duration, Instant};
+
+use self::Source::*;
+
+enum Source {
+  Stopped,
+  TimerSource(*const (Timer + 'static)),
+  SysTickSource(u32),
+}
+
+static mut SOURCE: Source = Stopped;
+/// Ticks accumulated by counter overflows.
+static mut OVERFLOWS: u64 = 0;
+/// Last timer counter value, to detect overflows.
+static mut LAST_COUNTER: u32 = 0;
+/// Timer counter value when the clock was started, which reads as zero.
+static mut START_COUNTER: u32 = 0;
+
+/// Alarm period, guarantees the counter is read at least twice per overflow.
+const TIMER_ALARM_TICKS: u32 = 0x8000_0000;
+
+/// Reload value of the SysTick, the counter is 24 bits wide.
+const SYSTICK_RELOAD: u32 = 0xff_ffff;
+
+/// Starts the clock from a timer and one of its alarm channels.
+pub fn start_timer<T: AlarmTimer + 'static>(timer: &'static T, alarm_channel: u8) {
+  let _crit = NoInterrupts::new();
+  unsafe {
+    SOURCE = TimerSource(transmute(timer as &Timer));
+    OVERFLOWS = 0;
+    LAST_COUNTER = timer.get_counter();
+    START_COUNTER = LAST_COUNTER;
+  }
+  timer.set_alarm(alarm_channel, TIMER_ALARM_TICKS, AlarmMode::Periodic,
+      on_timer_alarm);
+}
+
+/// Starts the clock from the SysTick, which is fed by the core clock running
+/// at `ticks_per_us` MHz.
+pub fn start_systick(ticks_per_us: u32) {
+  let _crit = NoInterrupts::new();
+  unsafe {
+    SOURCE = SysTickSource(ticks_per_us);
+    OVERFLOWS = 0;
+  }
+  systick::setup(SYSTICK_RELOAD);
+  // clears the overflow flag
+  systick::tick();
+  systick::enable_irq();
+  systick::enable();
+}
+
+/// Accounts for a SysTick overflow. Must be called from `isr_systick`.
+pub fn handle_systick() {
+  now();
+}
+
+fn on_timer_alarm(_: u8) {
+  now();
+}
+
+/// Returns the current time.
+pub fn now() -> Instant {
+  let _crit = NoInterrupts::new();
+  unsafe {
+    match SOURCE {
+      Stopped => Instant::from_us(0),
+      TimerSource(timer) => {
+        let counter = (*timer).get_counter();
+        if counter < LAST_COUNTER {
+          OVERFLOWS += 1 << 32;
+        }
+        LAST_COUNTER = counter;
+        Instant::from_us(OVERFLOWS + counter as u64 - START_COUNTER as u64)
+      },
+      SysTickSource(ticks_per_us) => {
+        // The flag is cleared when read, so overflows are only accounted for
+        // here, whether called from the ISR or not.
+        let mut current = systick::get_current();
+        if systick::tick() {
+          OVERFLOWS += SYSTICK_RELOAD as u64 + 1;
+          current = systick::get_current();
+        }
+        let ticks = OVERFLOWS + (SYSTICK_RELOAD - current) as u64;
+        Instant::from_us(ticks / ticks_per_us as u64)
+      },
+    }
+  }
+}
+
+/// Returns the time elapsed since `earlier`.
+pub fn elapsed(earlier: Instant) -> Duration {
+  now().duration_since(earlier)
+}
+
+/// Returns the instant `duration` from now.
+pub fn deadline(duration: Duration) -> Instant {
+  now() + duration
+}
+
+/// Returns true if `deadline` has passed.
+pub fn expired(deadline: Instant) -> bool {
+  now() >= deadline
+}
+
+/// Waits until `deadline` has passed.
+pub fn wait_until(deadline: Instant) {
+  while !expired(deadline) {}
+}
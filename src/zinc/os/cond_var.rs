@@ -62,6 +62,17 @@ mod internal {
       }
     }
 
+    /// Wait on a condition variable, leaving the critical section the
+    /// condition was checked in only once the thread is waiting, so that a
+    /// signal raised right after the check isn't lost.
+    pub fn wait_in(&self, crit: NoInterrupts) {
+      unsafe {
+        let mut waiting = Node::new(Tasks.current_task() as *mut TaskDescriptor);
+        self.waiting.push(&mut waiting, &crit);
+        Tasks.current_task().block(crit);
+      }
+    }
+
     /// Wake up a thread waiting on a condition variable.
     pub fn signal(&self) {
       unsafe {
@@ -95,7 +106,9 @@ mod internal {
   use core::kinds::marker;
   use core::kinds::Sync;
   use core::cell::UnsafeCell;
+  use core::mem::drop;
 
+  use hal::cortex_m3::irq::NoInterrupts;
   use util::support::wfi;
 
   /// A condition variable
@@ -130,6 +143,19 @@ mod internal {
       }
     }
 
+    /// Wait on a condition variable, leaving the critical section the
+    /// condition was checked in only once the thread is waiting, so that a
+    /// signal raised right after the check isn't lost.
+    pub fn wait_in(&self, crit: NoInterrupts) {
+      unsafe {
+        *self.waiting.get() = true;
+        drop(crit);
+        while *self.waiting.get() {
+          wfi();
+        }
+      }
+    }
+
     /// Wake up a thread waiting on a condition variable.
     pub fn signal(&self) {
       unsafe {
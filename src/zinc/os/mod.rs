@@ -22,7 +22,9 @@ incompatible direct hal usage in some cases.
 
 // pub mod debug;
 pub mod syscall;
+pub mod clock;
 #[cfg(cfg_multitasking)] pub mod task;
 pub mod mutex;
 pub mod cond_var;
 pub mod debug;
+pub mod storage;
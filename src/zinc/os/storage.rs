@@ -0,0 +1,822 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+Key-value storage in on-chip flash.
+
+Values of up to `MAX_VALUE_LEN` bytes are stored under 16-bit keys in a log of
+records spread over the sectors of a flash region, usually the one reserved by
+the MCU `layout.ld` (see `region`). Every update appends a new record, so the
+sectors are written and erased in turn, which levels their wear.
+
+Each record carries a CRC. A record torn by a power loss fails the check and
+is ignored along with the rest of its sector, so the previous value of the key
+is kept. One sector is always left erased: when the log reaches it, the live
+records of the oldest sector are copied forward and that sector is erased.
+*/
+
+use core::cell::Cell;
+use core::cmp::min;
+use core::iter::range;
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::result::Result;
+use core::result::Result::{Ok, Err};
+use core::slice::SliceExt;
+
+use hal::flash;
+use hal::flash::Flash;
+use util::crc::crc32;
+
+/// Storage error.
+#[derive(Copy, PartialEq)]
+pub enum Error {
+  /// The flash reported an error.
+  Flash(flash::Error),
+  /// The region doesn't cover at least two whole sectors of the same size.
+  InvalidRegion,
+  /// The key is reserved, or the value is longer than `MAX_VALUE_LEN`.
+  InvalidRecord,
+  /// There is no space left for the record.
+  Full,
+}
+
+/// Largest record, header included. Records are programmed in one go, so the
+/// flash page size must not be larger.
+const MAX_RECORD_LEN: uint = 256;
+
+/// Record header: key, value length and CRC.
+const HEADER_LEN: uint = 8;
+
+/// Sector header: sequence number and magic.
+const SECTOR_HEADER_LEN: uint = 8;
+
+/// Longest value that can be stored.
+pub const MAX_VALUE_LEN: uint = MAX_RECORD_LEN - HEADER_LEN;
+
+/// Key reserved to mark free space.
+pub const RESERVED_KEY: u16 = 0xffff;
+
+/// Value length marking a removed key.
+const REMOVED: u16 = 0xffff;
+
+/// Marks a sector in use. It follows the sequence number, so a torn header
+/// doesn't match.
+const SECTOR_MAGIC: u32 = 0x53564b5a;
+
+/// A record in a sector.
+enum Entry {
+  /// A valid record: key, value length or REMOVED, and size in flash.
+  Record(u16, u16, u32),
+  /// Erased space, the next record goes here.
+  Free,
+  /// The end of the sector, or a damaged record ending it.
+  End,
+}
+
+/// Key-value store over a flash region.
+pub struct Storage<'a, F: 'a> {
+  flash: &'a F,
+  first_sector: uint,
+  sectors: uint,
+  sector_size: u32,
+  page_size: u32,
+  /// Sector being written, relative to `first_sector`.
+  head: Cell<uint>,
+  /// Offset of the next record in the head sector.
+  offset: Cell<u32>,
+  /// Sequence number of the head sector.
+  sequence: Cell<u32>,
+}
+
+#[cfg(not(test))]
+extern {
+  static _storage_start: u32;
+  static _storage_end: u32;
+}
+
+/// Returns the start and end addresses of the flash region reserved for
+/// storage in the MCU `layout.ld`.
+#[cfg(not(test))]
+pub fn region() -> (u32, u32) {
+  ((&_storage_start as *const u32) as u32,
+   (&_storage_end as *const u32) as u32)
+}
+
+fn read_u16(buf: &[u8], offset: uint) -> u16 {
+  buf[offset] as u16 | (buf[offset + 1] as u16 << 8)
+}
+
+fn read_u32(buf: &[u8], offset: uint) -> u32 {
+  read_u16(buf, offset) as u32 | (read_u16(buf, offset + 2) as u32 << 16)
+}
+
+fn write_u16(buf: &mut [u8], offset: uint, value: u16) {
+  buf[offset] = value as u8;
+  buf[offset + 1] = (value >> 8) as u8;
+}
+
+fn write_u32(buf: &mut [u8], offset: uint, value: u32) {
+  write_u16(buf, offset, value as u16);
+  write_u16(buf, offset + 2, (value >> 16) as u16);
+}
+
+fn is_erased(buf: &[u8]) -> bool {
+  for &b in buf.iter() {
+    if b != 0xff {
+      return false;
+    }
+  }
+  true
+}
+
+impl<'a, F: Flash> Storage<'a, F> {
+  /// Opens the store in the region from `start` to `end`, recovering from an
+  /// interrupted update or garbage collection.
+  ///
+  /// A new region is formatted. Sectors that are neither in use nor erased are
+  /// erased.
+  pub fn mount(flash: &'a F, start: u32, end: u32)
+      -> Result<Storage<'a, F>, Error> {
+    let first_sector = match flash.sector_at(start) {
+      Some(sector) if flash.sector_address(sector) == start => sector,
+      _ => return Err(Error::InvalidRegion),
+    };
+    let sector_size = flash.sector_size(first_sector);
+    let page_size = flash.page_size();
+    if page_size % 4 != 0 || page_size as uint > MAX_RECORD_LEN ||
+        end <= start || (end - start) % sector_size != 0 {
+      return Err(Error::InvalidRegion);
+    }
+    let sectors = ((end - start) / sector_size) as uint;
+    if sectors < 2 || first_sector + sectors > flash.sector_count() {
+      return Err(Error::InvalidRegion);
+    }
+    for sector in range(first_sector, first_sector + sectors) {
+      if flash.sector_size(sector) != sector_size {
+        return Err(Error::InvalidRegion);
+      }
+    }
+
+    let storage = Storage {
+      flash: flash,
+      first_sector: first_sector,
+      sectors: sectors,
+      sector_size: sector_size,
+      page_size: page_size,
+      head: Cell::new(0),
+      offset: Cell::new(0),
+      sequence: Cell::new(0),
+    };
+
+    let mut head = None;
+    let mut erased = 0u;
+    for sector in range(0, sectors) {
+      match storage.sequence_of(sector) {
+        Some(sequence) => {
+          if head.is_none() || sequence > storage.sequence.get() {
+            head = Some(sector);
+            storage.sequence.set(sequence);
+          }
+        },
+        None => {
+          match flash.blank_check(first_sector + sector) {
+            Ok(()) => (),
+            Err(_) => match flash.erase_sector(first_sector + sector) {
+              Ok(()) => (),
+              Err(err) => return Err(Error::Flash(err)),
+            },
+          }
+          erased += 1;
+        },
+      }
+    }
+
+    match head {
+      Some(sector) if erased == 0 => {
+        // Garbage collection was interrupted before erasing the oldest
+        // sector, so head only holds copies of its records. Start over.
+        match flash.erase_sector(first_sector + sector) {
+          Ok(()) => (),
+          Err(err) => return Err(Error::Flash(err)),
+        }
+        let previous = (sector + sectors - 1) % sectors;
+        storage.head.set(previous);
+        storage.sequence.set(storage.sequence.get() - 1);
+        match storage.open_next() {
+          Ok(()) => (),
+          Err(err) => return Err(err),
+        }
+      },
+      Some(sector) => {
+        storage.head.set(sector);
+        storage.offset.set(storage.free_offset(sector));
+      },
+      None => {
+        // a new region, open the first sector
+        storage.head.set(sectors - 1);
+        match storage.open_next() {
+          Ok(()) => (),
+          Err(err) => return Err(err),
+        }
+      },
+    }
+
+    Ok(storage)
+  }
+
+  /// Reads the value of `key` into `value` and returns its length, or None if
+  /// the key isn't set. A value longer than `value` is truncated.
+  pub fn get(&self, key: u16, value: &mut [u8]) -> Option<uint> {
+    match self.find(key) {
+      Some((sector, offset, len)) if len != REMOVED => {
+        let len = len as uint;
+        let count = min(len, value.len());
+        self.flash.read(self.address(sector, offset) + HEADER_LEN as u32,
+            value.slice_to_mut(count));
+        Some(len)
+      },
+      _ => None,
+    }
+  }
+
+  /// Sets the value of `key`. Nothing is written if the value is unchanged.
+  pub fn set(&self, key: u16, value: &[u8]) -> Result<(), Error> {
+    if key == RESERVED_KEY || value.len() > MAX_VALUE_LEN {
+      return Err(Error::InvalidRecord);
+    }
+    match self.find(key) {
+      Some((sector, offset, len)) if len as uint == value.len() => {
+        if self.value_equals(self.address(sector, offset), value) {
+          return Ok(());
+        }
+      },
+      _ => (),
+    }
+    self.append(key, value.len() as u16, value)
+  }
+
+  /// Removes `key`.
+  pub fn remove(&self, key: u16) -> Result<(), Error> {
+    match self.find(key) {
+      Some((_, _, len)) if len != REMOVED => self.append(key, REMOVED, &[]),
+      _ => Ok(()),
+    }
+  }
+
+  fn address(&self, sector: uint, offset: u32) -> u32 {
+    self.flash.sector_address(self.first_sector + sector) + offset
+  }
+
+  /// Returns the flash size of `len` bytes, rounded up to whole pages.
+  fn slot_size(&self, len: uint) -> u32 {
+    let len = len as u32;
+    (len + self.page_size - 1) / self.page_size * self.page_size
+  }
+
+  /// Returns the sequence number of `sector` if it is in use.
+  fn sequence_of(&self, sector: uint) -> Option<u32> {
+    let mut header = [0u8; SECTOR_HEADER_LEN];
+    self.flash.read(self.address(sector, 0), &mut header);
+    if read_u32(&header, 4) == SECTOR_MAGIC {
+      Some(read_u32(&header, 0))
+    } else {
+      None
+    }
+  }
+
+  fn entry(&self, sector: uint, offset: u32) -> Entry {
+    if offset + HEADER_LEN as u32 > self.sector_size {
+      return Entry::End;
+    }
+
+    let address = self.address(sector, offset);
+    let mut header = [0u8; HEADER_LEN];
+    self.flash.read(address, &mut header);
+    if is_erased(&header) {
+      return Entry::Free;
+    }
+
+    let key = read_u16(&header, 0);
+    let len = read_u16(&header, 2);
+    let value_len = if len == REMOVED { 0 } else { len as uint };
+    if key == RESERVED_KEY || value_len > MAX_VALUE_LEN ||
+        offset + self.slot_size(HEADER_LEN + value_len) > self.sector_size {
+      return Entry::End;
+    }
+
+    let mut crc = crc32(0, header.slice_to(4));
+    let mut chunk = [0u8; 16];
+    let mut done = 0;
+    while done < value_len {
+      let count = min(chunk.len(), value_len - done);
+      self.flash.read(address + (HEADER_LEN + done) as u32,
+          chunk.slice_to_mut(count));
+      crc = crc32(crc, chunk.slice_to(count));
+      done += count;
+    }
+    if crc != read_u32(&header, 4) {
+      return Entry::End;
+    }
+
+    Entry::Record(key, len, self.slot_size(HEADER_LEN + value_len))
+  }
+
+  /// Returns the offset of the free space in `sector`, or the sector size if
+  /// it is full or damaged.
+  fn free_offset(&self, sector: uint) -> u32 {
+    let mut offset = self.slot_size(SECTOR_HEADER_LEN);
+    loop {
+      match self.entry(sector, offset) {
+        Entry::Record(_, _, size) => offset += size,
+        Entry::Free => return offset,
+        Entry::End => return self.sector_size,
+      }
+    }
+  }
+
+  /// Returns the sector, offset and length of the latest record of `key`.
+  fn find(&self, key: u16) -> Option<(uint, u32, u16)> {
+    let mut found = None;
+    let head = self.head.get();
+    // sectors are used in turn, so the oldest is the first in use after head
+    for i in range(1, self.sectors + 1) {
+      let sector = (head + i) % self.sectors;
+      if self.sequence_of(sector).is_none() {
+        continue;
+      }
+      let mut offset = self.slot_size(SECTOR_HEADER_LEN);
+      loop {
+        match self.entry(sector, offset) {
+          Entry::Record(k, len, size) => {
+            if k == key {
+              found = Some((sector, offset, len));
+            }
+            offset += size;
+          },
+          _ => break,
+        }
+      }
+    }
+    found
+  }
+
+  fn value_equals(&self, address: u32, value: &[u8]) -> bool {
+    let mut chunk = [0u8; 16];
+    let mut done = 0;
+    while done < value.len() {
+      let count = min(chunk.len(), value.len() - done);
+      self.flash.read(address + (HEADER_LEN + done) as u32,
+          chunk.slice_to_mut(count));
+      if chunk.slice_to(count) != value.slice(done, done + count) {
+        return false;
+      }
+      done += count;
+    }
+    true
+  }
+
+  fn program(&self, address: u32, data: &[u8]) -> Result<(), Error> {
+    match self.flash.program(address, data) {
+      Ok(()) => Ok(()),
+      Err(err) => Err(Error::Flash(err)),
+    }
+  }
+
+  /// Appends a record, moving on to the next sectors as needed.
+  fn append(&self, key: u16, len: u16, value: &[u8]) -> Result<(), Error> {
+    let size = self.slot_size(HEADER_LEN + value.len());
+    let mut moves = 0;
+    while self.offset.get() + size > self.sector_size {
+      // every move collects a sector, give up once all of them were
+      if moves == self.sectors - 1 {
+        return Err(Error::Full);
+      }
+      match self.open_next() {
+        Ok(()) => (),
+        Err(err) => return Err(err),
+      }
+      moves += 1;
+    }
+
+    let mut record = [0xffu8; MAX_RECORD_LEN];
+    write_u16(&mut record, 0, key);
+    write_u16(&mut record, 2, len);
+    for i in range(0, value.len()) {
+      record[HEADER_LEN + i] = value[i];
+    }
+    let crc = crc32(crc32(0, record.slice_to(4)), value);
+    write_u32(&mut record, 4, crc);
+
+    let offset = self.offset.get();
+    match self.program(self.address(self.head.get(), offset),
+        record.slice_to(size as uint)) {
+      Ok(()) => {
+        self.offset.set(offset + size);
+        Ok(())
+      },
+      Err(err) => {
+        // the slot may be partly programmed, don't write after it
+        self.offset.set(self.sector_size);
+        Err(err)
+      },
+    }
+  }
+
+  /// Starts writing to the sector after head, which is erased. If it was the
+  /// last erased one, the oldest sector is collected.
+  fn open_next(&self) -> Result<(), Error> {
+    let sector = (self.head.get() + 1) % self.sectors;
+    let sequence = self.sequence.get() + 1;
+    if self.sequence_of(sector).is_some() {
+      // a failed collection left no erased sector, remounting recovers
+      return Err(Error::Full);
+    }
+
+    let header_size = self.slot_size(SECTOR_HEADER_LEN);
+    let mut header = [0xffu8; MAX_RECORD_LEN];
+    write_u32(&mut header, 0, sequence);
+    write_u32(&mut header, 4, SECTOR_MAGIC);
+    match self.program(self.address(sector, 0),
+        header.slice_to(header_size as uint)) {
+      Ok(()) => (),
+      Err(err) => return Err(err),
+    }
+
+    self.head.set(sector);
+    self.sequence.set(sequence);
+    self.offset.set(header_size);
+
+    let oldest = (sector + 1) % self.sectors;
+    if self.sequence_of(oldest).is_some() {
+      self.collect(oldest)
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Copies the live records of `sector`, the oldest one, to head and erases
+  /// it.
+  fn collect(&self, sector: uint) -> Result<(), Error> {
+    let mut record = [0u8; MAX_RECORD_LEN];
+    let mut offset = self.slot_size(SECTOR_HEADER_LEN);
+    loop {
+      match self.entry(sector, offset) {
+        Entry::Record(key, len, size) => {
+          // removals can be dropped, there's nothing older to hide
+          let latest = match self.find(key) {
+            Some((s, o, _)) => s == sector && o == offset,
+            None => false,
+          };
+          if latest && len != REMOVED {
+            let head_offset = self.offset.get();
+            if head_offset + size > self.sector_size {
+              return Err(Error::Full);
+            }
+            let data = record.slice_to_mut(size as uint);
+            self.flash.read(self.address(sector, offset), data);
+            match self.program(self.address(self.head.get(), head_offset),
+                data) {
+              Ok(()) => self.offset.set(head_offset + size),
+              Err(err) => {
+                self.offset.set(self.sector_size);
+                return Err(err);
+              },
+            }
+          }
+          offset += size;
+        },
+        _ => break,
+      }
+    }
+
+    match self.flash.erase_sector(self.first_sector + sector) {
+      Ok(()) => Ok(()),
+      Err(err) => Err(Error::Flash(err)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use core::cell::{Cell, RefCell};
+  use core::cmp::{min, max};
+  use core::iter::range;
+  use core::option::Option;
+  use core::option::Option::{Some, None};
+  use core::result::Result;
+  use core::result::Result::{Ok, Err};
+  use core::slice::SliceExt;
+
+  use hal::flash;
+  use hal::flash::Flash;
+  use os::storage::{Storage, Error, MAX_VALUE_LEN, RESERVED_KEY};
+
+  const BASE: u32 = 0x1000;
+  const SECTOR_SIZE: u32 = 128;
+  const SECTORS: uint = 4;
+  const SIZE: uint = 512;
+
+  /// In-memory flash. Programming can only clear bits, and stops once
+  /// `budget` bytes were written to simulate a power loss.
+  struct SimFlash {
+    mem: RefCell<[u8; SIZE]>,
+    page_size: u32,
+    budget: Cell<Option<uint>>,
+    erases: RefCell<[uint; SECTORS]>,
+  }
+
+  impl SimFlash {
+    fn new(page_size: u32) -> SimFlash {
+      SimFlash {
+        mem: RefCell::new([0xff; SIZE]),
+        page_size: page_size,
+        budget: Cell::new(None),
+        erases: RefCell::new([0; SECTORS]),
+      }
+    }
+
+    /// Takes one unit of budget, returns false on power loss.
+    fn spend(&self) -> bool {
+      match self.budget.get() {
+        Some(0) => false,
+        Some(n) => {
+          self.budget.set(Some(n - 1));
+          true
+        },
+        None => true,
+      }
+    }
+  }
+
+  impl Flash for SimFlash {
+    fn sector_count(&self) -> uint { SECTORS }
+    fn sector_address(&self, sector: uint) -> u32 {
+      BASE + sector as u32 * SECTOR_SIZE
+    }
+    fn sector_size(&self, _: uint) -> u32 { SECTOR_SIZE }
+    fn page_size(&self) -> u32 { self.page_size }
+
+    fn erase_sector(&self, sector: uint) -> Result<(), flash::Error> {
+      let start = sector * SECTOR_SIZE as uint;
+      // a power loss leaves the sector half erased
+      let end = if self.spend() {
+        start + SECTOR_SIZE as uint
+      } else {
+        start + SECTOR_SIZE as uint / 2
+      };
+      let mut mem = self.mem.borrow_mut();
+      for i in range(start, end) {
+        mem[i] = 0xff;
+      }
+      if end != start + SECTOR_SIZE as uint {
+        return Err(flash::Error::Failed);
+      }
+      self.erases.borrow_mut()[sector] += 1;
+      Ok(())
+    }
+
+    fn program(&self, address: u32, data: &[u8]) -> Result<(), flash::Error> {
+      assert!(address % self.page_size == 0);
+      assert!(data.len() as u32 % self.page_size == 0);
+      let start = (address - BASE) as uint;
+      let mut mem = self.mem.borrow_mut();
+      for i in range(0, data.len()) {
+        if !self.spend() {
+          return Err(flash::Error::Failed);
+        }
+        mem[start + i] &= data[i];
+      }
+      Ok(())
+    }
+
+    fn verify(&self, address: u32, data: &[u8]) -> Result<(), flash::Error> {
+      let start = (address - BASE) as uint;
+      let mem = self.mem.borrow();
+      for i in range(0, data.len()) {
+        if mem[start + i] != data[i] {
+          return Err(flash::Error::Mismatch(address + i as u32));
+        }
+      }
+      Ok(())
+    }
+
+    fn blank_check(&self, sector: uint) -> Result<(), flash::Error> {
+      let start = sector * SECTOR_SIZE as uint;
+      let mem = self.mem.borrow();
+      for i in range(start, start + SECTOR_SIZE as uint) {
+        if mem[i] != 0xff {
+          return Err(flash::Error::Mismatch(BASE + i as u32));
+        }
+      }
+      Ok(())
+    }
+
+    fn read(&self, address: u32, data: &mut [u8]) {
+      let start = (address - BASE) as uint;
+      let mem = self.mem.borrow();
+      for i in range(0, data.len()) {
+        data[i] = mem[start + i];
+      }
+    }
+  }
+
+  fn mount<'a>(flash: &'a SimFlash) -> Storage<'a, SimFlash> {
+    match Storage::mount(flash, BASE, BASE + SECTOR_SIZE * SECTORS as u32) {
+      Ok(storage) => storage,
+      Err(_) => panic!("mount failed"),
+    }
+  }
+
+  fn has_value(storage: &Storage<SimFlash>, key: u16, value: &[u8]) -> bool {
+    let mut buf = [0u8; MAX_VALUE_LEN];
+    match storage.get(key, &mut buf) {
+      Some(len) => buf.slice_to(len) == value,
+      None => false,
+    }
+  }
+
+  #[test]
+  fn stores_and_reads_values() {
+    let flash = SimFlash::new(4);
+    let storage = mount(&flash);
+    assert!(storage.set(1, b"one") == Ok(()));
+    assert!(storage.set(2, b"two!") == Ok(()));
+    assert!(has_value(&storage, 1, b"one"));
+    assert!(has_value(&storage, 2, b"two!"));
+    let mut buf = [0u8; 4];
+    assert!(storage.get(3, &mut buf) == None);
+    // values are truncated to the buffer
+    let mut short = [0u8; 2];
+    assert!(storage.get(2, &mut short) == Some(4));
+    assert!(short[0] == b't' && short[1] == b'w');
+  }
+
+  #[test]
+  fn overwrites_and_removes_values() {
+    let flash = SimFlash::new(16);
+    let storage = mount(&flash);
+    assert!(storage.set(1, b"a") == Ok(()));
+    assert!(storage.set(1, b"bc") == Ok(()));
+    assert!(has_value(&storage, 1, b"bc"));
+    assert!(storage.remove(1) == Ok(()));
+    let mut buf = [0u8; 4];
+    assert!(storage.get(1, &mut buf) == None);
+    assert!(storage.remove(1) == Ok(()));
+    assert!(storage.set(1, b"") == Ok(()));
+    assert!(storage.get(1, &mut buf) == Some(0));
+  }
+
+  #[test]
+  fn keeps_values_across_mounts() {
+    let flash = SimFlash::new(4);
+    {
+      let storage = mount(&flash);
+      assert!(storage.set(7, b"calibration") == Ok(()));
+      assert!(storage.set(8, b"id") == Ok(()));
+      assert!(storage.remove(8) == Ok(()));
+    }
+    let storage = mount(&flash);
+    assert!(has_value(&storage, 7, b"calibration"));
+    let mut buf = [0u8; 4];
+    assert!(storage.get(8, &mut buf) == None);
+  }
+
+  #[test]
+  fn rejects_invalid_records() {
+    let flash = SimFlash::new(4);
+    let storage = mount(&flash);
+    assert!(storage.set(RESERVED_KEY, b"x") == Err(Error::InvalidRecord));
+    let long = [0u8; MAX_VALUE_LEN + 1];
+    assert!(storage.set(1, &long) == Err(Error::InvalidRecord));
+  }
+
+  #[test]
+  fn rejects_invalid_regions() {
+    let flash = SimFlash::new(4);
+    let end = BASE + SECTOR_SIZE * SECTORS as u32;
+    assert!(Storage::mount(&flash, BASE + 4, end).err() ==
+        Some(Error::InvalidRegion));
+    assert!(Storage::mount(&flash, BASE, BASE + SECTOR_SIZE).err() ==
+        Some(Error::InvalidRegion));
+    assert!(Storage::mount(&flash, BASE, end + SECTOR_SIZE).err() ==
+        Some(Error::InvalidRegion));
+  }
+
+  #[test]
+  fn skips_unchanged_values() {
+    let flash = SimFlash::new(4);
+    let storage = mount(&flash);
+    assert!(storage.set(1, b"same") == Ok(()));
+    flash.budget.set(Some(0));
+    assert!(storage.set(1, b"same") == Ok(()));
+  }
+
+  #[test]
+  fn collects_garbage_and_levels_wear() {
+    let flash = SimFlash::new(4);
+    {
+      let storage = mount(&flash);
+      assert!(storage.set(1, b"constant") == Ok(()));
+      assert!(storage.set(2, b"other") == Ok(()));
+      for i in range(0u, 200) {
+        let value = [i as u8; 10];
+        assert!(storage.set(3, &value) == Ok(()));
+        assert!(has_value(&storage, 1, b"constant"));
+        assert!(has_value(&storage, 3, &value));
+      }
+    }
+
+    let storage = mount(&flash);
+    assert!(has_value(&storage, 1, b"constant"));
+    assert!(has_value(&storage, 2, b"other"));
+    assert!(has_value(&storage, 3, &[199u8; 10]));
+
+    let erases = flash.erases.borrow();
+    let (mut least, mut most) = (erases[0], erases[0]);
+    for sector in range(1, SECTORS) {
+      least = min(least, erases[sector]);
+      most = max(most, erases[sector]);
+    }
+    assert!(least > 0);
+    assert!(most - least <= 1);
+  }
+
+  #[test]
+  fn reports_full_without_losing_data() {
+    let flash = SimFlash::new(4);
+    let storage = mount(&flash);
+    let mut key = 0u16;
+    loop {
+      let value = [key as u8; 50];
+      match storage.set(key, &value) {
+        Ok(()) => key += 1,
+        Err(err) => {
+          assert!(err == Error::Full);
+          break;
+        },
+      }
+      assert!(key < 100);
+    }
+    assert!(key > 0);
+    for k in range(0, key) {
+      assert!(has_value(&storage, k, &[k as u8; 50]));
+    }
+  }
+
+  #[test]
+  fn survives_power_loss() {
+    // cut the power after every possible number of written bytes
+    for cut in range(0u, 1500) {
+      let flash = SimFlash::new(4);
+      let mut committed: [Option<u8>; 3] = [None; 3];
+      let mut pending: Option<(uint, u8)> = None;
+      {
+        let storage = mount(&flash);
+        flash.budget.set(Some(cut));
+        for i in range(0u, 60) {
+          let key = i % 3;
+          let value = [i as u8; 12];
+          match storage.set(key as u16, &value) {
+            Ok(()) => committed[key] = Some(i as u8),
+            Err(_) => {
+              pending = Some((key, i as u8));
+              break;
+            },
+          }
+        }
+      }
+
+      flash.budget.set(None);
+      let storage = mount(&flash);
+      for key in range(0u, 3) {
+        let mut buf = [0u8; 12];
+        let value = match storage.get(key as u16, &mut buf) {
+          Some(12) => Some(buf[0]),
+          Some(_) => panic!("bad length"),
+          None => None,
+        };
+        let expected = match pending {
+          Some((k, v)) if k == key =>
+              value == committed[key] || value == Some(v),
+          _ => value == committed[key],
+        };
+        assert!(expected);
+      }
+      assert!(storage.set(0, b"after") == Ok(()));
+      assert!(has_value(&storage, 0, b"after"));
+    }
+  }
+}
@@ -0,0 +1,54 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CRC-32 checksum (IEEE 802.3, as used by zlib).
+//!
+//! The bitwise implementation is slow but doesn't need a 1KB table in flash.
+
+use core::iter::range;
+use core::slice::SliceExt;
+
+/// Updates `crc` with `data`. Start with 0, a CRC over several slices is the
+/// same as over their concatenation.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+  let mut crc = !crc;
+  for &byte in data.iter() {
+    crc ^= byte as u32;
+    for _ in range(0u, 8) {
+      crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+    }
+  }
+  !crc
+}
+
+#[cfg(test)]
+mod test {
+  use core::slice::SliceExt;
+
+  use util::crc::crc32;
+
+  #[test]
+  fn computes_check_value() {
+    assert!(crc32(0, b"123456789") == 0xCBF43926);
+    assert!(crc32(0, b"") == 0);
+  }
+
+  #[test]
+  fn updates_incrementally() {
+    let data = b"123456789";
+    let crc = crc32(crc32(0, data.slice_to(4)), data.slice_from(4));
+    assert!(crc == 0xCBF43926);
+  }
+}
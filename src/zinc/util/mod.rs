@@ -15,10 +15,13 @@
 
 //! Helper methods.
 
+pub mod crc;
 pub mod strconv;
+pub mod time;
 pub mod volatile_cell;
 pub mod support;
 pub mod shared;
+pub mod ring_buf;
 #[cfg(cfg_multitasking)] pub mod queue;
 
 mod lang_items;
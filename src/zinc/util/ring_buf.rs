@@ -0,0 +1,132 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fixed-size FIFO of bytes over a caller-provided buffer.
+
+use core::option::Option;
+use core::option::Option::{Some, None};
+use core::slice::SliceExt;
+
+/// A ring buffer of bytes.
+///
+/// One slot is always kept free to tell a full buffer from an empty one, so
+/// the buffer holds one byte less than the slice it is created with.
+pub struct RingBuf<'a> {
+  buf: &'a mut [u8],
+  head: uint,
+  tail: uint,
+}
+
+impl<'a> RingBuf<'a> {
+  /// Creates an empty ring buffer storing its data in `buf`.
+  pub fn new(buf: &'a mut [u8]) -> RingBuf<'a> {
+    RingBuf {
+      buf: buf,
+      head: 0,
+      tail: 0,
+    }
+  }
+
+  /// Returns the number of bytes the buffer can hold.
+  pub fn capacity(&self) -> uint {
+    self.buf.len() - 1
+  }
+
+  /// Returns the number of bytes in the buffer.
+  pub fn len(&self) -> uint {
+    (self.head + self.buf.len() - self.tail) % self.buf.len()
+  }
+
+  /// Returns true if there are no bytes in the buffer.
+  pub fn is_empty(&self) -> bool {
+    self.head == self.tail
+  }
+
+  /// Returns true if no more bytes can be pushed.
+  pub fn is_full(&self) -> bool {
+    self.next(self.head) == self.tail
+  }
+
+  /// Appends a byte, returns false if the buffer is full.
+  pub fn push(&mut self, value: u8) -> bool {
+    if self.is_full() {
+      return false;
+    }
+    self.buf[self.head] = value;
+    self.head = self.next(self.head);
+    true
+  }
+
+  /// Removes the oldest byte from the buffer.
+  pub fn pop(&mut self) -> Option<u8> {
+    if self.is_empty() {
+      return None;
+    }
+    let value = self.buf[self.tail];
+    self.tail = self.next(self.tail);
+    Some(value)
+  }
+
+  fn next(&self, index: uint) -> uint {
+    if index + 1 == self.buf.len() { 0 } else { index + 1 }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use core::option::Option::{Some, None};
+  use core::iter::range;
+
+  use util::ring_buf::RingBuf;
+
+  #[test]
+  fn pops_bytes_in_push_order() {
+    let mut storage = [0u8; 4];
+    let mut buf = RingBuf::new(&mut storage);
+    assert!(buf.is_empty());
+    assert!(buf.push(1));
+    assert!(buf.push(2));
+    assert!(buf.len() == 2);
+    assert!(buf.pop() == Some(1));
+    assert!(buf.pop() == Some(2));
+    assert!(buf.pop() == None);
+  }
+
+  #[test]
+  fn rejects_pushes_when_full() {
+    let mut storage = [0u8; 4];
+    let mut buf = RingBuf::new(&mut storage);
+    assert!(buf.capacity() == 3);
+    for i in range(0u8, 3) {
+      assert!(buf.push(i));
+    }
+    assert!(buf.is_full());
+    assert!(!buf.push(3));
+    assert!(buf.pop() == Some(0));
+    assert!(buf.push(3));
+  }
+
+  #[test]
+  fn wraps_around_the_end_of_storage() {
+    let mut storage = [0u8; 3];
+    let mut buf = RingBuf::new(&mut storage);
+    for i in range(0u8, 10) {
+      assert!(buf.push(i));
+      assert!(buf.len() == 1);
+      assert!(buf.pop() == Some(i));
+    }
+    assert!(buf.is_empty());
+  }
+}
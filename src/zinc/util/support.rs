@@ -15,6 +15,10 @@
 
 //! Support functions currently required by the linker for bare-metal targets.
 
+#[cfg(test)] use core::cell::Cell;
+#[cfg(test)] use core::option::Option;
+#[cfg(test)] use core::option::Option::{Some, None};
+
 #[doc(hidden)]
 #[cfg(test)]
 #[no_stack_check]
@@ -90,8 +94,24 @@ pub fn wfi() {
 }
 
 #[cfg(test)]
-/// WFI instruction (mock)
+thread_local!(static WFI_HOOK: Cell<Option<(fn(*const ()), *const ())>> =
+    Cell::new(None));
+
+#[cfg(test)]
+/// WFI instruction (mock), calls the hook set with `set_wfi_hook()` in place
+/// of the interrupt that would wake the core up
 pub fn wfi() {
+  WFI_HOOK.with(|hook| match hook.get() {
+    Some((handler, arg)) => handler(arg),
+    None => (),
+  });
+}
+
+#[cfg(test)]
+/// Sets the function the mock `wfi()` calls with the given argument on the
+/// current thread
+pub fn set_wfi_hook(hook: Option<(fn(*const ()), *const ())>) {
+  WFI_HOOK.with(|h| h.set(hook));
 }
 
 /// Hack to get a static 'ioreg' reference from a raw pointer to the register
@@ -0,0 +1,155 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Time spans and points in time, with microsecond resolution.
+
+use core::ops::{Add, Sub};
+
+/// A span of time.
+#[derive(Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration {
+  us: u64,
+}
+
+impl Duration {
+  /// Creates a duration of `us` microseconds.
+  pub fn from_us(us: u64) -> Duration {
+    Duration { us: us }
+  }
+
+  /// Creates a duration of `ms` milliseconds.
+  pub fn from_ms(ms: u64) -> Duration {
+    Duration { us: ms * 1000 }
+  }
+
+  /// Creates a duration of `s` seconds.
+  pub fn from_secs(s: u64) -> Duration {
+    Duration { us: s * 1000000 }
+  }
+
+  /// Returns the duration in microseconds.
+  pub fn as_us(&self) -> u64 {
+    self.us
+  }
+
+  /// Returns the duration in whole milliseconds.
+  pub fn as_ms(&self) -> u64 {
+    self.us / 1000
+  }
+
+  /// Returns the duration in whole seconds.
+  pub fn as_secs(&self) -> u64 {
+    self.us / 1000000
+  }
+}
+
+impl Add for Duration {
+  type Output = Duration;
+  fn add(self, other: Duration) -> Duration {
+    Duration { us: self.us + other.us }
+  }
+}
+
+impl Sub for Duration {
+  type Output = Duration;
+  /// Saturates at zero.
+  fn sub(self, other: Duration) -> Duration {
+    if other.us > self.us {
+      Duration { us: 0 }
+    } else {
+      Duration { us: self.us - other.us }
+    }
+  }
+}
+
+/// A point in time, as measured by a monotonic clock since it was started.
+#[derive(Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant {
+  us: u64,
+}
+
+impl Instant {
+  /// Creates an instant `us` microseconds after the clock started.
+  pub fn from_us(us: u64) -> Instant {
+    Instant { us: us }
+  }
+
+  /// Returns the microseconds elapsed between the clock start and the instant.
+  pub fn as_us(&self) -> u64 {
+    self.us
+  }
+
+  /// Returns the time elapsed from `earlier` to this instant, zero if
+  /// `earlier` is actually later.
+  pub fn duration_since(&self, earlier: Instant) -> Duration {
+    Duration::from_us(self.us) - Duration::from_us(earlier.us)
+  }
+}
+
+impl Add<Duration> for Instant {
+  type Output = Instant;
+  fn add(self, other: Duration) -> Instant {
+    Instant { us: self.us + other.as_us() }
+  }
+}
+
+impl Sub<Duration> for Instant {
+  type Output = Instant;
+  /// Saturates at the clock start.
+  fn sub(self, other: Duration) -> Instant {
+    Instant { us: (Duration::from_us(self.us) - other).as_us() }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use util::time::{Duration, Instant};
+
+  #[test]
+  fn converts_durations() {
+    let d = Duration::from_ms(1500);
+    assert!(d.as_us() == 1500000);
+    assert!(d.as_ms() == 1500);
+    assert!(d.as_secs() == 1);
+    assert!(Duration::from_secs(2) == Duration::from_ms(2000));
+  }
+
+  #[test]
+  fn duration_arithmetic_saturates() {
+    let a = Duration::from_us(10);
+    let b = Duration::from_us(25);
+    assert!(a + b == Duration::from_us(35));
+    assert!(b - a == Duration::from_us(15));
+    assert!(a - b == Duration::from_us(0));
+  }
+
+  #[test]
+  fn instants_are_offset_by_durations() {
+    let start = Instant::from_us(100);
+    let deadline = start + Duration::from_us(50);
+    assert!(deadline > start);
+    assert!(deadline.duration_since(start) == Duration::from_us(50));
+    assert!(start.duration_since(deadline) == Duration::from_us(0));
+    assert!(deadline - Duration::from_us(200) == Instant::from_us(0));
+  }
+
+  #[test]
+  fn time_spans_longer_than_32_bits() {
+    let start = Instant::from_us(0xffff_fff0);
+    let later = start + Duration::from_secs(3600);
+    assert!(later.as_us() == 0xffff_fff0 + 3600000000);
+    assert!(later.duration_since(start).as_secs() == 3600);
+  }
+}